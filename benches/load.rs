@@ -0,0 +1,40 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{fs, path::{Path, PathBuf}};
+use td::{crypt, parallel, task::{Frontmatter, Priority, Task, TaskStatus}};
+
+/// How many task files the generated project holds.
+const TASKS: usize = 1000;
+
+/// Writes a project of `TASKS` tasks with mixed statuses, priorities, tags
+/// and descriptions into `dir`, returning the paths of the task files.
+fn corpus(dir: &Path) -> Vec<PathBuf> {
+    fs::create_dir_all(dir).unwrap();
+    (0..TASKS).map(|i| {
+        let mut task = Task::new(&format!("Generated task number {}", i));
+        task.metadata.status = [TaskStatus::TODO, TaskStatus::DOING, TaskStatus::DONE][i % 3];
+        task.metadata.priority = [None, Some(Priority::Low), Some(Priority::Medium), Some(Priority::High)][i % 4];
+        task.metadata.tags = vec![format!("area-{}", i % 7), "generated".to_string()];
+        task.description = format!("Steps for task {}:\n\n- [ ] first\n- [x] second\n\n{}\n", i, "Some longer notes. ".repeat(20));
+        let path = dir.join(format!("generated-task-{}.{}.td", i, task.short_id()));
+        fs::write(&path, task.to_string_as(Frontmatter::Yaml).unwrap()).unwrap();
+        path
+    }).collect()
+}
+
+/// What loading a project does for each file: read it and parse the task.
+fn parse(path: &Path) -> Task {
+    Task::from_str(&crypt::read(path).unwrap()).unwrap()
+}
+
+fn load(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("td-bench-{}", std::process::id()));
+    let paths = corpus(&dir);
+    let mut group = c.benchmark_group(format!("load {} tasks", TASKS));
+    group.bench_function("sequential", |b| b.iter(|| paths.iter().map(|path| parse(path)).collect::<Vec<_>>()));
+    group.bench_function("parallel", |b| b.iter(|| parallel::map(&paths, |path| parse(path))));
+    group.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use std::{fs, path::Path, sync::atomic::{AtomicBool, Ordering}};
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::dates;
+use crate::index::load_index;
+use crate::journal;
+use crate::output;
+use crate::project::{locked, project_dir_in, td_home, with_scopes};
+use crate::task::TaskStatus;
+
+/// The directory inside a task directory that archived tasks are moved to.
+/// Hidden, so it is never taken for a sub-scope.
+pub const ARCHIVE_DIR: &str = ".archive";
+
+/// Holds the day a project was last checked for tasks to auto-archive.
+const STATE_FILE: &str = ".auto-archive";
+
+/// Set by `--no-auto-archive` to skip the check for this invocation.
+static AUTO_OFF: AtomicBool = AtomicBool::new(false);
+
+pub fn disable(off: bool) {
+    AUTO_OFF.store(off, Ordering::Relaxed);
+}
+
+/// Moves the DONE tasks of a task directory that were last changed before
+/// `cutoff` into its archive directory, returning how many were moved.
+pub fn archive_done(dir: &Path, cutoff: DateTime<Utc>) -> Result<usize> {
+    locked(dir, || {
+        let archive = dir.join(ARCHIVE_DIR);
+        let mut moved = 0;
+        for entry in load_index(dir)? {
+            let meta = &entry.metadata;
+            if meta.status != TaskStatus::DONE || meta.last_touched() >= cutoff {
+                continue;
+            }
+            let file_name = entry.path.file_name().ok_or_else(|| anyhow!("{} is not a task file", entry.path.display()))?;
+            fs::create_dir_all(&archive)?;
+            let target = archive.join(file_name);
+            fs::rename(&entry.path, &target)?;
+            journal::record(&entry.path, meta.id, &meta.title, "archived");
+            moved += 1;
+        }
+        Ok(moved)
+    })
+}
+
+/// Archives what `auto_archive_after` asks for in the current project,
+/// checking at most once a day. Failing only prints a warning, the command
+/// that was asked for runs either way.
+pub fn auto() {
+    let Some(period) = &config::get().auto_archive_after else {
+        return;
+    };
+    if AUTO_OFF.load(Ordering::Relaxed) {
+        return;
+    }
+    match auto_archive(period) {
+        Ok(0) => {}
+        Ok(moved) if !output::quiet() => eprintln!("auto-archived {} task{} (see td ls --archived)", moved, if moved == 1 { "" } else { "s" }),
+        Ok(_) => {}
+        Err(e) => eprintln!("warning: could not auto-archive: {:#}", e),
+    }
+}
+
+fn auto_archive(period: &str) -> Result<usize> {
+    let project_dir = project_dir_in(&td_home()?, config::get())?;
+    if !project_dir.exists() {
+        return Ok(0);
+    }
+    let state = project_dir.join(STATE_FILE);
+    let today = dates::today().to_string();
+    if fs::read_to_string(&state).is_ok_and(|day| day.trim() == today) {
+        return Ok(0);
+    }
+    // Written first, so a broken setting warns once a day and not on every run.
+    fs::write(&state, format!("{}\n", today))?;
+    let cutoff = dates::before(period, Utc::now())
+        .map_err(|e| anyhow!("auto_archive_after = '{}' is not a period like 30d: {}", period, e))?;
+    let mut moved = 0;
+    for dir in with_scopes(&project_dir)? {
+        moved += archive_done(&dir, cutoff)?;
+    }
+    Ok(moved)
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::commands::epic;
+use crate::dates;
+use crate::graph::Dependencies;
+use crate::milestone;
+use crate::project::{find_task, validate_parent};
+use crate::tags;
+use crate::task::{Priority, Task, TaskStatus};
+
+/// The keys `td set` accepts.
+pub const KEYS: &[&str] = &["title", "status", "priority", "due", "scheduled", "snoozed_until", "parent", "tags", "blocked_by", "commits", "branch", "epic", "milestone"];
+
+/// The fields td fills in itself, which can neither be set nor used as the
+/// name of an extra key.
+const FIXED_KEYS: &[&str] = &["id", "created_at", "updated_at"];
+
+/// The keys holding lists, which also accept `+=` and `-=`.
+const LIST_KEYS: &[&str] = &["tags", "blocked_by", "commits"];
+
+/// One parsed `key=value` assignment, with its value already validated.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Title(String),
+    Status(TaskStatus),
+    Priority(Option<Priority>),
+    Due(Option<DateTime<Utc>>),
+    Scheduled(Option<DateTime<Utc>>),
+    SnoozedUntil(Option<DateTime<Utc>>),
+    Parent(Option<Uuid>),
+    Tags(Vec<String>),
+    AddTags(Vec<String>),
+    RemoveTags(Vec<String>),
+    BlockedBy(Vec<Uuid>),
+    AddBlockedBy(Vec<Uuid>),
+    RemoveBlockedBy(Vec<Uuid>),
+    Commits(Vec<String>),
+    AddCommits(Vec<String>),
+    RemoveCommits(Vec<String>),
+    Branch(Option<String>),
+    Epic(Option<Uuid>),
+    Milestone(Option<String>),
+    /// A key td does not know, set with `extra.<name>=value`
+    Extra(String, Option<Value>),
+}
+
+/// Parses every assignment up front so a bad one aborts before any file is
+/// touched. An empty value clears an optional field, e.g. `due=`.
+pub fn parse_changes(assignments: &[String], project_dir: &Path) -> Result<Vec<Change>> {
+    assignments.iter().map(|a| parse_change(a, project_dir)).collect()
+}
+
+fn parse_change(assignment: &str, project_dir: &Path) -> Result<Change> {
+    let (key, value) = assignment.split_once('=')
+        .ok_or_else(|| anyhow!("'{}' is not an assignment, expected key=value", assignment))?;
+    let (key, op) = match key.chars().last() {
+        Some(op @ ('+' | '-')) => (key[..key.len() - 1].trim(), Some(op)),
+        _ => (key.trim(), None),
+    };
+    let value = value.trim();
+    if let Some(op) = op && !LIST_KEYS.contains(&key) {
+        return Err(anyhow!("'{}{}=' only works for {}", key, op, LIST_KEYS.join(", ")));
+    }
+    let date = |value: &str| -> Result<Option<DateTime<Utc>>> {
+        if value.is_empty() { Ok(None) } else { dates::parse_when(value).map(Some) }
+    };
+
+    if let Some(name) = key.strip_prefix("extra.") {
+        if name.is_empty() || KEYS.contains(&name) || FIXED_KEYS.contains(&name) {
+            return Err(anyhow!("'{}' can not be used as an extra key", name));
+        }
+        // Read the value as YAML so `extra.sprint=15` stays a number.
+        let value = if value.is_empty() { None } else { Some(serde_yaml::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))) };
+        return Ok(Change::Extra(name.to_string(), value));
+    }
+
+    let change = match key {
+        "title" if value.is_empty() => return Err(anyhow!("The title can not be empty")),
+        "title" => Change::Title(value.to_string()),
+        "status" => Change::Status(TaskStatus::parse(value)
+            .ok_or_else(|| anyhow!("Invalid status '{}', expected todo, doing or done", value))?.0),
+        "priority" if value.is_empty() => Change::Priority(None),
+        "priority" => Change::Priority(Some(Priority::from_str(value, true)
+            .map_err(|_| anyhow!("Invalid priority '{}', expected low, medium or high", value))?)),
+        "due" => Change::Due(date(value)?),
+        "scheduled" => Change::Scheduled(date(value)?),
+        "snoozed_until" => Change::SnoozedUntil(date(value)?),
+        "parent" if value.is_empty() => Change::Parent(None),
+        "parent" => Change::Parent(Some(find_task(project_dir, value)?.1.metadata.id)),
+        "tags" if value.is_empty() && op.is_none() => Change::Tags(Vec::new()),
+        "tags" => match op {
+            Some('+') => Change::AddTags(tags::parse_list(value)?),
+            // Removing goes by the written form too, but an invalid name can
+            // still be removed as it is.
+            Some('-') => Change::RemoveTags(list(value).iter().map(|tag| tags::normalize(tag).unwrap_or_else(|_| tag.clone())).collect()),
+            _ => Change::Tags(tags::parse_list(value)?),
+        },
+        "commits" => match op {
+            Some('+') => Change::AddCommits(commits(value)?),
+            // Like tags, a value that is not a SHA can still be removed.
+            Some('-') => Change::RemoveCommits(list(value)),
+            _ => Change::Commits(commits(value)?),
+        },
+        "branch" => Change::Branch(Some(value.to_string()).filter(|branch| !branch.is_empty())),
+        "epic" if value.is_empty() => Change::Epic(None),
+        "epic" => Change::Epic(Some(epic::find(project_dir, value)?.1.metadata.id)),
+        "milestone" if value.is_empty() => Change::Milestone(None),
+        "milestone" => Change::Milestone(Some(milestone::find(project_dir, value)?.name)),
+        "blocked_by" => {
+            let ids = list(value).iter()
+                .map(|id| find_task(project_dir, id).map(|(_, task)| task.metadata.id))
+                .collect::<Result<Vec<Uuid>>>()?;
+            match op {
+                Some('+') => Change::AddBlockedBy(ids),
+                Some('-') => Change::RemoveBlockedBy(ids),
+                _ => Change::BlockedBy(ids),
+            }
+        }
+        _ if FIXED_KEYS.contains(&key) => return Err(anyhow!("'{}' can not be changed", key)),
+        _ => return Err(anyhow!("Unknown key '{}', valid keys are: {} and extra.<name>", key, KEYS.join(", "))),
+    };
+    Ok(change)
+}
+
+fn list(value: &str) -> Vec<String> {
+    value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()
+}
+
+/// The commit SHAs of a list, full or abbreviated to at least 4 hex digits
+/// like git allows.
+fn commits(value: &str) -> Result<Vec<String>> {
+    list(value).into_iter()
+        .map(|sha| match sha.len() >= 4 && sha.len() <= 64 && sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            true => Ok(sha.to_lowercase()),
+            false => Err(anyhow!("'{}' is not a commit SHA, expected 4 to 64 hex digits", sha)),
+        })
+        .collect()
+}
+
+fn add_all<T: Clone + PartialEq>(list: &mut Vec<T>, items: &[T]) {
+    for item in items {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+/// Applies the changes to a task in memory. `tasks` is the rest of the
+/// project, used to reject parent cycles. Nothing is written here, so an
+/// error leaves the task file untouched.
+pub fn apply(task: &mut Task, changes: &[Change], tasks: &[(PathBuf, Task)]) -> Result<()> {
+    let meta = &mut task.metadata;
+    for change in changes {
+        match change {
+            Change::Title(title) => meta.title = title.clone(),
+            Change::Status(status) => meta.status = *status,
+            Change::Priority(priority) => meta.priority = *priority,
+            Change::Due(due) => meta.due = *due,
+            Change::Scheduled(scheduled) => meta.scheduled = *scheduled,
+            Change::SnoozedUntil(until) => meta.snoozed_until = *until,
+            Change::Parent(parent) => {
+                if let Some(parent) = parent {
+                    validate_parent(tasks, meta.id, *parent)?;
+                }
+                meta.parent = *parent;
+            }
+            Change::Tags(tags) => {
+                meta.tags.clear();
+                add_all(&mut meta.tags, tags);
+            }
+            Change::AddTags(tags) => add_all(&mut meta.tags, tags),
+            Change::RemoveTags(tags) => meta.tags.retain(|t| !tags.contains(t)),
+            Change::BlockedBy(ids) | Change::AddBlockedBy(ids) => {
+                if ids.contains(&meta.id) {
+                    return Err(anyhow!("{} can not block itself", meta.short_id()));
+                }
+                // The graph as it will be: the other tasks from disk, this one
+                // with the changes so far.
+                let deps = Dependencies::new(tasks.iter().map(|(_, t)| &t.metadata).filter(|other| other.id != meta.id).chain([&*meta]));
+                if let Some(chain) = ids.iter().find_map(|blocker| deps.chain(*blocker, meta.id)) {
+                    let mut cycle = vec![meta.id];
+                    cycle.extend(&chain[..chain.len() - 1]);
+                    return Err(anyhow!("Refusing to add the dependency, it would close a cycle:\n    {}", deps.describe_cycle(&cycle)));
+                }
+                if matches!(change, Change::BlockedBy(_)) {
+                    meta.blocked_by.clear();
+                }
+                add_all(&mut meta.blocked_by, ids);
+            }
+            Change::RemoveBlockedBy(ids) => meta.blocked_by.retain(|id| !ids.contains(id)),
+            Change::Commits(commits) => meta.commits = commits.clone(),
+            Change::AddCommits(commits) => add_all(&mut meta.commits, commits),
+            Change::RemoveCommits(commits) => meta.commits.retain(|c| !commits.contains(c)),
+            Change::Branch(branch) => meta.branch = branch.clone(),
+            Change::Epic(epic) if *epic == Some(meta.id) => return Err(anyhow!("An epic can not belong to itself")),
+            Change::Epic(epic) => meta.epic = *epic,
+            Change::Milestone(milestone) => meta.milestone = milestone.clone(),
+            Change::Extra(name, Some(value)) => {
+                meta.extra.insert(Value::String(name.clone()), value.clone());
+            }
+            Change::Extra(name, None) => {
+                meta.extra.remove(name.as_str());
+            }
+        }
+    }
+    meta.updated_at = Some(Utc::now());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_must_be_hex() {
+        assert_eq!(commits("ABCDEF1, 0123").unwrap(), ["abcdef1", "0123"]);
+        assert!(commits("ééééé").is_err());
+        assert!(commits("abc").is_err());
+        assert!(commits("deadbeef, main").is_err());
+    }
+
+    #[test]
+    fn statuses_take_their_synonyms() {
+        let status = |value: &str| match parse_change(&format!("status={}", value), Path::new(".")) {
+            Ok(Change::Status(status)) => Some(status),
+            _ => None,
+        };
+        assert_eq!(status("doing"), Some(TaskStatus::DOING));
+        assert_eq!(status("Done"), Some(TaskStatus::DONE));
+        assert_eq!(status("wip"), Some(TaskStatus::DOING));
+        assert_eq!(status("in-progress"), Some(TaskStatus::DOING));
+        assert_eq!(status("complete"), Some(TaskStatus::DONE));
+        assert_eq!(status("later"), None);
+    }
+}
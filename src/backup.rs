@@ -0,0 +1,489 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fs, path::{Component, Path, PathBuf}};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::archive::ARCHIVE_DIR;
+use crate::config::CONFIG_FILE;
+use crate::crypt::{self, KEY_FILE};
+use crate::events::HOOKS_DIR;
+use crate::index::load_entries;
+use crate::journal::{self, JOURNAL_FILE};
+use crate::milestone::MILESTONES_FILE;
+use crate::project::{TASK_EXTENSION, free_task_path, load_task_files, locked, project_name, resolve, save_task, td_home};
+use crate::task::{Frontmatter, Task};
+
+/// The version of the archive layout this td writes. Archives of a newer one
+/// are refused, since they may hold what this td would silently drop.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where an archive keeps the global project, the others are kept under
+/// `projects/<directory name>`.
+const GLOBAL_DIR: &str = "global";
+const PROJECTS_DIR: &str = "projects";
+
+/// When each task of a project was last carried in or out with an archive.
+/// An import only calls a task changed on a side when it was changed there
+/// after that, which tells updates from conflicts.
+const SYNC_FILE: &str = ".synced.json";
+
+/// What an archive holds, written next to the projects in it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub schema: u32,
+    pub exported_at: DateTime<Utc>,
+    #[serde(default)]
+    pub td_version: String,
+    pub projects: Vec<ArchivedProject>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedProject {
+    pub name: String,
+    /// The directory of the project in the data directory, `None` for the
+    /// global project
+    pub dir: Option<String>,
+}
+
+/// What importing an archive did, or would do with `--dry-run`.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub added: usize,
+    pub updated: usize,
+    pub identical: usize,
+    /// Tasks whose version here is newer than the archive's
+    pub kept: usize,
+    /// Tasks changed on both sides, which are left as they are here
+    pub conflicts: Vec<String>,
+}
+
+/// A directory in the temporary directory, removed again when dropped.
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new(purpose: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("td-{}-{}", purpose, std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Staging(path))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Packs the task files of the projects, with their archived tasks,
+/// sub-scopes, config, milestones and journal, into a gzipped tarball at
+/// `output`, found from the working directory. Returns how many tasks it holds.
+pub fn export(projects: &[PathBuf], output: &Path) -> Result<usize> {
+    let output = &resolve(output);
+    let home = td_home()?;
+    let now = Utc::now();
+    let mut manifest = Manifest { schema: SCHEMA_VERSION, exported_at: now, td_version: env!("CARGO_PKG_VERSION").to_string(), projects: Vec::new() };
+    let mut tasks = 0;
+    let mut exported = Vec::new();
+    let mut packed = Vec::new();
+    for project_dir in projects {
+        let global = *project_dir == home;
+        let dir = (!global).then(|| project_dir.file_name().unwrap_or_default().to_string_lossy().to_string());
+        let files = project_files(project_dir, global)?;
+        packed.extend(files.iter().map(|file| (archived_dir(dir.as_deref()).join(file), project_dir.join(file))));
+        let task_paths: Vec<PathBuf> = files.iter().filter(|file| is_task_file(file)).map(|file| project_dir.join(file)).collect();
+        tasks += task_paths.len();
+        // Encrypted projects are only ever imported whole, their tasks are
+        // never compared.
+        if !crypt::is_encrypted(project_dir) {
+            exported.push((project_dir, task_ids(&task_paths)?));
+        }
+        manifest.projects.push(ArchivedProject { name: project_name(project_dir), dir });
+    }
+
+    let file_name = output.file_name().ok_or_else(|| anyhow!("{} is not a file name", output.display()))?.to_string_lossy();
+    let temp_path = output.with_file_name(format!(".{}.tmp", file_name));
+    if let Err(e) = pack(&temp_path, &manifest, &packed) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(anyhow!("Could not write {}: {}", output.display(), e));
+    }
+    fs::rename(&temp_path, output)?;
+    for (project_dir, ids) in exported {
+        update_sync(project_dir, ids.into_iter().map(|id| (id, now)))?;
+    }
+    Ok(tasks)
+}
+
+/// Unpacks an archive into the data directory. Projects that are not there
+/// yet are copied as they are; the tasks of the others are merged by id:
+/// identical ones are skipped, ones that only changed on one side take the
+/// newer version and ones changed on both sides are reported and left alone.
+pub fn import(archive: &Path, dry_run: bool) -> Result<Report> {
+    let archive = &resolve(archive);
+    let home = td_home()?;
+    let staging = Staging::new("import")?;
+    unpack(archive, &staging.0).map_err(|e| anyhow!("Could not unpack {}: {:#}", archive.display(), e))?;
+    let manifest: Manifest = match fs::read_to_string(staging.0.join(MANIFEST_FILE)) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| anyhow!("Could not read the manifest of {}: {}", archive.display(), e))?,
+        Err(_) => return Err(anyhow!("{} is not an archive written by `td export --archive`, it has no {}", archive.display(), MANIFEST_FILE)),
+    };
+    if manifest.schema > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "{} was written by a newer td (archive schema {}, this td reads up to {}), update td to import it",
+            archive.display(), manifest.schema, SCHEMA_VERSION,
+        ));
+    }
+
+    // Everything that refuses the archive is checked before anything is written.
+    let mut projects = Vec::new();
+    for project in &manifest.projects {
+        let from = staging.0.join(archived_dir(project.dir.as_deref()));
+        let target = match &project.dir {
+            Some(dir) if dir.is_empty() || dir.starts_with('.') || dir.contains(['/', '\\']) || dir == HOOKS_DIR => {
+                return Err(anyhow!("The archive names '{}' as a project directory, refusing to import it", dir));
+            }
+            Some(dir) => home.join(dir),
+            None => home.clone(),
+        };
+        if target.exists() && (from.join(KEY_FILE).exists() || crypt::is_encrypted(&target)) {
+            return Err(anyhow!(
+                "{} is encrypted here or in the archive, encrypted projects can only be imported where they do not exist yet; run `td encrypt --disable` on both sides to merge them",
+                project.name,
+            ));
+        }
+        let files = project_files(&from, project.dir.is_none())?;
+        projects.push((project, from, target, files));
+    }
+
+    let source = archive.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut report = Report::default();
+    for (project, from, target, files) in projects {
+        if target.exists() {
+            locked(&target, || merge(&from, &target, &files, &project.name, &source, dry_run, &mut report))?;
+            continue;
+        }
+        let tasks: Vec<PathBuf> = files.iter().filter(|file| is_task_file(file)).map(|file| target.join(file)).collect();
+        report.added += tasks.len();
+        if dry_run {
+            println!("Would add the project {} with {} task(s)", project.name, tasks.len());
+            continue;
+        }
+        copy_files(&from, &target, &files)?;
+        if !crypt::is_encrypted(&target) {
+            let now = Utc::now();
+            update_sync(&target, task_ids(&tasks)?.into_iter().map(|id| (id, now)))?;
+        }
+    }
+    Ok(report)
+}
+
+/// What a task on one side of an import became.
+enum Outcome {
+    Identical,
+    Update,
+    Kept,
+    /// Changed on both sides since they were synced at this time
+    Conflict(DateTime<Utc>),
+}
+
+fn merge(from: &Path, target: &Path, files: &[PathBuf], name: &str, source: &str, dry_run: bool, report: &mut Report) -> Result<()> {
+    let mut local: HashMap<Uuid, (PathBuf, Task)> = HashMap::new();
+    for (path, task) in load_task_files(local_task_files(target)?)? {
+        local.insert(task.metadata.id, (path, task));
+    }
+    // Tasks that can not be read here, like those with an unknown status,
+    // are neither overwritten nor added a second time.
+    let mut unreadable = HashSet::new();
+    for dir in task_dirs(target)? {
+        unreadable.extend(load_entries(&dir)?.into_iter().filter(|entry| entry.problem.is_some()).map(|entry| entry.metadata.id));
+    }
+    let synced = read_sync(target)?;
+    let now = Utc::now();
+    let mut seen = Vec::new();
+
+    for file in files.iter().filter(|file| is_task_file(file)) {
+        let content = fs::read_to_string(from.join(file))?;
+        let task = match Task::from_str(&content) {
+            Ok(task) => task,
+            Err(e) => {
+                eprintln!("warning: skipping {} from the archive: {}", file.display(), e);
+                continue;
+            }
+        };
+        let id = task.metadata.id;
+        if unreadable.contains(&id) {
+            eprintln!("warning: skipping {} {}, the task can not be read here", task.short_id(), task.metadata.title);
+            continue;
+        }
+        let Some((path, existing)) = local.get(&id) else {
+            report.added += 1;
+            if dry_run {
+                println!("Would add {} {} to {}", task.short_id(), task.metadata.title, name);
+                continue;
+            }
+            let dir = target.join(file.parent().unwrap_or(Path::new("")));
+            fs::create_dir_all(&dir)?;
+            let path = free_task_path(&dir, &task, None);
+            let temp_path = path.with_file_name(format!(".{}.tmp", path.file_name().unwrap_or_default().to_string_lossy()));
+            fs::write(&temp_path, &content)?;
+            fs::rename(&temp_path, &path)?;
+            journal::record(&path, id, &task.metadata.title, &format!("imported from {}", source));
+            seen.push((id, now));
+            continue;
+        };
+        match outcome(existing, &task, synced.get(&id).copied()) {
+            Outcome::Identical => {
+                report.identical += 1;
+                seen.push((id, now));
+            }
+            Outcome::Kept => report.kept += 1,
+            Outcome::Conflict(since) => {
+                report.conflicts.push(format!("{} {} in {}: changed here and in the archive since {}", task.short_id(), existing.metadata.title, name, since.format("%Y-%m-%d %H:%M")));
+            }
+            Outcome::Update => {
+                report.updated += 1;
+                if dry_run {
+                    println!("Would update {} {} in {}", task.short_id(), task.metadata.title, name);
+                    continue;
+                }
+                save_task(path, &task)?;
+                seen.push((id, now));
+            }
+        }
+    }
+
+    for file in files.iter().filter(|file| !is_task_file(file) && !file.starts_with(JOURNAL_FILE) && file.as_path() != Path::new(KEY_FILE)) {
+        let path = target.join(file);
+        if !path.exists() {
+            match dry_run {
+                true => println!("Would copy {} to {}", file.display(), name),
+                false => copy_files(from, target, std::slice::from_ref(file))?,
+            }
+        } else if fs::read(&path)? != fs::read(from.join(file))? {
+            println!("Kept {} of {}, the archive has a different one", file.display(), name);
+        }
+    }
+    if !dry_run {
+        update_sync(target, seen)?;
+    }
+    Ok(())
+}
+
+/// Compares the task here with the archive's. A side changed when its task
+/// was touched after the two were last synced, without a sync the newer one
+/// wins.
+fn outcome(local: &Task, archived: &Task, synced: Option<DateTime<Utc>>) -> Outcome {
+    let same = local.to_string_as(Frontmatter::Yaml).ok() == archived.to_string_as(Frontmatter::Yaml).ok();
+    let (here, there) = (local.metadata.last_touched(), archived.metadata.last_touched());
+    match synced {
+        _ if same => Outcome::Identical,
+        Some(synced) if there <= synced => Outcome::Kept,
+        Some(synced) if here <= synced => Outcome::Update,
+        Some(synced) => Outcome::Conflict(synced),
+        None if there > here => Outcome::Update,
+        None => Outcome::Kept,
+    }
+}
+
+fn archived_dir(dir: Option<&str>) -> PathBuf {
+    match dir {
+        Some(dir) => Path::new(PROJECTS_DIR).join(dir),
+        None => PathBuf::from(GLOBAL_DIR),
+    }
+}
+
+fn is_task_file(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION)
+}
+
+/// The files of a project that go into an archive, relative to its
+/// directory: the tasks of the project, its sub-scopes and their archives,
+/// with the journal, milestones, encryption settings and, unless it is the
+/// global one whose config is everyone's, its config. Caches and state are
+/// left out.
+fn project_files(project_dir: &Path, global: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in sorted_entries(project_dir)? {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if entry.is_dir() {
+            let is_scope = !global && !name.starts_with('.');
+            if name == ARCHIVE_DIR || is_scope {
+                for inner in sorted_entries(&entry)? {
+                    let inner_name = inner.file_name().unwrap_or_default();
+                    if inner.is_dir() && is_scope && inner_name == ARCHIVE_DIR {
+                        files.extend(sorted_entries(&inner)?.iter().filter(|path| is_task_file(path)).map(|path| Path::new(&name).join(inner_name).join(path.file_name().unwrap_or_default())));
+                    } else if is_task_file(&inner) {
+                        files.push(Path::new(&name).join(inner_name));
+                    }
+                }
+            }
+        } else if is_task_file(&entry)
+            || name.starts_with(JOURNAL_FILE)
+            || [MILESTONES_FILE, KEY_FILE].contains(&name.as_str())
+            || (!global && name == CONFIG_FILE)
+        {
+            files.push(PathBuf::from(name));
+        }
+    }
+    Ok(files)
+}
+
+/// The entries of a directory in a fixed order, none when it does not exist.
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries.map(|entry| entry.map(|e| e.path())).collect::<std::io::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    paths.sort();
+    Ok(paths)
+}
+
+fn copy_files(from: &Path, to: &Path, files: &[PathBuf]) -> Result<()> {
+    for file in files {
+        let target = to.join(file);
+        fs::create_dir_all(target.parent().unwrap_or(to))?;
+        let temp_path = target.with_file_name(format!(".{}.tmp", target.file_name().unwrap_or_default().to_string_lossy()));
+        fs::copy(from.join(file), &temp_path)?;
+        fs::rename(&temp_path, &target)?;
+    }
+    Ok(())
+}
+
+/// Every directory of a project that holds task files.
+fn task_dirs(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let global = project_dir == td_home()?;
+    let mut dirs = vec![project_dir.to_path_buf()];
+    dirs.extend(project_files(project_dir, global)?.iter().filter_map(|file| file.parent()).filter(|dir| !dir.as_os_str().is_empty()).map(|dir| project_dir.join(dir)));
+    dirs.dedup();
+    Ok(dirs)
+}
+
+fn local_task_files(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let global = project_dir == td_home()?;
+    Ok(project_files(project_dir, global)?.into_iter().filter(|file| is_task_file(file)).map(|file| project_dir.join(file)).collect())
+}
+
+fn task_ids(paths: &[PathBuf]) -> Result<Vec<Uuid>> {
+    Ok(load_task_files(paths.to_vec())?.into_iter().map(|(_, task)| task.metadata.id).collect())
+}
+
+fn read_sync(project_dir: &Path) -> Result<BTreeMap<Uuid, DateTime<Utc>>> {
+    match fs::read_to_string(project_dir.join(SYNC_FILE)) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| anyhow!("Could not read {}: {}", project_dir.join(SYNC_FILE).display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn update_sync(project_dir: &Path, synced: impl IntoIterator<Item = (Uuid, DateTime<Utc>)>) -> Result<()> {
+    let mut state = read_sync(project_dir)?;
+    state.extend(synced);
+    let path = project_dir.join(SYNC_FILE);
+    let temp_path = path.with_file_name(format!("{}.tmp", SYNC_FILE));
+    fs::write(&temp_path, serde_json::to_string(&state)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Writes the manifest and the files, each a name in the archive and the
+/// path it is read from, as a gzipped tarball.
+fn pack(path: &Path, manifest: &Manifest, files: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let mut builder = tar::Builder::new(GzEncoder::new(fs::File::create(path)?, Compression::default()));
+    let manifest = serde_json::to_string_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, manifest.as_bytes())?;
+    for (name, source) in files {
+        builder.append_path_with_name(source, name)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Extracts a gzipped tarball into `dir`. Only files and directories below
+/// it are accepted, an entry with an absolute path, a `..` or a link refuses
+/// the whole archive.
+fn unpack(archive: &Path, dir: &Path) -> Result<()> {
+    let mut tarball = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+    for entry in tarball.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !path.components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir)) {
+            return Err(anyhow!("it holds '{}', which points outside the archive", path.display()));
+        }
+        let target = dir.join(&path);
+        match entry.header().entry_type() {
+            tar::EntryType::Regular => {
+                fs::create_dir_all(target.parent().unwrap_or(dir))?;
+                entry.unpack(&target)?;
+            }
+            tar::EntryType::Directory => fs::create_dir_all(&target)?,
+            kind => return Err(anyhow!("it holds '{}' of type {:?}, only files and directories can be imported", path.display(), kind)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tarball with one file stored under `name` as it is, bypassing the
+    /// checks `tar::Header::set_path` makes.
+    fn tarball_with(path: &Path, name: &str) {
+        let mut builder = tar::Builder::new(GzEncoder::new(fs::File::create(path).unwrap(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(2);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"hi"[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn packed_files_unpack_under_their_names() {
+        let staging = Staging::new("test-pack").unwrap();
+        fs::write(staging.0.join("a.md"), "task").unwrap();
+        let manifest = Manifest { schema: SCHEMA_VERSION, exported_at: Utc::now(), td_version: String::new(), projects: Vec::new() };
+        let archive = staging.0.join("out.tar.gz");
+        pack(&archive, &manifest, &[(PathBuf::from("projects/p/a.md"), staging.0.join("a.md"))]).unwrap();
+
+        let into = staging.0.join("into");
+        unpack(&archive, &into).unwrap();
+        assert_eq!(fs::read_to_string(into.join("projects/p/a.md")).unwrap(), "task");
+        assert!(into.join(MANIFEST_FILE).exists());
+    }
+
+    #[test]
+    fn entries_outside_the_archive_are_refused() {
+        let staging = Staging::new("test-escape").unwrap();
+        for name in ["../escaped.md", "projects/../../escaped.md", "/tmp/escaped.md"] {
+            let archive = staging.0.join("bad.tar.gz");
+            tarball_with(&archive, name);
+            let error = unpack(&archive, &staging.0.join("into")).unwrap_err().to_string();
+            assert!(error.contains("points outside the archive"), "{}: {}", name, error);
+        }
+        assert!(!staging.0.join("escaped.md").exists());
+    }
+
+    #[test]
+    fn archives_are_found_from_the_working_dir() {
+        let staging = Staging::new("test-relative").unwrap();
+        crate::project::in_working_dir(&staging.0, || {
+            export(&[], Path::new("moved.tar.gz")).unwrap();
+            assert!(staging.0.join("moved.tar.gz").is_file());
+            assert_eq!(import(Path::new("moved.tar.gz"), true).unwrap().added, 0);
+        });
+    }
+}
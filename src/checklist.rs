@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// A markdown checkbox (`- [ ]` or `- [x]`) found in a task description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecklistItem {
+    pub done: bool,
+    pub text: String,
+}
+
+/// How many of the checkboxes in a description are ticked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Finds the checkboxes in a markdown description, skipping anything inside
+/// fenced code blocks.
+pub fn parse(description: &str) -> Vec<ChecklistItem> {
+    let mut items = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in description.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        let Some(rest) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")).or_else(|| trimmed.strip_prefix("+ ")) else {
+            continue;
+        };
+        let done = match rest.get(..3) {
+            Some("[ ]") => false,
+            Some("[x]") | Some("[X]") => true,
+            _ => continue,
+        };
+        items.push(ChecklistItem { done, text: rest[3..].trim().to_string() });
+    }
+    items
+}
+
+pub fn progress(description: &str) -> Progress {
+    let items = parse(description);
+    Progress {
+        done: items.iter().filter(|item| item.done).count(),
+        total: items.len(),
+    }
+}
+
+impl Progress {
+    /// `3/7`, or an empty string when there is no checklist.
+    pub fn fraction(&self) -> String {
+        if self.total == 0 { String::new() } else { format!("{}/{}", self.done, self.total) }
+    }
+}
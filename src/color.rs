@@ -0,0 +1,137 @@
+use std::{env, io::IsTerminal, sync::atomic::{AtomicBool, Ordering}};
+
+use crate::config;
+use crate::tags;
+use crate::task::TaskStatus;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns color on when stdout is a terminal, unless `--no-color` was given or
+/// `NO_COLOR` is set.
+pub fn init(no_color: bool) {
+    let enabled = !no_color && env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 basic terminal colors, as its SGR foreground code
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+    Dim,
+    Bold,
+}
+
+/// The basic colors handed out to tags that have no configured color.
+const TAG_PALETTE: [u8; 12] = [31, 32, 33, 34, 35, 36, 91, 92, 93, 94, 95, 96];
+
+impl Color {
+    /// Parses a color name like `red`, `bright_blue` or `dim`, or a `#rrggbb` hex value.
+    pub fn parse(name: &str) -> Option<Color> {
+        let name = name.trim().to_lowercase();
+        if let Some(hex) = name.strip_prefix('#') {
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        let (bright, base) = match name.strip_prefix("bright_").or_else(|| name.strip_prefix("bright-")) {
+            Some(base) => (true, base),
+            None => (false, name.as_str()),
+        };
+        let code = match base {
+            "black" => 30,
+            "red" => 31,
+            "green" => 32,
+            "yellow" => 33,
+            "blue" => 34,
+            "magenta" | "purple" => 35,
+            "cyan" => 36,
+            "white" => 37,
+            "gray" | "grey" if !bright => 90,
+            "dim" if !bright => return Some(Color::Dim),
+            "bold" if !bright => return Some(Color::Bold),
+            _ => return None,
+        };
+        Some(Color::Ansi(if bright && code < 90 { code + 60 } else { code }))
+    }
+
+    fn sgr(&self) -> String {
+        match *self {
+            Color::Ansi(code) => code.to_string(),
+            Color::Dim => "2".to_string(),
+            Color::Bold => "1".to_string(),
+            Color::Rgb(r, g, b) if truecolor() => format!("38;2;{};{};{}", r, g, b),
+            Color::Rgb(r, g, b) => {
+                // Closest entry of the 6x6x6 cube in the 256 color palette.
+                let level = |c: u8| (c as u16 * 5 + 127) / 255;
+                format!("38;5;{}", 16 + 36 * level(r) + 6 * level(g) + level(b))
+            }
+        }
+    }
+}
+
+fn truecolor() -> bool {
+    matches!(env::var("COLORTERM").as_deref(), Ok("truecolor") | Ok("24bit"))
+}
+
+/// Wraps `text` in the escape codes for `color` when color is enabled.
+pub fn paint(text: &str, color: Color) -> String {
+    if !enabled() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", color.sgr(), text)
+}
+
+pub fn status_color(status: TaskStatus) -> Option<Color> {
+    match status {
+        TaskStatus::TODO => None,
+        TaskStatus::DOING => Some(Color::Ansi(33)),
+        TaskStatus::DONE => Some(Color::Ansi(32)),
+    }
+}
+
+/// Colors `text` (usually the padded status) by the status of the task.
+pub fn status(text: &str, status: TaskStatus) -> String {
+    match status_color(status) {
+        Some(color) => paint(text, color),
+        None => text.to_string(),
+    }
+}
+
+/// The color for a tag: the one configured in `[tag_colors]` for it or the
+/// closest tag above it, like `area` for `area/backend`, or one derived from
+/// the name so every tag keeps the same color between runs.
+pub fn tag_color(tag: &str) -> Color {
+    let mut level = Some(tag);
+    while let Some(name) = level {
+        if let Some(color) = config::get().tag_colors.get(name).and_then(|name| Color::parse(name)) {
+            return color;
+        }
+        level = tags::parent(name);
+    }
+    // FNV-1a, which unlike the std hasher is stable across releases.
+    let hash = tag.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    Color::Ansi(TAG_PALETTE[(hash % TAG_PALETTE.len() as u64) as usize])
+}
+
+pub fn tag(tag: &str) -> String {
+    paint(tag, tag_color(tag))
+}
+
+/// `text` in the color of `tag`, for showing part of a hierarchical tag.
+pub fn tag_as(text: &str, tag: &str) -> String {
+    paint(text, tag_color(tag))
+}
+
+/// A comma separated list of colored tags.
+pub fn tags(tags: &[String]) -> String {
+    tags.iter().map(|t| tag(t)).collect::<Vec<_>>().join(", ")
+}
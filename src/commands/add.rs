@@ -0,0 +1,269 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow};
+
+use crate::commands::{epic, link_url, show};
+use crate::config;
+use crate::dates;
+use crate::events::{self, Event, EventKind};
+use crate::milestone;
+use crate::project::{current_branch, find_task, open_repo, get_project_path, load_scoped_tasks, load_tasks, resolve, save_task, task_path, validate_parent};
+use crate::say;
+use crate::tags;
+use crate::task::{Priority, Task, TaskStatus};
+
+/// The share of words two titles need in common to count as similar.
+const SIMILAR_WORDS: f64 = 0.6;
+
+#[derive(Args, Debug)]
+pub struct AddArgs {
+    /// The title of the task
+    #[arg(required_unless_present = "from_file", conflicts_with = "from_file")]
+    title: Option<String>,
+    /// Add a task for every line of this file, `-` for stdin. Empty lines and
+    /// those starting with `#` are skipped, trailing `#words` become tags
+    #[arg(long, value_name = "FILE", conflicts_with = "desc")]
+    from_file: Option<PathBuf>,
+    /// A description of the task
+    #[arg(long, short)]
+    desc: Option<String>,
+    /// A tag for the task (can be repeated, or list several separated by commas)
+    #[arg(long = "tag", short, visible_alias = "tags", value_name = "TAG")]
+    tags: Vec<String>,
+    /// The priority of the task
+    #[arg(long, short)]
+    priority: Option<Priority>,
+    /// When the task is due, e.g. 2024-06-01, tomorrow or "next friday"
+    #[arg(long, value_parser = dates::parse_when)]
+    due: Option<DateTime<Utc>>,
+    /// When you intend to start working on the task
+    #[arg(long, value_parser = dates::parse_when)]
+    scheduled: Option<DateTime<Utc>>,
+    /// The id of the task this is a subtask of
+    #[arg(long)]
+    parent: Option<String>,
+    /// Add the task without asking when a similar one already exists
+    #[arg(long, short)]
+    force: bool,
+    /// The id of the epic the task belongs to
+    #[arg(long)]
+    epic: Option<String>,
+    /// A URL the task is about, like its pull request (can be repeated)
+    #[arg(long, value_name = "URL")]
+    link: Vec<String>,
+    /// The name of the milestone the task is planned for
+    #[arg(long)]
+    milestone: Option<String>,
+    /// File the task under the current git branch
+    #[arg(long)]
+    here: bool,
+}
+
+pub fn run(args: &AddArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let Some(file) = &args.from_file else {
+        let title = args.title.as_deref().expect("clap requires a title without --from-file");
+        return add(&project_dir, args, title, Vec::new(), args.force);
+    };
+    let content = read_list(file)?;
+    let lines: Vec<&str> = content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    if lines.is_empty() {
+        say!("Nothing to add");
+        return Ok(());
+    }
+    // Asking about each similar title would not work with stdin, and is too
+    // much for a list; the warnings are still printed.
+    for line in lines {
+        let (title, tags) = inline_tags(line);
+        add(&project_dir, args, &title, tags, true)?;
+    }
+    Ok(())
+}
+
+/// The list given with `--from-file`: stdin for `-`, otherwise the file,
+/// found from the working directory.
+fn read_list(file: &Path) -> Result<String> {
+    if file.as_os_str() == "-" {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        return Ok(content);
+    }
+    fs::read_to_string(resolve(file)).map_err(|e| anyhow!("Could not read {}: {}", file.display(), e))
+}
+
+/// Splits trailing `#words` off a line, as in `Fix login crash #bug #auth`.
+fn inline_tags(line: &str) -> (String, Vec<String>) {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let mut tags = Vec::new();
+    while words.len() > 1 && let Some(tag) = words.last().and_then(|word| word.strip_prefix('#')).filter(|tag| !tag.is_empty()) {
+        tags.insert(0, tag.to_string());
+        words.pop();
+    }
+    (words.join(" "), tags)
+}
+
+/// The tags given with `--tag`, where each occurrence may list several
+/// separated by commas.
+fn given_tags(args: &AddArgs) -> Result<Vec<String>> {
+    let mut given = Vec::new();
+    for list in &args.tags {
+        given.extend(tags::parse_list(list)?);
+    }
+    Ok(given)
+}
+
+fn add(project_dir: &Path, args: &AddArgs, title: &str, extra_tags: Vec<String>, force: bool) -> Result<()> {
+    let mut task = Task::new(title);
+    task.description = args.desc.clone().unwrap_or_default();
+    task.metadata.add_tags(given_tags(args)?.iter().chain(&extra_tags).chain(&config::get().default_tags))?;
+    task.metadata.priority = args.priority;
+    task.metadata.due = args.due;
+    task.metadata.scheduled = args.scheduled;
+    if let Some(parent) = &args.parent {
+        let (_, parent) = find_task(project_dir, parent)?;
+        validate_parent(&load_scoped_tasks(project_dir)?, task.metadata.id, parent.metadata.id)?;
+        task.metadata.parent = Some(parent.metadata.id);
+    }
+
+    if let Some(epic) = &args.epic {
+        task.metadata.epic = Some(epic::find(project_dir, epic)?.1.metadata.id);
+    }
+
+    for link in &args.link {
+        let link = link_url::validate(link)?;
+        if !task.metadata.links.contains(&link) {
+            task.metadata.links.push(link);
+        }
+    }
+    if let Some(name) = &args.milestone {
+        task.metadata.milestone = Some(milestone::find(project_dir, name)?.name);
+    }
+
+    if args.here || config::get().branch_scope {
+        if args.here && open_repo().is_err() {
+            return Err(anyhow!("--here needs a git repository"));
+        }
+        task.metadata.branch = current_branch();
+    }
+
+    if config::get().check_duplicates && !confirm_unique(project_dir, &task.metadata.title, force)? {
+        say!("Not added");
+        return Ok(());
+    }
+
+    let path = save_task(&task_path(project_dir, &task), &task)?;
+    events::emit(Event::new(EventKind::Created, &path, &task.metadata, None))?;
+    say!("Added {} {}", task.short_id(), task.metadata.title);
+    Ok(())
+}
+
+/// The lowercase words of a title, ignoring punctuation.
+fn words(title: &str) -> Vec<String> {
+    let mut words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Whether two titles are the same ignoring case, or share most of their words.
+fn similar(a: &str, b: &str) -> bool {
+    if a.trim().to_lowercase() == b.trim().to_lowercase() {
+        return true;
+    }
+    let (a, b) = (words(a), words(b));
+    let common = a.iter().filter(|word| b.contains(word)).count();
+    let total = a.len() + b.len() - common;
+    total > 0 && common as f64 / total as f64 >= SIMILAR_WORDS
+}
+
+/// Looks for open tasks with a title like `title`. On a terminal the user is
+/// asked whether to add the task anyway; with `--force` or without a terminal
+/// only a warning is printed. Returns whether to go ahead.
+fn confirm_unique(project_dir: &Path, title: &str, force: bool) -> Result<bool> {
+    let tasks: Vec<Task> = load_tasks(project_dir)?.into_iter().map(|(_, task)| task).collect();
+    let matches: Vec<&Task> = tasks.iter()
+        .filter(|task| task.metadata.status != TaskStatus::DONE)
+        .filter(|task| similar(&task.metadata.title, title))
+        .collect();
+    if matches.is_empty() {
+        return Ok(true);
+    }
+    for task in &matches {
+        eprintln!("similar existing task: {} {}", task.short_id(), task.metadata.title);
+    }
+    if force || !std::io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    loop {
+        eprint!("Add anyway? [y]es, [n]o, [s]how existing: ");
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "s" | "show" => {
+                for task in &matches {
+                    eprintln!();
+                    show::print_task(task, &tasks);
+                }
+                eprintln!();
+            }
+            _ => return Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::in_working_dir;
+
+    #[test]
+    fn the_list_is_read_from_the_working_dir() {
+        let dir = std::env::temp_dir().join(format!("td-add-list-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("todo.txt"), "Write docs\nShip it\n").unwrap();
+        let content = in_working_dir(&dir, || read_list(Path::new("todo.txt")));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(content.unwrap(), "Write docs\nShip it\n");
+    }
+
+    fn parse(args: &[&str]) -> AddArgs {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            add: AddArgs,
+        }
+        <Cli as clap::Parser>::try_parse_from(["td"].iter().chain(args)).unwrap().add
+    }
+
+    fn tags_of(args: &[&str]) -> Vec<String> {
+        let mut task = Task::new("x");
+        task.metadata.add_tags(&given_tags(&parse(args)).unwrap()).unwrap();
+        task.metadata.tags
+    }
+
+    #[test]
+    fn tags_can_be_repeated_or_listed_with_commas() {
+        assert_eq!(tags_of(&["x", "-t", "bug", "--tag", "auth"]), ["bug", "auth"]);
+        assert_eq!(tags_of(&["x", "--tags", "bug, auth,ui"]), ["bug", "auth", "ui"]);
+        assert_eq!(tags_of(&["x", "-t", "bug,auth", "--tag", " ui ", "-t", "bug"]), ["bug", "auth", "ui"]);
+        assert!(tags_of(&["x"]).is_empty());
+    }
+
+    #[test]
+    fn trailing_hash_words_become_tags() {
+        let tags = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(inline_tags("Fix login crash #bug #auth"), ("Fix login crash".to_string(), tags(&["bug", "auth"])));
+        assert_eq!(inline_tags("Tag #a in the middle"), ("Tag #a in the middle".to_string(), tags(&[])));
+        assert_eq!(inline_tags("Learn C# #lang"), ("Learn C#".to_string(), tags(&["lang"])));
+        assert_eq!(inline_tags("#only"), ("#only".to_string(), tags(&[])));
+        assert_eq!(inline_tags("Ends in a lone # sign #"), ("Ends in a lone # sign #".to_string(), tags(&[])));
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::Result;
+
+use crate::archive;
+use crate::dates;
+use crate::project::{get_project_path, with_scopes};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct ArchiveArgs {
+    /// Only archive tasks finished longer ago than this, like 30d or 2w
+    #[arg(long, value_name = "PERIOD")]
+    older_than: Option<String>,
+}
+
+/// Moves the DONE tasks out of the listings into the archive directory,
+/// where `td ls --archived` still finds them.
+pub fn run(args: &ArchiveArgs) -> Result<()> {
+    let now = Utc::now();
+    let cutoff = match &args.older_than {
+        Some(period) => dates::before(period, now)?,
+        None => now,
+    };
+    let mut moved = 0;
+    for dir in with_scopes(&get_project_path()?)? {
+        moved += archive::archive_done(&dir, cutoff)?;
+    }
+    match moved {
+        0 => say!("Nothing to archive"),
+        1 => say!("Archived 1 task"),
+        n => say!("Archived {} tasks", n),
+    }
+    Ok(())
+}
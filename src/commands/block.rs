@@ -0,0 +1,32 @@
+use clap::Args;
+use anyhow::Result;
+
+use crate::assign::{self, Change};
+use crate::project::{find_task, get_project_path, load_scoped_tasks, update_task};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct BlockArgs {
+    /// The id (or a unique prefix of it) of the task that has to wait
+    id: String,
+    /// The ids of the tasks it waits for
+    #[arg(required = true)]
+    blockers: Vec<String>,
+}
+
+/// Records that a task waits for others, refusing dependencies that would
+/// make tasks wait for each other in a circle.
+pub fn run(args: &BlockArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let blockers = args.blockers.iter()
+        .map(|id| find_task(&project_dir, id).map(|(_, blocker)| blocker))
+        .collect::<Result<Vec<_>>>()?;
+    let ids = blockers.iter().map(|blocker| blocker.metadata.id).collect();
+    let (_, task) = update_task(&project_dir, &args.id, |task| {
+        assign::apply(task, &[Change::AddBlockedBy(ids)], &load_scoped_tasks(&project_dir)?)
+    })?;
+    for blocker in &blockers {
+        say!("{} is now blocked by {} {}", task.short_id(), blocker.short_id(), blocker.metadata.title);
+    }
+    Ok(())
+}
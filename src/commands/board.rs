@@ -0,0 +1,80 @@
+use clap::ValueEnum;
+use anyhow::Result;
+
+use crate::color;
+use crate::icons;
+use crate::index::{IndexEntry, load_index};
+use crate::project::get_project_path;
+use crate::sort::{SortKey, sort_tasks};
+use crate::task::TaskStatus;
+use crate::width;
+use chrono::Utc;
+
+/// The width of one column of the board, including the gap to the next one,
+/// when stdout is not a terminal.
+const COLUMN_WIDTH: usize = 32;
+
+/// Prints the tasks as a kanban board with one column per status.
+pub fn run() -> Result<()> {
+    let project_dir = get_project_path()?;
+    let now = Utc::now();
+    let mut tasks: Vec<IndexEntry> = load_index(&project_dir)?
+        .into_iter()
+        .filter(|task| !task.metadata.is_snoozed(now))
+        .collect();
+    sort_tasks(&mut tasks, SortKey::Priority, false, |task| &task.metadata);
+    let column_width = width::terminal_width()
+        .map(|w| (w / TaskStatus::value_variants().len()).max(12))
+        .unwrap_or(COLUMN_WIDTH);
+
+    let columns: Vec<Vec<Vec<String>>> = TaskStatus::value_variants()
+        .iter()
+        .map(|status| {
+            tasks.iter()
+                .filter(|task| task.metadata.status == *status)
+                .map(|task| card(task, column_width))
+                .collect()
+        })
+        .collect();
+
+    let icons = icons::get();
+    let header: Vec<String> = TaskStatus::value_variants()
+        .iter()
+        .zip(&columns)
+        .map(|(status, cards)| {
+            let text = pad(&format!("{} {:?} ({})", icons.status(*status), status, cards.len()), column_width);
+            color::status(&text, *status)
+        })
+        .collect();
+    println!("{}", header.join("").trim_end());
+    let rule = pad(&icons.rule(column_width - 2), column_width);
+    println!("{}", rule.repeat(header.len()).trim_end());
+
+    let lines: Vec<Vec<String>> = columns.into_iter().map(|cards| cards.concat()).collect();
+    let height = lines.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..height {
+        let line: String = lines.iter()
+            .map(|column| column.get(row).cloned().unwrap_or_else(|| " ".repeat(column_width)))
+            .collect();
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// The lines making up one card, each padded to the column width.
+fn card(task: &IndexEntry, column_width: usize) -> Vec<String> {
+    let meta = &task.metadata;
+    let mut lines = vec![pad(&format!("{} {}", meta.short_id(), meta.title), column_width)];
+    if !meta.tags.is_empty() {
+        let plain = width::truncate(&format!("         {}", meta.tags.join(", ")), column_width - 2);
+        let colored = plain.replace(&meta.tags.join(", "), &color::tags(&meta.tags));
+        let padding = column_width - width::display_width(&plain);
+        lines.push(format!("{}{}", colored, " ".repeat(padding)));
+    }
+    lines.push(" ".repeat(column_width));
+    lines
+}
+
+fn pad(text: &str, column_width: usize) -> String {
+    width::pad(&width::truncate(text, column_width - 2), column_width)
+}
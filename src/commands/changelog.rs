@@ -0,0 +1,76 @@
+use chrono::{DateTime, TimeZone, Utc};
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::dates;
+use crate::index::load_index;
+use crate::project::{get_project_path, open_repo};
+use crate::task::{TaskMetadata, TaskStatus};
+
+/// The section collecting finished tasks whose tags have no heading.
+const OTHER: &str = "Other";
+
+#[derive(Args, Debug)]
+pub struct ChangelogArgs {
+    /// Only tasks finished after this date, or after the commit a git tag or
+    /// ref points at
+    #[arg(long)]
+    since: String,
+}
+
+/// Reads `--since` as a date expression, falling back to a git ref whose
+/// commit time starts the window.
+fn resolve_since(since: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = dates::parse_since(since) {
+        return Ok(time);
+    }
+    let repo = open_repo()
+        .map_err(|_| anyhow!("'{}' is not a date and there is no git repository to look it up in", since))?;
+    let commit = repo.revparse_single(since)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| anyhow!("'{}' is neither a date nor a git tag or ref", since))?;
+    Utc.timestamp_opt(commit.time().seconds(), 0)
+        .single()
+        .ok_or_else(|| anyhow!("The commit {} has an invalid time", commit.id()))
+}
+
+pub fn run(args: &ChangelogArgs) -> Result<()> {
+    let since = resolve_since(&args.since)?;
+    let settings = &config::get().changelog;
+    let mut tasks: Vec<TaskMetadata> = load_index(&get_project_path()?)?
+        .into_iter()
+        .map(|task| task.metadata)
+        .filter(|meta| meta.status == TaskStatus::DONE)
+        .filter(|meta| meta.updated_at.is_some_and(|updated_at| updated_at >= since))
+        .filter(|meta| !meta.tags.iter().any(|tag| settings.exclude.contains(tag)))
+        .collect();
+    tasks.sort_by_key(|meta| meta.updated_at);
+
+    // Each task lands in the first section whose tag it carries.
+    let mut sections: Vec<(&str, Vec<&TaskMetadata>)> = settings.headings.iter().map(|(_, heading)| (heading.as_str(), Vec::new())).collect();
+    sections.push((OTHER, Vec::new()));
+    for meta in &tasks {
+        let section = settings.headings.iter()
+            .position(|(tag, _)| meta.tags.contains(tag))
+            .unwrap_or(settings.headings.len());
+        sections[section].1.push(meta);
+    }
+
+    println!("## Changes since {}", args.since);
+    if tasks.is_empty() {
+        println!("\nNo tasks were finished since {}", dates::format_time(since));
+    }
+    for (heading, members) in sections.iter().filter(|(_, members)| !members.is_empty()) {
+        println!("\n### {}\n", heading);
+        for meta in members {
+            if meta.commits.is_empty() {
+                println!("- {}", meta.title);
+            } else {
+                let shas: Vec<&str> = meta.commits.iter().map(|sha| sha.get(..7).unwrap_or(sha)).collect();
+                println!("- {} ({})", meta.title, shas.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use chrono::Utc;
+use clap::Args;
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::graph::Dependencies;
+use crate::index::load_index;
+use crate::project::{find_task, get_project_path, locked, save_task};
+use crate::say;
+use crate::tags;
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Rewrite tags that are not in their normalized form, dropping empty
+    /// ones, and remove links to related tasks that no longer exist
+    #[arg(long)]
+    fix: bool,
+}
+
+/// Looks for dependency cycles, which can come in through edited or
+/// imported files, for blockers and related tasks that no longer exist and
+/// for tags that td would not have written.
+pub fn run(args: &CheckArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let tasks = load_index(&project_dir)?;
+    let deps = Dependencies::new(tasks.iter().map(|task| &task.metadata));
+
+    for task in &tasks {
+        for blocker in &task.metadata.blocked_by {
+            if !tasks.iter().any(|other| other.metadata.id == *blocker) {
+                println!("warning: {} is blocked by {}, which does not exist", deps.describe(task.metadata.id), deps.describe(*blocker));
+            }
+        }
+    }
+
+    let (mut bad_tags, mut dangling) = (0, 0);
+    for entry in &tasks {
+        let problems: Vec<String> = entry.metadata.tags.iter().filter_map(|tag| tags::problem(tag)).collect();
+        let missing: Vec<Uuid> = entry.metadata.related.iter().copied().filter(|id| !tasks.iter().any(|other| other.metadata.id == *id)).collect();
+        if problems.is_empty() && missing.is_empty() {
+            continue;
+        }
+        if !args.fix {
+            for problem in &problems {
+                println!("tag: {}: {}", deps.describe(entry.metadata.id), problem);
+            }
+            for id in &missing {
+                println!("related: {} is related to {}, which does not exist", deps.describe(entry.metadata.id), deps.describe(*id));
+            }
+            bad_tags += problems.len();
+            dangling += missing.len();
+            continue;
+        }
+        locked(&project_dir, || {
+            let (path, mut task) = find_task(&project_dir, &entry.metadata.id.to_string())?;
+            task.metadata.related.retain(|id| !missing.contains(id));
+            let mut fixed: Vec<String> = Vec::new();
+            for tag in &task.metadata.tags {
+                if tag.trim().is_empty() {
+                    continue;
+                }
+                match tags::normalize(tag) {
+                    Ok(tag) if fixed.contains(&tag) => {}
+                    Ok(tag) => fixed.push(tag),
+                    Err(e) => {
+                        println!("tag: {}: {}, fix it by hand", deps.describe(task.metadata.id), e);
+                        bad_tags += 1;
+                        fixed.push(tag.clone());
+                    }
+                }
+            }
+            if fixed != task.metadata.tags || !missing.is_empty() {
+                task.metadata.tags = fixed;
+                task.metadata.updated_at = Some(Utc::now());
+                save_task(&path, &task)?;
+                say!("Fixed {} {}", task.short_id(), task.metadata.title);
+            }
+            Ok(())
+        })?;
+    }
+
+    let cycles = deps.cycles();
+    for cycle in &cycles {
+        println!("cycle: {}", deps.describe_cycle(cycle));
+    }
+    if !cycles.is_empty() {
+        return Err(anyhow!("Found {} dependency cycle(s), break them with `td set <id> blocked_by-=<id>`", cycles.len()));
+    }
+    if (bad_tags > 0 || dangling > 0) && !args.fix {
+        let mut found = Vec::new();
+        if bad_tags > 0 {
+            found.push(format!("{} invalid tag(s)", bad_tags));
+        }
+        if dangling > 0 {
+            found.push(format!("{} link(s) to missing tasks", dangling));
+        }
+        return Err(anyhow!("Found {}, `td check --fix` fixes them", found.join(" and ")));
+    }
+    if bad_tags > 0 {
+        return Err(anyhow!("{} invalid tag(s) could not be fixed", bad_tags));
+    }
+    println!("No dependency cycles among {} tasks", tasks.len());
+    Ok(())
+}
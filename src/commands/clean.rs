@@ -0,0 +1,152 @@
+use clap::Args;
+use std::{fs, io::{BufRead, IsTerminal, Write}, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+use anyhow::{Result, anyhow};
+
+use crate::index::{self, INDEX_FILE};
+use crate::project::{LOCK_FILE, TASK_EXTENSION, td_home};
+use crate::say;
+
+/// Temporary files younger than this may belong to a write in progress.
+const TEMP_GRACE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Args, Debug)]
+pub struct CleanArgs {
+    /// Remove what was found instead of only reporting it
+    #[arg(long)]
+    apply: bool,
+    /// Don't ask for confirmation before removing
+    #[arg(long, short, requires = "apply")]
+    yes: bool,
+}
+
+/// What `td clean` found in the data directory.
+#[derive(Default)]
+struct Findings {
+    /// Project directories holding nothing but an index
+    empty_dirs: Vec<PathBuf>,
+    /// Leftovers of interrupted writes
+    temp_files: Vec<PathBuf>,
+    /// Indexes naming task files that are gone
+    stale_indexes: Vec<PathBuf>,
+}
+
+/// Reports, and with `--apply` removes, what td left behind in the data
+/// directory. Task files are never touched.
+pub fn run(args: &CleanArgs) -> Result<()> {
+    let home = td_home()?;
+    let mut findings = Findings::default();
+    scan(&home, &home, &mut findings)?;
+
+    let categories = [
+        ("Empty project directories", &findings.empty_dirs),
+        ("Temporary files", &findings.temp_files),
+        ("Stale indexes", &findings.stale_indexes),
+    ];
+    let total: usize = categories.iter().map(|(_, paths)| paths.len()).sum();
+    if total == 0 {
+        println!("Nothing to clean in {}", home.display());
+        return Ok(());
+    }
+    for (name, paths) in categories.iter().filter(|(_, paths)| !paths.is_empty()) {
+        let bytes: u64 = paths.iter().map(|path| size(path)).sum();
+        println!("{}: {} ({})", name, paths.len(), human_size(bytes));
+        for path in paths.iter() {
+            println!("  {}", path.display());
+        }
+    }
+    if !args.apply {
+        println!("\nRun `td clean --apply` to remove them");
+        return Ok(());
+    }
+    if !args.yes && !confirm(&format!("Remove these {} entries?", total))? {
+        say!("Nothing removed");
+        return Ok(());
+    }
+
+    for path in findings.temp_files.iter().chain(&findings.stale_indexes) {
+        remove_file(path)?;
+    }
+    for dir in &findings.empty_dirs {
+        for file in [INDEX_FILE, LOCK_FILE] {
+            match fs::remove_file(dir.join(file)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        // Not recursive: anything that appeared since the scan keeps the directory.
+        fs::remove_dir(dir)?;
+    }
+    say!("Removed {} entries", total);
+    Ok(())
+}
+
+fn scan(home: &Path, dir: &Path, findings: &mut Findings) -> Result<()> {
+    let mut has_content = false;
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') {
+                subdirs.push(path);
+            }
+            has_content = true;
+        } else if name.starts_with('.') && name.ends_with(".tmp") {
+            let modified = entry.metadata()?.modified()?;
+            if SystemTime::now().duration_since(modified).unwrap_or_default() > TEMP_GRACE {
+                findings.temp_files.push(path);
+            } else {
+                has_content = true;
+            }
+        } else if name != INDEX_FILE && name != LOCK_FILE {
+            has_content = true;
+        }
+    }
+    if dir != home && !has_content {
+        findings.empty_dirs.push(dir.to_path_buf());
+    } else if index::is_stale(dir) {
+        findings.stale_indexes.push(dir.join(INDEX_FILE));
+    }
+    for subdir in subdirs {
+        scan(home, &subdir, findings)?;
+    }
+    Ok(())
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    if path.extension().is_some_and(|extension| extension == TASK_EXTENSION) {
+        return Err(anyhow!("Refusing to remove the task file {}", path.display()));
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The size of a file, or of the files directly in a directory.
+fn size(path: &Path) -> u64 {
+    match fs::read_dir(path) {
+        Ok(entries) => entries.flatten().filter_map(|entry| entry.metadata().ok()).map(|metadata| metadata.len()).sum(),
+        Err(_) => fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0),
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        b => format!("{:.1} MiB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("Pass --yes to clean up without a terminal to confirm on"));
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+
+use crate::config;
+
+/// Prints every effective setting with the file it came from.
+pub fn run() -> Result<()> {
+    let settings = config::settings()?;
+    let width = settings.iter().map(|(key, value, _)| key.len() + value.len() + 3).max().unwrap_or(0);
+    for (key, value, source) in &settings {
+        let setting = format!("{} = {}", key, value);
+        let source = source.map(|path| path.display().to_string()).unwrap_or_else(|| "(default)".to_string());
+        println!("{:<width$}  {}", setting, source, width = width);
+    }
+    Ok(())
+}
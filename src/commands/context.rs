@@ -0,0 +1,93 @@
+use clap::{Args, Subcommand};
+use anyhow::{Result, anyhow};
+
+use crate::config::{self, ContextConfig};
+use crate::context;
+use crate::say;
+use crate::task::TaskStatus;
+
+/// The name that turns the active context off.
+const NONE: &str = "none";
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct ContextArgs {
+    /// The context to activate, or `none` to list everything again. Without a
+    /// name the contexts are listed
+    name: Option<String>,
+    #[command(subcommand)]
+    command: Option<ContextCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ContextCommand {
+    /// Save a filter under a name, replacing an existing one
+    Define {
+        /// The name to activate the context with
+        name: String,
+        /// Only tasks with this tag (can be repeated)
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Leave out tasks with this tag (can be repeated)
+        #[arg(long)]
+        not_tag: Vec<String>,
+        /// Only tasks with this status (can be repeated)
+        #[arg(long, short, value_enum)]
+        status: Vec<TaskStatus>,
+    },
+}
+
+pub fn run(args: &ContextArgs) -> Result<()> {
+    match (&args.command, &args.name) {
+        (Some(ContextCommand::Define { name, tag, not_tag, status }), _) => {
+            define(name, ContextConfig { tags: tag.clone(), not_tags: not_tag.clone(), statuses: status.clone() })
+        }
+        (None, Some(name)) if name == NONE => {
+            context::select(None)?;
+            say!("No context is active");
+            Ok(())
+        }
+        (None, Some(name)) => {
+            if !config::get().contexts.contains_key(name) {
+                return Err(anyhow!("There is no context '{}', define it with `td context define {} --tag ...`", name, name));
+            }
+            context::select(Some(name))?;
+            say!("Context '{}' is active, `td context none` lists everything again", name);
+            Ok(())
+        }
+        (None, None) => list(),
+    }
+}
+
+fn define(name: &str, context: ContextConfig) -> Result<()> {
+    if name.is_empty() || name == NONE || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!("'{}' is not a valid context name, use letters, digits, '-' and '_'", name));
+    }
+    if context.tags.is_empty() && context.not_tags.is_empty() && context.statuses.is_empty() {
+        return Err(anyhow!("A context needs at least one of --tag, --not-tag or --status"));
+    }
+    config::set_context(name, &context)?;
+    say!("Defined context '{}': {}", name, describe(&context));
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let active = context::selected()?;
+    let contexts = &config::get().contexts;
+    if contexts.is_empty() {
+        println!("No contexts are defined, add one with `td context define <name> --tag ...`");
+    }
+    for (name, context) in contexts {
+        let marker = if active.as_ref() == Some(name) { "*" } else { " " };
+        println!("{} {}  {}", marker, name, describe(context));
+    }
+    Ok(())
+}
+
+fn describe(context: &ContextConfig) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(context.tags.iter().map(|tag| format!("+{}", tag)));
+    parts.extend(context.not_tags.iter().map(|tag| format!("-{}", tag)));
+    parts.extend(context.statuses.iter().map(|status| format!("{:?}", status)));
+    parts.join(" ")
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use anyhow::Result;
+
+use crate::config;
+use crate::crypt;
+use crate::project::{TASK_EXTENSION, alias_for, chosen_remote, current_scope, get_project_path, open_repo, repo_project_path, td_home};
+use crate::task::Task;
+
+/// Prints where td keeps things for the current directory and whether the
+/// task files can be read.
+pub fn run() -> Result<()> {
+    let settings = config::get();
+    println!("data dir:     {}", td_home()?.display());
+    println!("config:       {}", config::config_path()?.display());
+
+    match open_repo() {
+        Ok(repo) => println!("repository:   {}", repo.workdir().unwrap_or(repo.path()).display()),
+        Err(_) => println!("repository:   (none)"),
+    }
+    match chosen_remote(&settings.remote_priority) {
+        Some(remote) => {
+            let reason = if remote.from_repo_config {
+                "set with `td init --remote`".to_string()
+            } else {
+                format!("from remote_priority = [{}]", settings.remote_priority.join(", "))
+            };
+            println!("remote:       {} {} ({})", remote.name, remote.url, reason);
+        }
+        None => println!("remote:       (none, using the global project)"),
+    }
+
+    let repo_dir = repo_project_path()?;
+    let dir_name = repo_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    match alias_for(&dir_name, &settings.projects) {
+        Some(alias) => println!("project:      {} (alias {})", repo_dir.display(), alias),
+        None => println!("project:      {}", repo_dir.display()),
+    }
+    if let Some(scope) = current_scope() {
+        println!("scope:        {}", scope);
+    }
+
+    let project_dir = get_project_path()?;
+
+    let mut tasks = 0;
+    let mut broken = Vec::new();
+    for entry in fs::read_dir(&project_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(TASK_EXTENSION) {
+            continue;
+        }
+        tasks += 1;
+        if let Err(e) = crypt::read(&path).and_then(|content| Task::from_str(&content)) {
+            broken.push((path, e));
+        }
+    }
+    println!("tasks:        {}", tasks);
+    for (path, error) in &broken {
+        println!("  can not parse {}: {}", path.display(), error);
+    }
+    Ok(())
+}
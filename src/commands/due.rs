@@ -0,0 +1,62 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use anyhow::Result;
+
+use crate::dates;
+use crate::index::{IndexEntry, load_index};
+use crate::project::get_project_path;
+use crate::sort::{SortKey, sort_tasks};
+use crate::task::TaskStatus;
+
+/// Prints the open tasks with deadlines or scheduled starts coming up, split
+/// into deadline sections and start sections.
+pub fn run() -> Result<()> {
+    let project_dir = get_project_path()?;
+    let now = Utc::now();
+    let mut tasks: Vec<IndexEntry> = load_index(&project_dir)?
+        .into_iter()
+        .filter(|task| task.metadata.status != TaskStatus::DONE && !task.metadata.is_snoozed(now))
+        .collect();
+    sort_tasks(&mut tasks, SortKey::Due, false, |task| &task.metadata);
+
+    let today = dates::today();
+    let week_end = today + Days::new(7);
+    let due_on = |task: &IndexEntry| task.metadata.due.map(dates::day_of);
+    let starts_on = |task: &IndexEntry| task.metadata.scheduled.map(dates::day_of);
+    let in_range = |day: Option<NaiveDate>, from: NaiveDate, to: NaiveDate| day.is_some_and(|d| d >= from && d < to);
+
+    let sections: [(&str, Vec<&IndexEntry>); 6] = [
+        ("Overdue", tasks.iter().filter(|t| due_on(t).is_some_and(|d| d < today)).collect()),
+        ("Due today", tasks.iter().filter(|t| due_on(t) == Some(today)).collect()),
+        ("Due this week", tasks.iter().filter(|t| in_range(due_on(t), today + Days::new(1), week_end)).collect()),
+        ("Should have started", tasks.iter().filter(|t| t.metadata.start_overdue()).collect()),
+        ("Starts today", tasks.iter().filter(|t| starts_on(t) == Some(today)).collect()),
+        ("Starts this week", tasks.iter().filter(|t| in_range(starts_on(t), today + Days::new(1), week_end)).collect()),
+    ];
+
+    let mut printed_any = false;
+    for (name, members) in sections.iter().filter(|(_, members)| !members.is_empty()) {
+        if printed_any {
+            println!();
+        }
+        printed_any = true;
+        println!("{} ({})", name, members.len());
+        for task in members {
+            let (label, date) = if name.starts_with("Starts") {
+                ("starts", task.metadata.scheduled)
+            } else if name.starts_with("Should") {
+                ("was to start", task.metadata.scheduled)
+            } else {
+                ("due", task.metadata.due)
+            };
+            println!("{}  {:<5}  {} {}  {}", task.metadata.short_id(), format!("{:?}", task.metadata.status), label, format_day(date), task.metadata.title);
+        }
+    }
+    if !printed_any {
+        println!("Nothing due or scheduled in the next week");
+    }
+    Ok(())
+}
+
+fn format_day(time: Option<DateTime<Utc>>) -> String {
+    time.map(dates::format_day).unwrap_or_default()
+}
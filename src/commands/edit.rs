@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::{env, io::{Read, Write}, path::PathBuf, process::Stdio};
+use anyhow::Result;
+
+use crate::dates;
+use crate::project::{find_task, get_project_path, update_task};
+
+#[derive(Args, Debug)]
+pub struct EditArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// Set when the task is due instead of opening the editor
+    #[arg(long, value_parser = dates::parse_when)]
+    due: Option<DateTime<Utc>>,
+    /// Set when you intend to start the task instead of opening the editor
+    #[arg(long, value_parser = dates::parse_when)]
+    scheduled: Option<DateTime<Utc>>,
+    /// Remove the due date
+    #[arg(long, conflicts_with = "due")]
+    no_due: bool,
+    /// Remove the scheduled date
+    #[arg(long, conflicts_with = "scheduled")]
+    no_scheduled: bool,
+}
+
+pub fn run(args: &EditArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+
+    if args.due.is_some() || args.scheduled.is_some() || args.no_due || args.no_scheduled {
+        update_task(&project_dir, &args.id, |task| {
+            if args.due.is_some() || args.no_due {
+                task.metadata.due = args.due;
+            }
+            if args.scheduled.is_some() || args.no_scheduled {
+                task.metadata.scheduled = args.scheduled;
+            }
+            task.metadata.updated_at = Some(Utc::now());
+            Ok(())
+        })?;
+        return Ok(());
+    }
+
+    // The editor runs without the lock; only the write of the result takes it.
+    let (_, task) = find_task(&project_dir, &args.id)?;
+
+    let mut temp_path = PathBuf::new();
+    temp_path.push(std::env::temp_dir());
+    temp_path.push(format!("td_{}_{}.md", task.metadata.id, Utc::now().timestamp()));
+    let mut temp_file = std::fs::File::create(&temp_path)?;
+    temp_file.write_all(task.description.as_bytes())?;
+
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
+    let mut command = std::process::Command::new(&editor);
+    command.arg(&temp_path);
+    command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    match command.spawn() {
+        Ok(mut child) => {
+            let status = child.wait().expect("child process should complete");
+            if !status.success() {eprintln!("Editor exited with non-zero status: {:?}", status)}
+            let mut edited_content = String::new();
+            let mut temp_file = std::fs::File::open(&temp_path)?;
+            temp_file.read_to_string(&mut edited_content)?;
+            update_task(&project_dir, &task.metadata.id.to_string(), |task| {
+                task.description = edited_content;
+                task.metadata.updated_at = Some(Utc::now());
+                Ok(())
+            })?;
+        }
+        Err(e) => eprintln!("Failed to launch editor '{}': '{}'", editor, e)
+    }
+
+    Ok(())
+}
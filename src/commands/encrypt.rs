@@ -0,0 +1,131 @@
+use clap::Args;
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow};
+
+use crate::archive::ARCHIVE_DIR;
+use crate::config;
+use crate::index::INDEX_FILE;
+use crate::crypt::{self, Scope};
+use crate::project::{TASK_EXTENSION, free_task_path, get_project_path, locked, project_name, project_root, slug, with_scopes};
+use crate::say;
+use crate::task::{Frontmatter, Task};
+
+#[derive(Args, Debug)]
+#[command(group = clap::ArgGroup::new("mode").required(true))]
+pub struct EncryptArgs {
+    /// Encrypt the tasks of the current project, and every task written to
+    /// it from now on. The passphrase is read from TD_PASSPHRASE or asked for
+    #[arg(long, group = "mode")]
+    enable: bool,
+    /// Decrypt the tasks back to plain text and stop encrypting them
+    #[arg(long, group = "mode")]
+    disable: bool,
+    /// How much of each task is encrypted. Unless it is only the body,
+    /// listing the tasks asks for the passphrase too
+    #[arg(long, value_enum, default_value_t = Scope::Body)]
+    scope: Scope,
+}
+
+/// Converts every task file of the project, archived ones and sub-scopes
+/// included. Each file is replaced on its own and td reads plain and
+/// encrypted files alike, so running it again after an interruption finishes
+/// the conversion.
+pub fn run(args: &EncryptArgs) -> Result<()> {
+    let root = project_root(&get_project_path()?)?;
+    let name = project_name(&root);
+    if args.disable {
+        if crypt::settings(&root)?.is_none() {
+            return Err(anyhow!("The tasks of {} are not encrypted", name));
+        }
+        let converted = convert(&root, None)?;
+        crypt::disable(&root)?;
+        say!("Decrypted {} task file(s) of {}", converted, name);
+        return Ok(());
+    }
+    let passphrase = match crypt::settings(&root)? {
+        Some(_) => None,
+        None => Some(new_passphrase()?),
+    };
+    crypt::enable(&root, args.scope, passphrase.as_deref())?;
+    let converted = convert(&root, Some(args.scope))?;
+    say!("Encrypted {} task file(s) of {} ({})", converted, name, format!("{:?}", args.scope).to_lowercase());
+    Ok(())
+}
+
+/// Rewrites the task files that are not stored as `scope` yet, or as plain
+/// text for `None`, returning how many were rewritten.
+fn convert(root: &Path, scope: Option<Scope>) -> Result<usize> {
+    locked(root, || {
+        let mut converted = 0;
+        for dir in with_scopes(root)? {
+            // Listing leaves the index out from now on, it must not keep the
+            // titles either.
+            if scope >= Some(Scope::Title) && let Err(e) = fs::remove_file(dir.join(INDEX_FILE)) && e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+            for path in task_files(&dir)?.into_iter().chain(task_files(&dir.join(ARCHIVE_DIR))?) {
+                if crypt::stored_scope(&path)? == scope {
+                    continue;
+                }
+                let content = crypt::read(&path)?;
+                let dir = path.parent().unwrap_or(root);
+                let (stored, target) = match Task::from_str_lenient(&content)? {
+                    (task, None) if scope.is_some() => (crypt::seal(dir, &task, config::get().frontmatter)?, free_task_path(dir, &task, Some(&path))),
+                    (task, None) => (task.to_string_as(Frontmatter::detect(&content).unwrap_or_default())?, plain_path(dir, &task, &path)),
+                    // Rewriting it would lose the status, plain text is kept as it is.
+                    (_, Some(problem)) if scope.is_some() => {
+                        eprintln!("warning: leaving {} unencrypted: {}", path.display(), problem);
+                        continue;
+                    }
+                    (_, Some(_)) => (content, path.clone()),
+                };
+                let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+                let temp_path = target.with_file_name(format!(".{}.tmp", file_name));
+                fs::write(&temp_path, stored)?;
+                fs::rename(&temp_path, &target)?;
+                if target != path {
+                    fs::remove_file(&path)?;
+                }
+                converted += 1;
+            }
+        }
+        Ok(converted)
+    })
+}
+
+/// The file name a decrypted task gets back its title in, unless another
+/// file has it already.
+fn plain_path(dir: &Path, task: &Task, current: &Path) -> PathBuf {
+    let target = dir.join(format!("{}.{}.{}", slug(&task.metadata.title), task.short_id(), TASK_EXTENSION));
+    if target.exists() { current.to_path_buf() } else { target }
+}
+
+fn task_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// The passphrase for a newly encrypted project, typed twice unless it comes
+/// from TD_PASSPHRASE.
+fn new_passphrase() -> Result<String> {
+    let passphrase = crypt::passphrase("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("The passphrase can not be empty"));
+    }
+    if std::env::var_os(crypt::PASSPHRASE_ENV).is_none() && crypt::passphrase("Repeat the passphrase: ")? != passphrase {
+        return Err(anyhow!("The passphrases do not match, nothing was encrypted"));
+    }
+    Ok(passphrase)
+}
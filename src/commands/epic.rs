@@ -0,0 +1,120 @@
+use clap::Subcommand;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+
+use crate::color;
+use crate::commands::show::PROGRESS_CELLS;
+use crate::events::{self, Event, EventKind};
+use crate::icons;
+use crate::index::load_index;
+use crate::project::{find_task, get_project_path, save_task, task_path};
+use crate::say;
+use crate::tags;
+use crate::task::{Priority, Task, TaskKind, TaskMetadata, TaskStatus};
+
+#[derive(Subcommand, Debug)]
+pub enum EpicCommand {
+    /// Create an epic, which tasks are filed under with `td add --epic`
+    Add {
+        title: String,
+        /// A description of the epic
+        #[arg(long, short)]
+        desc: Option<String>,
+        /// A tag for the epic (can be repeated, or list several separated by commas)
+        #[arg(long = "tag", short, value_name = "TAG")]
+        tags: Vec<String>,
+        #[arg(long, short)]
+        priority: Option<Priority>,
+    },
+    /// List the epics with how far along their tasks are
+    Ls,
+    /// Show an epic and its tasks
+    Show {
+        /// The id (or a unique prefix of it) of the epic
+        id: String,
+    },
+}
+
+pub fn run(command: &EpicCommand) -> Result<()> {
+    let project_dir = get_project_path()?;
+    match command {
+        EpicCommand::Add { title, desc, tags, priority } => {
+            let mut task = Task::new(title);
+            task.metadata.kind = Some(TaskKind::Epic);
+            task.description = desc.clone().unwrap_or_default();
+            let mut given = Vec::new();
+            for list in tags {
+                given.extend(tags::parse_list(list)?);
+            }
+            task.metadata.add_tags(&given)?;
+            task.metadata.priority = *priority;
+            let path = save_task(&task_path(&project_dir, &task), &task)?;
+            events::emit(Event::new(EventKind::Created, &path, &task.metadata, None))?;
+            say!("Added epic {} {}", task.short_id(), task.metadata.title);
+        }
+        EpicCommand::Ls => {
+            let tasks: Vec<TaskMetadata> = load_index(&project_dir)?.into_iter().map(|entry| entry.metadata).collect();
+            let epics: Vec<&TaskMetadata> = tasks.iter().filter(|meta| meta.is_epic()).collect();
+            if epics.is_empty() {
+                println!("No epics, add one with `td epic add <title>`");
+            }
+            for epic in epics {
+                let (done, total) = progress(epic, &tasks);
+                let status = format!("{:<5}", format!("{:?}", epic.status));
+                println!("{}  {}  {} {:>5}  {}", epic.short_id(), color::status(&status, epic.status), icons::get().bar(done, total, PROGRESS_CELLS), format!("{}/{}", done, total), epic.title);
+            }
+        }
+        EpicCommand::Show { id } => {
+            let (_, epic) = find(&project_dir, id)?;
+            let tasks: Vec<TaskMetadata> = load_index(&project_dir)?.into_iter().map(|entry| entry.metadata).collect();
+            let (done, total) = progress(&epic.metadata, &tasks);
+            println!("{}", epic.metadata.title);
+            println!("id:       {}", epic.metadata.id);
+            println!("status:   {:?}", epic.metadata.status);
+            println!("progress: {} {}/{}", icons::get().bar(done, total, PROGRESS_CELLS), done, total);
+            if !epic.description.trim().is_empty() {
+                println!("\n{}", epic.description.trim_end());
+            }
+            let members: Vec<&TaskMetadata> = tasks.iter().filter(|meta| meta.epic == Some(epic.metadata.id)).collect();
+            if !members.is_empty() {
+                println!("\ntasks:");
+            }
+            for meta in members {
+                let status = format!("{:<5}", format!("{:?}", meta.status));
+                println!("  {} {}  {}  {}", icons::get().status(meta.status), meta.short_id(), color::status(&status, meta.status), meta.title);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finds the task with the id, failing when it is not an epic.
+pub fn find(project_dir: &Path, id: &str) -> Result<(PathBuf, Task)> {
+    let (path, task) = find_task(project_dir, id)?;
+    if !task.metadata.is_epic() {
+        return Err(anyhow!("{} {} is not an epic, create one with `td epic add`", task.short_id(), task.metadata.title));
+    }
+    Ok((path, task))
+}
+
+/// How many of the tasks filed under an epic are done, and how many there are.
+pub fn progress(epic: &TaskMetadata, tasks: &[TaskMetadata]) -> (usize, usize) {
+    let members: Vec<&TaskMetadata> = tasks.iter().filter(|meta| meta.epic == Some(epic.id)).collect();
+    (members.iter().filter(|meta| meta.status == TaskStatus::DONE).count(), members.len())
+}
+
+/// Warns when an epic is completed while tasks filed under it are still open.
+pub fn warn_open(epic: &TaskMetadata, tasks: &[TaskMetadata]) {
+    if !epic.is_epic() {
+        return;
+    }
+    let open: Vec<&TaskMetadata> = tasks.iter()
+        .filter(|meta| meta.epic == Some(epic.id) && meta.status != TaskStatus::DONE)
+        .collect();
+    if !open.is_empty() {
+        eprintln!("warning: epic {} still has {} open task(s):", epic.short_id(), open.len());
+        for meta in open {
+            eprintln!("  {}  {:?}  {}", meta.short_id(), meta.status, meta.title);
+        }
+    }
+}
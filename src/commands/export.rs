@@ -0,0 +1,315 @@
+use clap::{Args, ValueEnum};
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::backup;
+use crate::dates;
+use crate::filter::FilterArgs;
+use crate::markdown::{self, escape};
+use crate::project::{find_project, find_task, get_project_path, list_projects, load_tasks, project_name, project_root, resolve};
+use crate::say;
+use crate::sort::{SortKey, sort_tasks};
+use crate::task::{Task, TaskStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A self-contained HTML page with the board
+    Html,
+    /// A Mermaid gantt diagram of the scheduled and due dates
+    MermaidGantt,
+    /// A Graphviz graph of which tasks block which
+    Dot,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// What to export the tasks as
+    #[arg(long, value_enum, required_unless_present = "archive")]
+    format: Option<ExportFormat>,
+    /// Pack the task files of the project, with its archived tasks, config,
+    /// milestones and journal, into this .tar.gz to carry them to another
+    /// machine with `td import --archive`
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["format", "output", "title", "focus"])]
+    archive: Option<PathBuf>,
+    /// Pack these projects instead of the current one, by alias or directory name
+    #[arg(long, value_delimiter = ',', requires = "archive")]
+    projects: Vec<String>,
+    /// Pack every project
+    #[arg(long, requires = "archive", conflicts_with = "projects")]
+    all_projects: bool,
+    /// Write to this file instead of stdout
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+    /// The heading of the page or diagram, the project name by default
+    #[arg(long)]
+    title: Option<String>,
+    /// Only graph the tasks this one depends on and those depending on it, for dot
+    #[arg(long)]
+    focus: Option<String>,
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+pub fn run(args: &ExportArgs) -> Result<()> {
+    let Some(format) = args.format else {
+        return archive(args, args.archive.as_deref().unwrap_or(Path::new("")));
+    };
+    let project_dir = get_project_path()?;
+    let filter = args.filter.filter()?;
+    let all_tasks: Vec<Task> = load_tasks(&project_dir)?.into_iter().map(|(_, task)| task).collect();
+    let all_ids: HashSet<Uuid> = all_tasks.iter().map(|task| task.metadata.id).collect();
+    let mut tasks: Vec<Task> = all_tasks.into_iter().filter(|task| filter.matches_task(task)).collect();
+    if let Some(focus) = &args.focus {
+        let (_, focus) = find_task(&project_dir, focus)?;
+        let related = related(focus.metadata.id, &tasks);
+        tasks.retain(|task| related.contains(&task.metadata.id));
+    }
+    sort_tasks(&mut tasks, SortKey::Priority, false, |task| &task.metadata);
+
+    let title = args.title.clone().unwrap_or_else(|| format!("{} tasks", project_name(&project_dir)));
+    let statuses: Vec<TaskStatus> = if filter.statuses.is_empty() { TaskStatus::value_variants().to_vec() } else { filter.statuses.clone() };
+    let document = match format {
+        ExportFormat::Html => html(&title, &statuses, &tasks),
+        ExportFormat::MermaidGantt => mermaid_gantt(&title, &tasks),
+        ExportFormat::Dot => dot(&title, &tasks, &all_ids),
+    };
+    write_document(args.output.as_deref(), &document)
+}
+
+/// Writes the document to `output`, found from the working directory, or
+/// prints it without one.
+fn write_document(output: Option<&Path>, document: &str) -> Result<()> {
+    match output {
+        Some(path) => fs::write(resolve(path), document)?,
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+fn archive(args: &ExportArgs, output: &Path) -> Result<()> {
+    let projects: Vec<PathBuf> = if args.all_projects {
+        list_projects()?.into_iter().map(|(_, dir)| dir).collect()
+    } else if args.projects.is_empty() {
+        vec![project_root(&get_project_path()?)?]
+    } else {
+        args.projects.iter().map(|name| find_project(name)).collect::<Result<_>>()?
+    };
+    let tasks = backup::export(&projects, output)?;
+    say!("Packed {} task(s) of {} project(s) into {}", tasks, projects.len(), output.display());
+    Ok(())
+}
+
+/// `id` with every task it waits for and every task waiting for it, directly
+/// or through others.
+fn related(id: Uuid, tasks: &[Task]) -> HashSet<Uuid> {
+    let mut related = HashSet::from([id]);
+    for upstream in [true, false] {
+        let mut pending = vec![id];
+        while let Some(current) = pending.pop() {
+            let next: Vec<Uuid> = if upstream {
+                tasks.iter().find(|task| task.metadata.id == current).map(|task| task.metadata.blocked_by.clone()).unwrap_or_default()
+            } else {
+                tasks.iter().filter(|task| task.metadata.blocked_by.contains(&current)).map(|task| task.metadata.id).collect()
+            };
+            for id in next {
+                if related.insert(id) {
+                    pending.push(id);
+                }
+            }
+        }
+    }
+    related
+}
+
+/// How long a line of a node label in the dependency graph gets.
+const DOT_LABEL_WIDTH: usize = 24;
+
+/// A directed graph with an edge from each blocker to the task it blocks.
+/// Blockers whose task no longer exists show up as red "missing" nodes;
+/// `known` holds every id in the project, so filtered out tasks are simply
+/// left out instead.
+fn dot(title: &str, tasks: &[Task], known: &HashSet<Uuid>) -> String {
+    let mut graph = format!("digraph tasks {{\n    label=\"{}\";\n    labelloc=t;\n    node [fontname=\"Helvetica\", shape=box, style=\"rounded,filled\"];\n", dot_text(title));
+    let shown: HashSet<Uuid> = tasks.iter().map(|task| task.metadata.id).collect();
+    let mut missing = Vec::new();
+    for task in tasks {
+        let meta = &task.metadata;
+        let (fill, border) = match meta.status {
+            TaskStatus::TODO => ("#ffffff", "#57606a"),
+            TaskStatus::DOING => ("#fff8c5", "#bf8700"),
+            TaskStatus::DONE => ("#dafbe1", "#1a7f37"),
+        };
+        let label = format!("{}\n{}", meta.short_id(), wrap(&meta.title, DOT_LABEL_WIDTH).join("\n"));
+        graph.push_str(&format!("    \"{}\" [label=\"{}\", fillcolor=\"{}\", color=\"{}\"];\n", meta.id, dot_text(&label), fill, border));
+        for blocker in &meta.blocked_by {
+            if !known.contains(blocker) && !missing.contains(blocker) {
+                missing.push(*blocker);
+            }
+            if shown.contains(blocker) || !known.contains(blocker) {
+                graph.push_str(&format!("    \"{}\" -> \"{}\";\n", blocker, meta.id));
+            }
+        }
+    }
+    for id in missing {
+        graph.push_str(&format!("    \"{}\" [label=\"{}\\n(missing)\", shape=octagon, style=dashed, color=\"#cf222e\", fontcolor=\"#cf222e\"];\n", id, &id.to_string()[..8]));
+    }
+    graph.push_str("}\n");
+    graph
+}
+
+/// Splits text into lines of at most `width` characters at spaces.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Escapes text for a quoted DOT string, keeping the `\n` line breaks of labels.
+fn dot_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// The section of the gantt diagram for tasks without tags.
+const UNTAGGED: &str = "Untagged";
+
+/// A gantt diagram with one bar per task that has dates, from its scheduled
+/// start (or creation) to its deadline, in a section per first tag. Tasks
+/// without dates are listed in a comment at the end.
+fn mermaid_gantt(title: &str, tasks: &[Task]) -> String {
+    let mut diagram = format!("gantt\n    title {}\n    dateFormat YYYY-MM-DD\n", mermaid_text(title));
+    let mut sections: Vec<(&str, Vec<String>)> = Vec::new();
+    let mut undated = Vec::new();
+    for task in tasks {
+        let meta = &task.metadata;
+        if meta.scheduled.is_none() && meta.due.is_none() {
+            undated.push(format!("%% {} {}", meta.short_id(), meta.title.replace('\n', " ")));
+            continue;
+        }
+        let start = dates::day_of(meta.scheduled.unwrap_or(meta.created_at));
+        let end = match meta.due.map(dates::day_of) {
+            Some(end) if end > start => end.format("%Y-%m-%d").to_string(),
+            _ => "1d".to_string(),
+        };
+        let state = match meta.status {
+            TaskStatus::DONE => "done, ",
+            TaskStatus::DOING => "active, ",
+            TaskStatus::TODO => "",
+        };
+        let bar = format!("    {} :{}t{}, {}, {}", mermaid_text(&meta.title), state, meta.short_id(), start.format("%Y-%m-%d"), end);
+        let section = meta.tags.first().map(String::as_str).unwrap_or(UNTAGGED);
+        match sections.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, bars)) => bars.push(bar),
+            None => sections.push((section, vec![bar])),
+        }
+    }
+    sections.sort_by_key(|(name, _)| (*name == UNTAGGED, name.to_string()));
+    for (name, bars) in sections {
+        diagram.push_str(&format!("    section {}\n", mermaid_text(name)));
+        for bar in bars {
+            diagram.push_str(&bar);
+            diagram.push('\n');
+        }
+    }
+    if !undated.is_empty() {
+        diagram.push_str("%% Tasks without dates:\n");
+        for line in undated {
+            diagram.push_str(&line);
+            diagram.push('\n');
+        }
+    }
+    diagram
+}
+
+/// Replaces the characters that end a gantt task name or a statement with
+/// Mermaid's entity codes.
+fn mermaid_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            ':' => escaped.push_str("#58;"),
+            ',' => escaped.push_str("#44;"),
+            ';' => escaped.push_str("#59;"),
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Inline styles only, so the page survives being mailed or pasted into a wiki.
+const STYLE: &str = "body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;color:#24292f;margin:24px}\
+table{border-collapse:separate;border-spacing:12px 0;width:100%;table-layout:fixed}\
+th{text-align:left;padding:8px;border-bottom:3px solid #d0d7de}\
+th.todo{border-color:#0969da}th.doing{border-color:#bf8700}th.done{border-color:#1a7f37}\
+td{vertical-align:top;padding:0}\
+.card{border:1px solid #d0d7de;border-radius:6px;padding:8px 10px;margin:8px 0;background:#f6f8fa}\
+.id{color:#57606a;font-family:monospace;font-size:85%}\
+.tag{display:inline-block;background:#ddf4ff;color:#0550ae;border-radius:10px;padding:0 8px;margin:2px 4px 0 0;font-size:85%}\
+.due{color:#57606a;font-size:85%;margin-top:4px}.overdue{color:#cf222e}\
+details{margin-top:6px;font-size:90%}summary{cursor:pointer;color:#57606a}";
+
+pub fn html(title: &str, statuses: &[TaskStatus], tasks: &[Task]) -> String {
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n<table>\n<tr>\n",
+        escape(title), STYLE,
+    );
+    for status in statuses {
+        let count = tasks.iter().filter(|task| task.metadata.status == *status).count();
+        let name = format!("{:?}", status);
+        page.push_str(&format!("<th class=\"{}\">{} ({})</th>\n", name.to_lowercase(), name, count));
+    }
+    page.push_str("</tr>\n<tr>\n");
+    for status in statuses {
+        page.push_str("<td>\n");
+        for task in tasks.iter().filter(|task| task.metadata.status == *status) {
+            page.push_str(&card(task));
+        }
+        page.push_str("</td>\n");
+    }
+    page.push_str("</tr>\n</table>\n</body>\n</html>\n");
+    page
+}
+
+fn card(task: &Task) -> String {
+    let meta = &task.metadata;
+    let mut card = format!("<div class=\"card\">\n<span class=\"id\">{}</span> {}\n", meta.short_id(), escape(&meta.title));
+    if !meta.tags.is_empty() {
+        let tags: Vec<String> = meta.tags.iter().map(|tag| format!("<span class=\"tag\">{}</span>", escape(tag))).collect();
+        card.push_str(&format!("<div>{}</div>\n", tags.join("")));
+    }
+    if let Some(due) = meta.due {
+        let class = if meta.status != TaskStatus::DONE && crate::dates::day_of(due) < crate::dates::today() { "due overdue" } else { "due" };
+        card.push_str(&format!("<div class=\"{}\">due {}</div>\n", class, dates::format_day(due)));
+    }
+    if !task.description.trim().is_empty() {
+        card.push_str(&format!("<details>\n<summary>Description</summary>\n{}</details>\n", markdown::to_html(&task.description)));
+    }
+    card.push_str("</div>\n");
+    card
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::in_working_dir;
+
+    #[test]
+    fn the_output_is_written_in_the_working_dir() {
+        let dir = std::env::temp_dir().join(format!("td-export-output-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        in_working_dir(&dir, || write_document(Some(Path::new("board.html")), "<html></html>")).unwrap();
+        let written = fs::read_to_string(dir.join("board.html"));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(written.unwrap(), "<html></html>");
+    }
+}
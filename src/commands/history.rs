@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use anyhow::Result;
+
+use crate::dates;
+use crate::journal::{self, Entry};
+use crate::project::repo_project_path;
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Only the changes of the task with this id (or a prefix of it), which
+    /// may have been deleted since
+    id: Option<String>,
+    /// Only changes made after this date
+    #[arg(long, value_parser = dates::parse_since)]
+    since: Option<DateTime<Utc>>,
+    /// How many of the most recent changes to show
+    #[arg(long, short = 'n', default_value_t = 20)]
+    limit: usize,
+}
+
+pub fn run(args: &HistoryArgs) -> Result<()> {
+    let path = journal::journal_path(&repo_project_path()?)?;
+    let prefix = args.id.as_deref().map(str::to_lowercase);
+    let entries: Vec<Entry> = journal::read(&path)?
+        .into_iter()
+        .filter(|entry| prefix.as_ref().is_none_or(|prefix| entry.task_id.to_string().starts_with(prefix)))
+        .filter(|entry| args.since.is_none_or(|since| entry.timestamp >= since))
+        .collect();
+    if entries.is_empty() {
+        println!("No matching changes in {}", path.display());
+    }
+    for entry in &entries[entries.len().saturating_sub(args.limit)..] {
+        // Encrypted projects journal no titles.
+        let title = if entry.title.is_empty() { String::new() } else { format!("{}: ", entry.title) };
+        println!(
+            "{}  {}  {}{}  ({})",
+            dates::format_time(entry.timestamp),
+            &entry.task_id.to_string()[..8],
+            title,
+            entry.changes,
+            entry.command,
+        );
+    }
+    Ok(())
+}
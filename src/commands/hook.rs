@@ -0,0 +1,95 @@
+use clap::Subcommand;
+use git2::Repository;
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::Result;
+
+use crate::index::load_index;
+use crate::project::{listing_dirs, open_repo, resolve};
+use crate::say;
+use crate::task::TaskStatus;
+
+/// A line every hook td installs carries, so reinstalling can tell them from
+/// hooks the user wrote.
+const MARKER: &str = "# Installed by td";
+
+/// The git hooks td installs, with the script of each. The scripts only
+/// hand over to td and swallow its errors so a commit never fails because
+/// of them.
+const HOOKS: &[(&str, &str)] = &[
+    ("prepare-commit-msg", "td __hook prepare-commit-msg \"$@\" 2>/dev/null || true\nexit 0\n"),
+];
+
+#[derive(Subcommand, Debug)]
+pub enum HookCommand {
+    /// Append the DOING tasks as comments to a commit message
+    PrepareCommitMsg {
+        /// The file holding the commit message
+        file: PathBuf,
+        /// Where the message came from: message, template, merge, squash or commit
+        source: Option<String>,
+        /// The commit being amended
+        sha: Option<String>,
+    },
+}
+
+pub fn run(command: &HookCommand) -> Result<()> {
+    match command {
+        HookCommand::PrepareCommitMsg { file, source, .. } => prepare_commit_msg(&resolve(file), source.as_deref()),
+    }
+}
+
+/// Appends a commented line for each DOING task, like `# DOING: a1b2c3d4 Fix
+/// login crash`. Merge and squash messages are left alone.
+fn prepare_commit_msg(file: &Path, source: Option<&str>) -> Result<()> {
+    if matches!(source, Some("merge" | "squash")) {
+        return Ok(());
+    }
+    let mut doing = Vec::new();
+    for dir in listing_dirs(false)? {
+        doing.extend(load_index(&dir)?.into_iter().filter(|task| task.metadata.status == TaskStatus::DOING));
+    }
+    if doing.is_empty() {
+        return Ok(());
+    }
+    let comment = open_repo().ok()
+        .and_then(|repo| repo.config().ok()?.get_string("core.commentChar").ok())
+        .filter(|c| c.len() == 1)
+        .unwrap_or_else(|| "#".to_string());
+
+    let mut message = fs::read_to_string(file)?;
+    if !message.is_empty() && !message.ends_with('\n') {
+        message.push('\n');
+    }
+    message.push_str(&format!("{}\n", comment));
+    for task in &doing {
+        message.push_str(&format!("{} DOING: {} {}\n", comment, task.metadata.short_id(), task.metadata.title));
+    }
+    fs::write(file, message)?;
+    Ok(())
+}
+
+/// Writes td's hooks into the repository's hooks directory, honouring
+/// `core.hooksPath`. A hook the user wrote is kept unless `force` is set.
+pub fn install(repo: &Repository, force: bool) -> Result<()> {
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_relative() => repo.workdir().unwrap_or(repo.path()).join(path),
+        Ok(path) => path,
+        Err(_) => repo.path().join("hooks"),
+    };
+    fs::create_dir_all(&hooks_dir)?;
+    for (name, script) in HOOKS {
+        let path = hooks_dir.join(name);
+        if let Ok(existing) = fs::read_to_string(&path) && !existing.contains(MARKER) && !force {
+            eprintln!("warning: not replacing {}, it was not installed by td (use --force)", path.display());
+            continue;
+        }
+        fs::write(&path, format!("#!/bin/sh\n{}\n{}", MARKER, script))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        say!("Installed the {} hook", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::backup;
+use crate::crypt;
+use crate::events::{self, Event, EventKind};
+use crate::index::load_entries;
+use crate::journal;
+use crate::project::{get_project_path, resolve, save_task, task_path};
+use crate::say;
+use crate::tags;
+use crate::task::{Frontmatter, Task};
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+pub struct ImportArgs {
+    #[command(subcommand)]
+    command: Option<ImportCommand>,
+    /// Unpack an archive written by `td export --archive` into the data
+    /// directory, merging its projects with those already there by task id
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+    /// Print what the archive would change without writing anything
+    #[arg(long, requires = "archive")]
+    dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommand {
+    /// Turn markdown files into tasks of the current project. The first
+    /// `# heading` becomes the title, the rest the description. Files that
+    /// already are td tasks are copied as they are
+    Md {
+        /// Files, directories to take every `.md` file of, or patterns like
+        /// `notes/NOTES-*.md`
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Use the time the file was last modified as the creation time
+        #[arg(long)]
+        mtime_as_created: bool,
+        /// Print what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// What became of one file.
+enum Outcome {
+    Imported,
+    Skipped(String),
+}
+
+pub fn run(args: &ImportArgs) -> Result<()> {
+    let Some(ImportCommand::Md { paths, mtime_as_created, dry_run }) = &args.command else {
+        return import_archive(args.archive.as_deref().unwrap_or(Path::new("")), args.dry_run);
+    };
+    let project_dir = get_project_path()?;
+    let mut known: HashSet<Uuid> = load_entries(&project_dir)?.into_iter().map(|entry| entry.metadata.id).collect();
+    let (mut imported, mut skipped, mut failed) = (0, 0, 0);
+    for path in expand(paths)? {
+        match import(&project_dir, &path, *mtime_as_created, *dry_run, &mut known) {
+            Ok(Outcome::Imported) => imported += 1,
+            Ok(Outcome::Skipped(reason)) => {
+                skipped += 1;
+                say!("Skipped {}: {}", path.display(), reason);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("error: {}: {:#}", path.display(), e);
+            }
+        }
+    }
+    let verb = if *dry_run { "Would import" } else { "Imported" };
+    println!("{} {}, skipped {}, failed {}", verb, imported, skipped, failed);
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) could not be imported", failed));
+    }
+    Ok(())
+}
+
+/// Merges an archive into the data directory and sums up what happened to
+/// its tasks. Conflicts fail the command once everything else is imported.
+fn import_archive(archive: &Path, dry_run: bool) -> Result<()> {
+    let report = backup::import(archive, dry_run)?;
+    for conflict in &report.conflicts {
+        eprintln!("conflict: {}", conflict);
+    }
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {}: {} added, {} updated, {} identical, {} newer here, {} conflict(s)",
+        verb, archive.display(), report.added, report.updated, report.identical, report.kept, report.conflicts.len(),
+    );
+    if !report.conflicts.is_empty() {
+        return Err(anyhow!("{} task(s) changed on both sides were left as they are here", report.conflicts.len()));
+    }
+    Ok(())
+}
+
+fn import(project_dir: &Path, path: &Path, mtime_as_created: bool, dry_run: bool, known: &mut HashSet<Uuid>) -> Result<Outcome> {
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(Outcome::Skipped("the file is empty".to_string()));
+    }
+
+    // A td task already: keep the file byte for byte.
+    if Frontmatter::detect(&content).is_some() {
+        let task = Task::from_str(&content).map_err(|e| anyhow!("the frontmatter is not that of a td task: {}", e))?;
+        if let Some(problem) = task.metadata.tags.iter().find_map(|tag| tags::problem(tag)) {
+            return Err(anyhow!("{}, import it after fixing its tags", problem));
+        }
+        if !known.insert(task.metadata.id) {
+            return Ok(Outcome::Skipped(format!("task {} is already in the project", task.short_id())));
+        }
+        if dry_run {
+            println!("Would copy {} {}", task.short_id(), task.metadata.title);
+            return Ok(Outcome::Imported);
+        }
+        let target = task_path(project_dir, &task);
+        let temp_path = target.with_file_name(format!(".{}.tmp", target.file_name().unwrap_or_default().to_string_lossy()));
+        match crypt::is_encrypted(project_dir) {
+            true => fs::write(&temp_path, crypt::seal(project_dir, &task, Frontmatter::detect(&content).unwrap_or_default())?)?,
+            false => fs::write(&temp_path, &content)?,
+        }
+        fs::rename(&temp_path, &target)?;
+        journal::record(&target, task.metadata.id, &task.metadata.title, &format!("imported from {}", path.display()));
+        events::emit(Event::new(EventKind::Created, &target, &task.metadata, None))?;
+        say!("Copied {} {}", task.short_id(), task.metadata.title);
+        return Ok(Outcome::Imported);
+    }
+
+    let (title, description) = split_heading(&content);
+    let title = match title {
+        Some(title) => title,
+        None => path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+    };
+    let mut task = Task::new(&title);
+    task.description = description;
+    if mtime_as_created {
+        task.metadata.created_at = DateTime::<Utc>::from(fs::metadata(path)?.modified()?);
+    }
+    known.insert(task.metadata.id);
+    if dry_run {
+        println!("Would import {} as '{}'", path.display(), task.metadata.title);
+        return Ok(Outcome::Imported);
+    }
+    let target = save_task(&task_path(project_dir, &task), &task)?;
+    events::emit(Event::new(EventKind::Created, &target, &task.metadata, None))?;
+    say!("Imported {} {} from {}", task.short_id(), task.metadata.title, path.display());
+    Ok(Outcome::Imported)
+}
+
+/// The text of the first `# heading` and the file without it, along with the
+/// blank line after it. Blank lines left at the start of the description are
+/// dropped.
+fn split_heading(content: &str) -> (Option<String>, String) {
+    let mut lines = content.lines().peekable();
+    let mut before = Vec::new();
+    while let Some(line) = lines.next() {
+        if let Some(heading) = line.strip_prefix("# ").map(str::trim).filter(|heading| !heading.is_empty()) {
+            lines.next_if(|next| next.trim().is_empty());
+            let rest: Vec<&str> = before.into_iter().chain(lines).collect();
+            return (Some(heading.to_string()), description(&rest));
+        }
+        before.push(line);
+    }
+    (None, description(&before))
+}
+
+fn description(lines: &[&str]) -> String {
+    let text = lines.join("\n");
+    let text = text.trim_start_matches('\n').trim_end();
+    if text.is_empty() { String::new() } else { format!("{}\n", text) }
+}
+
+/// The files named by the arguments, relative to the working directory:
+/// directories give their `.md` files, and `*` and `?` in the last component
+/// match file names.
+fn expand(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = &resolve(path);
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if name.contains(['*', '?']) {
+            let dir = path.parent().unwrap_or(path);
+            let mut matched: Vec<PathBuf> = fs::read_dir(dir)
+                .map_err(|e| anyhow!("Could not read {}: {}", dir.display(), e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.is_file() && file.file_name().is_some_and(|file| wildcard(&name, &file.to_string_lossy())))
+                .collect();
+            if matched.is_empty() {
+                return Err(anyhow!("No files match '{}'", path.display()));
+            }
+            matched.sort();
+            files.extend(matched);
+        } else if path.is_dir() {
+            let mut matched: Vec<PathBuf> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.is_file() && file.extension().is_some_and(|extension| extension == "md"))
+                .collect();
+            matched.sort();
+            files.extend(matched);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `name` matches a pattern where `*` stands for any run of
+/// characters and `?` for a single one.
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::in_working_dir;
+
+    #[test]
+    fn paths_are_found_from_the_working_dir() {
+        let dir = std::env::temp_dir().join(format!("td-import-md-{}", std::process::id()));
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        for file in ["notes/a.md", "notes/b.md", "notes/c.txt", "todo.md"] {
+            fs::write(dir.join(file), "- a task").unwrap();
+        }
+        let expanded = in_working_dir(&dir, || {
+            (
+                expand(&[PathBuf::from("notes")]).unwrap(),
+                expand(&[PathBuf::from("notes/*.md")]).unwrap(),
+                expand(&[PathBuf::from("todo.md")]).unwrap(),
+            )
+        });
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(expanded.0, vec![dir.join("notes/a.md"), dir.join("notes/b.md")]);
+        assert_eq!(expanded.1, expanded.0);
+        assert_eq!(expanded.2, vec![dir.join("todo.md")]);
+    }
+}
@@ -0,0 +1,83 @@
+use clap::Args;
+use std::{fs, path::Path};
+use anyhow::{Result, anyhow};
+
+use crate::commands::{hook, project};
+use crate::config;
+use crate::project::{REMOTE_CONFIG_KEY, SUBDIRS_CONFIG_KEY, alias_for, open_repo, project_dir_in, td_home};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// A short name for the project, used with --project and in listings
+    #[arg(conflicts_with = "global")]
+    name: Option<String>,
+    /// Set up the global task list used outside of repositories instead
+    #[arg(long, conflicts_with_all = ["remote", "subdirs", "hooks"])]
+    global: bool,
+    /// A tag every new task in the project gets (can be repeated)
+    #[arg(long, short)]
+    tag: Vec<String>,
+    /// File this repository's tasks under this remote instead of the first
+    /// one from `remote_priority`. Stored in the repository's git config.
+    #[arg(long)]
+    remote: Option<String>,
+    /// Keep separate tasks for each subdirectory the commands are run from,
+    /// for monorepos. Stored in the repository's git config.
+    #[arg(long)]
+    subdirs: bool,
+    /// Install git hooks, such as one listing the DOING tasks in commit messages
+    #[arg(long)]
+    hooks: bool,
+    /// Replace existing hooks that were not installed by td
+    #[arg(long, requires = "hooks")]
+    force: bool,
+}
+
+pub fn run(args: &InitArgs) -> Result<()> {
+    if args.global {
+        let home = td_home()?;
+        fs::create_dir_all(&home)?;
+        set_default_tags(&home, &args.tag)?;
+        say!("The global task list is kept in {}", home.display());
+        return Ok(());
+    }
+    let repo = open_repo().map_err(|_| anyhow!("Not in a git repository, use --global to set up the global task list"))?;
+    if let Some(remote) = &args.remote {
+        repo.find_remote(remote).map_err(|_| anyhow!("The repository has no remote named '{}'", remote))?;
+        repo.config()?.open_level(git2::ConfigLevel::Local)?.set_str(REMOTE_CONFIG_KEY, remote)?;
+    }
+    if args.subdirs {
+        repo.config()?.open_level(git2::ConfigLevel::Local)?.set_bool(SUBDIRS_CONFIG_KEY, true)?;
+        say!("Tasks are now kept per subdirectory, run td from the repository root to see all of them");
+    }
+    if args.hooks {
+        hook::install(&repo, args.force)?;
+    }
+
+    let home = td_home()?;
+    let project_dir = project_dir_in(&home, config::get())?;
+    if project_dir == home {
+        say!("This repository has no remote, its tasks go to the global list in {}", home.display());
+    } else {
+        say!("Tasks for this repository are kept in {}", project_dir.display());
+    }
+    fs::create_dir_all(&project_dir)?;
+    if let Some(name) = &args.name {
+        let dir_name = project_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if alias_for(&dir_name, &config::get().projects) != Some(name.as_str()) {
+            project::alias(name)?;
+        }
+    }
+    set_default_tags(&project_dir, &args.tag)?;
+    Ok(())
+}
+
+fn set_default_tags(project_dir: &Path, tags: &[String]) -> Result<()> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    config::set_project_value(project_dir, "default_tags", &tags)?;
+    say!("New tasks will be tagged {}", tags.join(", "));
+    Ok(())
+}
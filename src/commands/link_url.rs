@@ -0,0 +1,58 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::project::{find_task, get_project_path, locked, save_task};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct LinkUrlArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// The address to link, like https://github.com/org/repo/pull/12
+    url: String,
+}
+
+/// Adds a link to something outside td, opened with `td open --url`.
+pub fn run(args: &LinkUrlArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let url = validate(&args.url)?;
+    locked(&project_dir, || {
+        let (path, mut task) = find_task(&project_dir, &args.id)?;
+        if task.metadata.links.contains(&url) {
+            say!("{} {} already links {}", task.short_id(), task.metadata.title, url);
+            return Ok(());
+        }
+        task.metadata.links.push(url.clone());
+        task.metadata.updated_at = Some(Utc::now());
+        save_task(&path, &task)?;
+        say!("Linked {} to {} {}", url, task.short_id(), task.metadata.title);
+        Ok(())
+    })
+}
+
+/// Checks that the text looks like an absolute URL: a scheme such as
+/// `https:` followed by something without whitespace, with a host for the
+/// schemes that have one.
+pub fn validate(url: &str) -> Result<String> {
+    let url = url.trim();
+    let invalid = |reason: &str| anyhow!("'{}' is not a valid URL: {}", url, reason);
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return Err(invalid("it has no scheme like https://"));
+    };
+    if scheme.is_empty() || !scheme.starts_with(|c: char| c.is_ascii_alphabetic()) || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+        return Err(invalid("it has no scheme like https://"));
+    }
+    if url.contains(char::is_whitespace) {
+        return Err(invalid("it contains whitespace"));
+    }
+    if matches!(scheme.to_lowercase().as_str(), "http" | "https" | "ftp") {
+        let host = rest.strip_prefix("//").map(|rest| rest.split(['/', '?', '#']).next().unwrap_or_default());
+        if host.is_none_or(str::is_empty) {
+            return Err(invalid("it has no host"));
+        }
+    } else if rest.is_empty() {
+        return Err(invalid("there is nothing after the scheme"));
+    }
+    Ok(url.to_string())
+}
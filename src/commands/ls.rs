@@ -0,0 +1,485 @@
+use chrono::{DateTime, Utc};
+use clap::{Args, ValueEnum};
+use std::{collections::{HashMap, HashSet}, io::IsTerminal};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::archive::ARCHIVE_DIR;
+use crate::color;
+use crate::commands::epic;
+use crate::config;
+use crate::crypt;
+use crate::dates;
+use crate::expr;
+use crate::context;
+use crate::filter::{Filter, FilterArgs};
+use crate::icons;
+use crate::index::{IndexEntry, load_entries};
+use crate::project::{current_branch, get_project_path, listing_dirs};
+use crate::sort::{SortKey, sort_by_urgency, sort_tasks};
+use crate::output::{self, OutputArgs, TaskView};
+use crate::task::{Task, TaskMetadata, TaskStatus};
+use crate::template::Template;
+use crate::urgency;
+use crate::width;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Status,
+    Tag,
+    Epic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortGroups {
+    /// Sections with the most open tasks first
+    #[default]
+    Count,
+    /// Sections in alphabetical order
+    Name,
+}
+
+/// The section that collects tasks without any tags when grouping by tag.
+const UNTAGGED: &str = "(untagged)";
+
+/// The section of tasks outside any epic when grouping by epic.
+const NO_EPIC: &str = "(no epic)";
+
+#[derive(Args, Debug)]
+#[command(args_override_self = true)]
+pub struct LsArgs {
+    /// A filter expression like 'status:todo tag:bug due<7d priority>=medium
+    /// "login" !tag:wontfix'. Words without a key are looked for in the
+    /// title and description
+    #[arg(value_name = "EXPR")]
+    terms: Vec<String>,
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Only the tasks of this epic
+    #[arg(long)]
+    epic: Option<String>,
+    /// The field to sort the tasks by
+    #[arg(long, value_enum, default_value_t)]
+    sort: SortKey,
+    /// Reverse the sort order
+    #[arg(long, short)]
+    reverse: bool,
+    /// Split the listing into sections
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+    /// Show sections that have no tasks
+    #[arg(long, requires = "group_by")]
+    show_empty: bool,
+    /// How to order the sections when grouping by tag
+    #[arg(long, value_enum, default_value_t, requires = "group_by")]
+    sort_groups: SortGroups,
+    /// Show subtasks indented beneath their parents
+    #[arg(long, conflicts_with_all = ["group_by", "output", "json", "yaml"])]
+    tree: bool,
+    /// Print section headers even when stdout is not a terminal
+    #[arg(long)]
+    headers: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Print each task with a format string like '{short_id} {status:>5} {title}'
+    #[arg(long, conflicts_with_all = ["output", "json", "yaml"])]
+    format: Option<String>,
+    /// Print only the full id of each task, one per line
+    #[arg(long, conflicts_with_all = ["format", "tree", "group_by", "output", "json", "yaml"])]
+    ids_only: bool,
+    /// End each id with a NUL byte instead of a newline, for `xargs -0`
+    #[arg(short = '0', long, requires = "ids_only")]
+    print0: bool,
+    /// In a repository with per-subdirectory tasks, list those of every
+    /// subdirectory instead of only the current one
+    #[arg(long)]
+    repo: bool,
+    /// List the archived tasks instead of the current ones
+    #[arg(long)]
+    archived: bool,
+    /// Show when each task was created as a date and time instead of how
+    /// long ago
+    #[arg(long)]
+    absolute: bool,
+    /// With `branch_scope` on, also list the tasks filed under other branches
+    #[arg(long, conflicts_with = "branch")]
+    all_branches: bool,
+    /// Start from the flags saved under this name in `[queries]`. Flags
+    /// given here override those of the query, repeatable ones add to them
+    #[arg(long)]
+    query: Option<String>,
+}
+
+pub fn run(args: &LsArgs) -> Result<()> {
+    // Queries are expanded before parsing, one left over came from a query.
+    if let Some(query) = &args.query {
+        return Err(anyhow!("A saved query can not use another one (--query {})", query));
+    }
+    let template = match args.format.as_ref().or(config::get().default_format.as_ref()) {
+        Some(format) => Some(Template::parse(format)?),
+        None => None,
+    };
+    let mut filter = args.filter.filter()?;
+    for expression in &args.terms {
+        filter.terms.extend(expr::parse(expression)?);
+    }
+    if let Some(id) = &args.epic {
+        filter.epic = Some(epic::find(&get_project_path()?, id)?.1.metadata.id);
+    }
+    let context = context::apply(&mut filter)?;
+    if filter.statuses.is_empty() && config::get().hide_done && !args.archived {
+        filter.statuses = vec![TaskStatus::TODO, TaskStatus::DOING];
+    }
+    let mut all_tasks = Vec::new();
+    for dir in listing_dirs(args.repo)? {
+        let dir = if args.archived { dir.join(ARCHIVE_DIR) } else { dir };
+        if dir.is_dir() {
+            all_tasks.extend(load_entries(&dir)?);
+        }
+    }
+    // Files with an unknown status are listed on their own after the rest,
+    // never filtered, so they are not missed.
+    let (all_tasks, unreadable): (Vec<IndexEntry>, Vec<IndexEntry>) = all_tasks.into_iter().partition(|task| task.problem.is_none());
+    let all_ids: HashSet<Uuid> = all_tasks.iter().map(|task| task.metadata.id).collect();
+    let epics: Vec<(Uuid, String)> = all_tasks.iter()
+        .filter(|task| task.metadata.is_epic())
+        .map(|task| (task.metadata.id, format!("{} {}", task.metadata.short_id(), task.metadata.title)))
+        .collect();
+    // Scored before filtering so a blocker that is not listed still counts.
+    let statuses: HashMap<Uuid, TaskStatus> = all_tasks.iter().map(|task| (task.metadata.id, task.metadata.status)).collect();
+    let blocked: HashSet<Uuid> = all_tasks.iter()
+        .filter(|task| task.metadata.is_blocked(|id| statuses.get(&id).copied()))
+        .map(|task| task.metadata.id)
+        .collect();
+    let scores = (args.sort == SortKey::Urgency).then(|| urgency::scores(all_tasks.iter().map(|task| &task.metadata)));
+    let with_snoozed = Filter { snoozed: true, ..filter.clone() };
+    // The index has no descriptions, the files are only read when the
+    // expression looks for words.
+    let matches = |filter: &Filter, task: &IndexEntry| if filter.needs_description() {
+        crypt::read(&task.path).ok()
+            .and_then(|content| Task::from_str(&content).ok())
+            .is_some_and(|full| filter.matches_task(&full))
+    } else {
+        filter.matches(&task.metadata)
+    };
+    let (tasks, snoozed): (Vec<IndexEntry>, Vec<IndexEntry>) = all_tasks
+        .into_iter()
+        .filter(|task| matches(&with_snoozed, task))
+        .partition(|task| matches(&filter, task));
+    // Tasks without a branch belong everywhere, only those filed under
+    // another branch are left out.
+    let scope = if config::get().branch_scope && !args.all_branches && filter.branch.is_none() { current_branch() } else { None };
+    let (mut tasks, other_branches): (Vec<IndexEntry>, Vec<IndexEntry>) = tasks
+        .into_iter()
+        .partition(|task| scope.is_none() || task.metadata.branch.is_none() || task.metadata.branch == scope);
+    match &scores {
+        Some(scores) => sort_by_urgency(&mut tasks, scores, args.reverse, |task| &task.metadata),
+        None => sort_tasks(&mut tasks, args.sort, args.reverse, |task| &task.metadata),
+    }
+
+    if args.ids_only {
+        tasks.iter().for_each(|task| output::print_record(&task.metadata.id.to_string(), args.print0));
+        return Ok(());
+    }
+    if let Some(context) = &context && !args.output.is_structured() && show_headers(args) {
+        println!("context: {}\n", context);
+    }
+    if args.tree {
+        print_tree(&tasks, &all_ids, &Layout::new(&tasks, template, scores, blocked, args.absolute));
+    } else {
+        match args.group_by {
+            None if args.output.is_structured() => {
+                let views: Vec<TaskView> = tasks.iter().map(TaskView::from_entry).collect();
+                output::print(args.output.format(), &views)?;
+            }
+            None => {
+                let layout = Layout::new(&tasks, template, scores, blocked, args.absolute);
+                tasks.iter().for_each(|task| print_task(task, &layout));
+            }
+            Some(group_by) => print_groups(args, &groups(group_by, args.sort_groups, &tasks, &epics), &Layout::new(&tasks, template, scores, blocked, args.absolute))?,
+        }
+    }
+
+    for task in &unreadable {
+        let problem = task.problem.as_deref().unwrap_or_default();
+        if args.output.is_structured() || args.format.is_some() {
+            eprintln!("warning: skipping {}: {}", task.path.display(), problem);
+        } else {
+            println!("{}  {:<5}  {}  ({}: {})", task.metadata.short_id(), "?", task.metadata.title, task.path.display(), problem);
+        }
+    }
+    if !snoozed.is_empty() && !args.output.is_structured() && show_headers(args) {
+        println!("\n{} snoozed (use --snoozed to show)", snoozed.len());
+    }
+    if !other_branches.is_empty() && !args.output.is_structured() && show_headers(args) {
+        println!("\n{} tasks on other branches (use --all-branches to show)", other_branches.len());
+    }
+    Ok(())
+}
+
+fn show_headers(args: &LsArgs) -> bool {
+    args.headers || std::io::stdout().is_terminal()
+}
+
+/// Splits already sorted tasks into named sections, keeping the order within
+/// each. `epics` are the ids and names of every epic, listed or not.
+fn groups<'a>(group_by: GroupBy, sort_groups: SortGroups, tasks: &'a [IndexEntry], epics: &[(Uuid, String)]) -> Vec<(String, Vec<&'a IndexEntry>)> {
+    match group_by {
+        GroupBy::Status => TaskStatus::value_variants()
+            .iter()
+            .map(|status| {
+                let members = tasks.iter().filter(|task| task.metadata.status == *status).collect();
+                (format!("{:?}", status), members)
+            })
+            .collect(),
+        GroupBy::Tag => {
+            let mut groups: Vec<(String, Vec<&IndexEntry>)> = Vec::new();
+            for task in tasks {
+                for tag in &task.metadata.tags {
+                    match groups.iter_mut().find(|(name, _)| name == tag) {
+                        Some((_, members)) => members.push(task),
+                        None => groups.push((tag.clone(), vec![task])),
+                    }
+                }
+            }
+            let open_count = |members: &Vec<&IndexEntry>| {
+                members.iter().filter(|task| task.metadata.status != TaskStatus::DONE).count()
+            };
+            match sort_groups {
+                SortGroups::Count => groups.sort_by(|(a_name, a), (b_name, b)| {
+                    open_count(b).cmp(&open_count(a)).then_with(|| a_name.cmp(b_name))
+                }),
+                SortGroups::Name => groups.sort_by_key(|(name, _)| name.to_lowercase()),
+            }
+            let untagged = tasks.iter().filter(|task| task.metadata.tags.is_empty()).collect();
+            groups.push((UNTAGGED.to_string(), untagged));
+            groups
+        }
+        // An epic heads its own section, so it is not counted as outside one.
+        GroupBy::Epic => {
+            let in_epic = |task: &IndexEntry, epic: Uuid| task.metadata.epic == Some(epic) || task.metadata.id == epic;
+            let mut groups: Vec<(String, Vec<&IndexEntry>)> = epics.iter()
+                .map(|(id, name)| (name.clone(), tasks.iter().filter(|task| in_epic(task, *id)).collect()))
+                .collect();
+            let outside = tasks.iter()
+                .filter(|task| !epics.iter().any(|(id, _)| in_epic(task, *id)))
+                .collect();
+            groups.push((NO_EPIC.to_string(), outside));
+            groups
+        }
+    }
+}
+
+fn print_groups(args: &LsArgs, groups: &[(String, Vec<&IndexEntry>)], layout: &Layout) -> Result<()> {
+    let groups = groups.iter().filter(|(_, members)| args.show_empty || !members.is_empty());
+
+    if args.output.is_structured() {
+        let mut object = serde_yaml::Mapping::new();
+        for (name, members) in groups {
+            let views: Vec<TaskView> = members.iter().map(|task| TaskView::from_entry(task)).collect();
+            let key = match args.group_by {
+                Some(GroupBy::Status) => name.to_lowercase(),
+                _ => name.clone(),
+            };
+            object.insert(key.into(), serde_yaml::to_value(views)?);
+        }
+        output::print(args.output.format(), &object)?;
+        return Ok(());
+    }
+
+    let headers = show_headers(args);
+    for (i, (name, members)) in groups.enumerate() {
+        if headers {
+            if i > 0 {
+                println!();
+            }
+            println!("{} ({})", name, members.len());
+            if members.is_empty() {
+                println!("(none)");
+            }
+        }
+        members.iter().for_each(|task| print_task(task, layout));
+    }
+    Ok(())
+}
+
+/// Prints tasks with their children indented beneath them. Children whose
+/// parent is filtered out are shown at the top level, and children whose parent
+/// no longer exists are flagged as orphans.
+fn print_tree(tasks: &[IndexEntry], all_ids: &HashSet<Uuid>, layout: &Layout) {
+    let listed: HashSet<Uuid> = tasks.iter().map(|task| task.metadata.id).collect();
+    let mut printed = HashSet::new();
+
+    fn print_subtree(task: &IndexEntry, tasks: &[IndexEntry], layout: &Layout, depth: usize, flag: &str, printed: &mut HashSet<Uuid>) {
+        if !printed.insert(task.metadata.id) {
+            return;
+        }
+        println!("{}{}", "    ".repeat(depth), task_line(task, layout, depth * 4, flag));
+        for child in tasks.iter().filter(|child| child.metadata.parent == Some(task.metadata.id)) {
+            print_subtree(child, tasks, layout, depth + 1, "", printed);
+        }
+    }
+
+    for task in tasks {
+        match task.metadata.parent {
+            None => print_subtree(task, tasks, layout, 0, "", &mut printed),
+            Some(parent) if !all_ids.contains(&parent) => print_subtree(task, tasks, layout, 0, "  (orphan)", &mut printed),
+            Some(parent) if !listed.contains(&parent) => print_subtree(task, tasks, layout, 0, "", &mut printed),
+            Some(_) => {}
+        }
+    }
+    // Tasks that are part of a parent cycle are never reached from the top level.
+    for task in tasks {
+        print_subtree(task, tasks, layout, 0, "  (cycle)", &mut printed);
+    }
+}
+
+fn print_task(task: &IndexEntry, layout: &Layout) {
+    println!("{}", task_line(task, layout, 0, ""));
+}
+
+/// Titles are never shrunk below this many cells to make room for tags.
+const MIN_TITLE_WIDTH: usize = 10;
+
+/// How many cells the checklist bar of a row takes.
+const PROGRESS_CELLS: usize = 5;
+
+/// The widths of the optional columns, sized to the tasks being listed so a
+/// column nobody uses takes no space.
+struct Layout {
+    age: usize,
+    priority: usize,
+    due: usize,
+    checklist: usize,
+    /// Each task's urgency, shown in a column when sorting by it
+    urgency: Option<HashMap<Uuid, f64>>,
+    /// Tasks waiting for an open blocker, marked with the blocked icon
+    blocked: HashSet<Uuid>,
+    terminal: Option<usize>,
+    /// Replaces the columns entirely when a format string is in use
+    template: Option<Template>,
+    /// Ages are shown as the creation time instead, for `--absolute`
+    absolute: bool,
+    now: DateTime<Utc>,
+}
+
+impl Layout {
+    fn new(tasks: &[IndexEntry], template: Option<Template>, urgency: Option<HashMap<Uuid, f64>>, blocked: HashSet<Uuid>, absolute: bool) -> Self {
+        let widest = |f: &dyn Fn(&IndexEntry) -> usize| tasks.iter().map(f).max().unwrap_or(0);
+        let mut layout = Layout {
+            age: 0,
+            priority: widest(&|t| t.metadata.priority.map_or(0, |p| format!("{:?}", p).len())),
+            due: widest(&|t| t.metadata.due.map_or(0, |due| width::display_width(&dates::format_day(due)))),
+            checklist: widest(&|t| width::display_width(&progress(t))),
+            urgency,
+            blocked,
+            terminal: width::terminal_width(),
+            template,
+            absolute,
+            now: Utc::now(),
+        };
+        layout.age = widest(&|t| width::display_width(&layout.age(&t.metadata)));
+        layout
+    }
+
+    /// How long ago the task was created, or when with `--absolute`.
+    fn age(&self, meta: &TaskMetadata) -> String {
+        match self.absolute {
+            true => dates::format_time(meta.created_at),
+            false => dates::age(meta.created_at, self.now),
+        }
+    }
+}
+
+/// The checklist of a task as a bar and how many items are done, empty
+/// without one.
+fn progress(task: &IndexEntry) -> String {
+    let checklist = &task.checklist;
+    if checklist.total == 0 {
+        return String::new();
+    }
+    format!("{} {}", icons::get().bar(checklist.done, checklist.total, PROGRESS_CELLS), checklist.fraction())
+}
+
+/// One row of the listing. On a terminal the title is shortened so the row
+/// fits its width after `indent` cells; piped output is never truncated.
+fn task_line(task: &IndexEntry, layout: &Layout, indent: usize, flag: &str) -> String {
+    if let Some(template) = &layout.template {
+        return format!("{}{}", template.render(task), flag);
+    }
+    let meta = &task.metadata;
+    let status = format!("{} {:<5}", icons::get().status(meta.status), format!("{:?}", meta.status));
+    let column = |text: String, width: usize| if width == 0 { String::new() } else { format!("{}  ", width::pad(&text, width)) };
+    let urgency = layout.urgency.as_ref()
+        .map(|scores| format!("{:>5.1}  ", scores.get(&meta.id).copied().unwrap_or_default()))
+        .unwrap_or_default();
+    let age = layout.age(meta);
+    let age = format!("{}{}  ", " ".repeat(layout.age.saturating_sub(width::display_width(&age))), age);
+    let columns = |status: &str| format!(
+        "{}  {}  {}{}{}{}{}",
+        meta.short_id(),
+        status,
+        urgency,
+        age,
+        column(meta.priority.map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_default(), layout.priority),
+        column(meta.due.map(dates::format_day).unwrap_or_default(), layout.due),
+        column(progress(task), layout.checklist),
+    );
+    let prefix = columns(&color::status(&status, meta.status));
+    let tags = |tags: String| if meta.tags.is_empty() { String::new() } else { format!("  [{}]", tags) };
+    let blocked = if layout.blocked.contains(&meta.id) { format!("  {}", icons::get().blocked) } else { String::new() };
+    let stale = config::get().stale_after_days.is_some_and(|days| meta.is_stale(days, Utc::now()));
+    let flags = format!("{}{}{}{}", blocked, if meta.start_overdue() { "  (should have started)" } else { "" }, if stale { "  (stale)" } else { "" }, flag);
+
+    let Some(width) = layout.terminal.map(|w| w.saturating_sub(indent)) else {
+        return format!("{}{}{}{}", prefix, meta.title, tags(color::tags(&meta.tags)), flags);
+    };
+    let fixed = width::display_width(&columns(&status));
+    let plain_suffix = format!("{}{}", tags(meta.tags.join(", ")), flags);
+    let available = width.saturating_sub(fixed + width::display_width(&plain_suffix));
+    let title_width = width::display_width(&meta.title);
+    if title_width <= available {
+        return format!("{}{}{}{}", prefix, meta.title, tags(color::tags(&meta.tags)), flags);
+    }
+    if available >= MIN_TITLE_WIDTH {
+        return format!("{}{}{}{}", prefix, width::truncate(&meta.title, available), tags(color::tags(&meta.tags)), flags);
+    }
+    // Too narrow for the tags as well: keep a minimal title and cut the rest.
+    let title = width::truncate(&meta.title, MIN_TITLE_WIDTH.min(width.saturating_sub(fixed)));
+    let rest = width.saturating_sub(fixed + width::display_width(&title));
+    let suffix = if rest > 4 { width::truncate(&plain_suffix, rest) } else { String::new() };
+    format!("{}{}{}", prefix, title, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checklist::Progress;
+
+    fn entry(done: usize, total: usize) -> IndexEntry {
+        IndexEntry { path: "a.td".into(), metadata: Task::new("a").metadata, checklist: Progress { done, total }, problem: None }
+    }
+
+    #[test]
+    fn rows_show_the_status_and_checklist_with_icons() {
+        assert_eq!(progress(&entry(1, 3)), "##--- 1/3");
+        assert_eq!(progress(&entry(0, 0)), "");
+        let tasks = [entry(1, 3), entry(0, 0)];
+        let layout = Layout { terminal: None, ..Layout::new(&tasks, None, None, HashSet::new(), false) };
+        let line = task_line(&tasks[1], &layout, 0, "");
+        // The empty checklist cell is as wide as the bar of the other row.
+        assert!(line.starts_with(&format!("{}  [ ] TODO   0m  {}  a", tasks[1].metadata.short_id(), " ".repeat(9))), "{}", line);
+    }
+
+    #[test]
+    fn ages_are_relative_unless_absolute() {
+        let mut task = entry(0, 0);
+        task.metadata.created_at = Utc::now() - chrono::Duration::days(3);
+        let tasks = [task];
+        let relative = Layout { terminal: None, ..Layout::new(&tasks, None, None, HashSet::new(), false) };
+        assert!(task_line(&tasks[0], &relative, 0, "").contains(" 3d  a"));
+        let absolute = Layout { terminal: None, ..Layout::new(&tasks, None, None, HashSet::new(), true) };
+        let created = dates::format_time(tasks[0].metadata.created_at);
+        assert!(task_line(&tasks[0], &absolute, 0, "").contains(&format!(" {}  a", created)));
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::Utc;
+use std::path::Path;
+use anyhow::Result;
+
+use crate::commands::epic;
+use crate::events::{self, Event, EventKind};
+use crate::project::{get_project_path, load_tasks, update_task};
+use crate::say;
+use crate::task::{Task, TaskStatus};
+
+pub fn run(id: &str, status: TaskStatus) -> Result<()> {
+    mark(&get_project_path()?, id, status)?;
+    Ok(())
+}
+
+/// Moves a task of the project to `status`, warning when a task is completed
+/// while some of its children are still open. Returns the task as saved.
+pub fn mark(project_dir: &Path, id: &str, status: TaskStatus) -> Result<Task> {
+    let mut old_status = status;
+    let (path, task) = update_task(project_dir, id, |task| {
+        if status == TaskStatus::DONE {
+            let open_children: Vec<_> = load_tasks(project_dir)?
+                .into_iter()
+                .map(|(_, child)| child)
+                .filter(|child| child.metadata.parent == Some(task.metadata.id))
+                .filter(|child| child.metadata.status != TaskStatus::DONE)
+                .collect();
+            if !open_children.is_empty() {
+                eprintln!("warning: {} still has {} open subtask(s):", task.short_id(), open_children.len());
+                for child in &open_children {
+                    eprintln!("  {}  {:?}  {}", child.short_id(), child.metadata.status, child.metadata.title);
+                }
+            }
+        }
+
+        if status == TaskStatus::DONE && task.metadata.is_epic() {
+            let tasks: Vec<_> = load_tasks(project_dir)?.into_iter().map(|(_, task)| task.metadata).collect();
+            epic::warn_open(&task.metadata, &tasks);
+        }
+
+        old_status = task.metadata.status;
+        task.metadata.status = status;
+        task.metadata.updated_at = Some(Utc::now());
+        Ok(())
+    })?;
+    // Confirmed only once the hooks had their say, one may undo the change.
+    if old_status != status {
+        events::emit(Event::new(EventKind::StatusChanged, &path, &task.metadata, Some(old_status)))?;
+    }
+    say!("{} {} is now {:?}", task.short_id(), task.metadata.title, status);
+    Ok(task)
+}
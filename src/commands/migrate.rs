@@ -0,0 +1,72 @@
+use clap::Args;
+use std::fs;
+use anyhow::Result;
+
+use crate::config;
+use crate::crypt;
+use crate::index::load_entries;
+use crate::project::{locked, repo_project_path, with_scopes};
+use crate::say;
+use crate::task::{Frontmatter, Task, TaskStatus};
+
+#[derive(Args, Debug)]
+#[command(group = clap::ArgGroup::new("what").required(true).multiple(true))]
+pub struct MigrateArgs {
+    /// Rewrite every task file of the project with this frontmatter style
+    #[arg(long, value_enum, group = "what")]
+    frontmatter: Option<Frontmatter>,
+    /// Rewrite statuses written like `TODO` or `wip` as td writes them
+    #[arg(long, group = "what")]
+    status: bool,
+    /// Print the files that would be rewritten without changing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Converts the task files of the current project, sub-scopes included. A new
+/// frontmatter style is also set in its config so later writes keep it.
+pub fn run(args: &MigrateArgs) -> Result<()> {
+    let project_dir = repo_project_path()?;
+    let mut converted = 0;
+    locked(&project_dir, || {
+        for dir in with_scopes(&project_dir)? {
+            for entry in load_entries(&dir)? {
+                let path = entry.path;
+                if let Some(problem) = entry.problem {
+                    eprintln!("warning: skipping {}: {}", path.display(), problem);
+                    continue;
+                }
+                let content = crypt::read(&path)?;
+                let current = Frontmatter::detect(&content).unwrap_or_default();
+                let style = args.frontmatter.unwrap_or(current);
+                let legacy_status = args.status && Task::raw_status(&content)
+                    .and_then(|raw| TaskStatus::parse(&raw))
+                    .is_some_and(|(_, canonical)| !canonical);
+                if style == current && !legacy_status {
+                    continue;
+                }
+                converted += 1;
+                if args.dry_run {
+                    println!("Would convert {}", path.display());
+                    continue;
+                }
+                let task = Task::from_str(&content)?;
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+                fs::write(&temp_path, crypt::seal(&dir, &task, style)?)?;
+                fs::rename(&temp_path, &path)?;
+            }
+        }
+        Ok(())
+    })?;
+    let target = args.frontmatter.map(|style| format!(" to {} frontmatter", format!("{:?}", style).to_lowercase())).unwrap_or_default();
+    if args.dry_run {
+        println!("Would convert {} task file(s){}", converted, target);
+        return Ok(());
+    }
+    if let Some(style) = args.frontmatter && config::get().frontmatter != style {
+        config::set_project_value(&project_dir, "frontmatter", &format!("{:?}", style).to_lowercase())?;
+    }
+    say!("Converted {} task file(s){}", converted, target);
+    Ok(())
+}
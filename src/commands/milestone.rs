@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow};
+
+use crate::color;
+use crate::commands::show::PROGRESS_CELLS;
+use crate::config;
+use crate::crypt;
+use crate::dates;
+use crate::icons;
+use crate::index::load_index;
+use crate::journal;
+use crate::milestone::{self, Milestone};
+use crate::project::{load_tasks, locked, repo_project_path, with_scopes};
+use crate::say;
+use crate::task::{Task, TaskMetadata};
+
+#[derive(Subcommand, Debug)]
+pub enum MilestoneCommand {
+    /// Add a milestone, which tasks are filed under with `--milestone`
+    Add {
+        name: String,
+        /// When the milestone should be reached, e.g. 2024-09-01 or "next friday"
+        #[arg(long, value_parser = dates::parse_when)]
+        due: Option<DateTime<Utc>>,
+    },
+    /// List the milestones with their due dates and progress
+    #[command(visible_alias = "ls")]
+    List,
+    /// Show a milestone and its tasks
+    Show { name: String },
+    /// Rename a milestone along with every task filed under it
+    Rename { old: String, new: String },
+}
+
+pub fn run(command: &MilestoneCommand) -> Result<()> {
+    let project_dir = repo_project_path()?;
+    match command {
+        MilestoneCommand::Add { name, due } => {
+            let name = valid_name(name)?;
+            let mut milestones = milestone::load(&project_dir)?;
+            if milestones.iter().any(|milestone| milestone.name == name) {
+                return Err(anyhow!("There already is a milestone '{}'", name));
+            }
+            milestones.push(Milestone { name: name.to_string(), due: *due });
+            milestone::save(&project_dir, &milestones)?;
+            say!("Added milestone {}", name);
+        }
+        MilestoneCommand::List => {
+            let milestones = milestone::load(&project_dir)?;
+            if milestones.is_empty() {
+                println!("No milestones, add one with `td milestone add <name> --due <date>`");
+            }
+            let tasks = all_tasks(&project_dir)?;
+            let width = milestones.iter().map(|milestone| milestone.name.chars().count()).max().unwrap_or(0);
+            for milestone in &milestones {
+                let (open, done) = milestone.counts(&tasks);
+                let due = milestone.due.map(|due| format!("  due {} ({})", dates::format_day(due), dates::due_in(due))).unwrap_or_default();
+                let flag = if milestone.is_overdue() && open > 0 { "  overdue" } else { "" };
+                println!("{:<width$}  {} {} open, {} done{}{}", milestone.name, icons::get().bar(done, open + done, PROGRESS_CELLS), open, done, due, flag, width = width);
+            }
+        }
+        MilestoneCommand::Show { name } => {
+            let milestone = milestone::find(&project_dir, name)?;
+            let tasks = all_tasks(&project_dir)?;
+            let (open, done) = milestone.counts(&tasks);
+            println!("{}", milestone.name);
+            if let Some(due) = milestone.due {
+                println!("due:      {} ({})", dates::format_day(due), dates::due_in(due));
+            }
+            println!("progress: {} {}/{} done", icons::get().bar(done, open + done, PROGRESS_CELLS), done, open + done);
+            let members: Vec<&TaskMetadata> = tasks.iter().filter(|meta| meta.milestone.as_ref() == Some(&milestone.name)).collect();
+            if !members.is_empty() {
+                println!("\ntasks:");
+            }
+            for meta in members {
+                let status = format!("{:<5}", format!("{:?}", meta.status));
+                println!("  {} {}  {}  {}", icons::get().status(meta.status), meta.short_id(), color::status(&status, meta.status), meta.title);
+            }
+        }
+        MilestoneCommand::Rename { old, new } => {
+            let new = valid_name(new)?;
+            let mut milestones = milestone::load(&project_dir)?;
+            let old = milestone::find(&project_dir, old)?.name;
+            if milestones.iter().any(|milestone| milestone.name == new) {
+                return Err(anyhow!("There already is a milestone '{}'", new));
+            }
+            let renamed = rename_tasks(&project_dir, &old, new)?;
+            for milestone in milestones.iter_mut().filter(|milestone| milestone.name == old) {
+                milestone.name = new.to_string();
+            }
+            milestone::save(&project_dir, &milestones)?;
+            say!("Renamed milestone {} to {} on {} task(s)", old, new, renamed);
+        }
+    }
+    Ok(())
+}
+
+fn valid_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['\n', ',']) {
+        return Err(anyhow!("'{}' is not a valid milestone name", name));
+    }
+    Ok(name)
+}
+
+fn all_tasks(project_dir: &Path) -> Result<Vec<TaskMetadata>> {
+    let mut tasks = Vec::new();
+    for dir in with_scopes(project_dir)? {
+        tasks.extend(load_index(&dir)?.into_iter().map(|entry| entry.metadata));
+    }
+    Ok(tasks)
+}
+
+/// Moves every task of the milestone to the new name. All files are written
+/// to temporary files first, and only renamed into place once every one of
+/// them was written, so a failure leaves all tasks as they were.
+fn rename_tasks(project_dir: &Path, old: &str, new: &str) -> Result<usize> {
+    locked(project_dir, || {
+        let mut changed: Vec<(PathBuf, Task, Task)> = Vec::new();
+        for dir in with_scopes(project_dir)? {
+            for (path, task) in load_tasks(&dir)? {
+                if task.metadata.milestone.as_deref() == Some(old) {
+                    let mut renamed = task.clone();
+                    renamed.metadata.milestone = Some(new.to_string());
+                    renamed.metadata.updated_at = Some(Utc::now());
+                    changed.push((path, task, renamed));
+                }
+            }
+        }
+        let temp_path = |path: &PathBuf| path.with_file_name(format!(".{}.tmp", path.file_name().unwrap_or_default().to_string_lossy()));
+        for (i, (path, _, renamed)) in changed.iter().enumerate() {
+            if let Err(e) = crypt::seal(path.parent().unwrap_or(path), renamed, config::get().frontmatter).and_then(|content| Ok(fs::write(temp_path(path), content)?)) {
+                for (path, _, _) in &changed[..=i] {
+                    let _ = fs::remove_file(temp_path(path));
+                }
+                return Err(e);
+            }
+        }
+        for (path, task, renamed) in &changed {
+            fs::rename(temp_path(path), path)?;
+            journal::record_change(path, Some(task), renamed);
+        }
+        Ok(changed.len())
+    })
+}
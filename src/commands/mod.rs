@@ -0,0 +1,51 @@
+pub mod add;
+pub mod archive;
+pub mod block;
+pub mod board;
+pub mod changelog;
+pub mod check;
+pub mod clean;
+pub mod config;
+pub mod context;
+pub mod doctor;
+pub mod due;
+pub mod edit;
+pub mod encrypt;
+pub mod epic;
+pub mod export;
+pub mod hook;
+pub mod history;
+pub mod import;
+pub mod init;
+pub mod link_url;
+pub mod ls;
+pub mod mark;
+pub mod migrate;
+pub mod milestone;
+pub mod mv;
+pub mod next;
+pub mod notify;
+pub mod open;
+pub mod path;
+pub mod pick;
+pub mod project;
+pub mod projects;
+pub mod prompt;
+pub mod queries;
+pub mod reindex;
+pub mod relate;
+pub mod remind;
+pub mod rm;
+pub mod rename;
+pub mod search;
+pub mod serve;
+pub mod set;
+pub mod show;
+pub mod snooze;
+pub mod stale;
+pub mod standup;
+pub mod stats;
+pub mod status;
+pub mod tag;
+pub mod tags;
+pub mod webhook;
@@ -0,0 +1,69 @@
+use clap::Args;
+use std::{fs, path::Path};
+use anyhow::{Result, anyhow};
+
+use crate::crypt;
+use crate::index::load_index;
+use crate::journal;
+use crate::project::{find_project, find_task, free_task_path, locked, project_name, project_root, repo_project_path, scope_name};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct MvArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// Move the task to the sub-scope of this directory, relative to the
+    /// repository root; `.` is the root itself
+    #[arg(long, required_unless_present = "to", conflicts_with = "to")]
+    scope: Option<String>,
+    /// Move the task to the project with this alias or directory name
+    #[arg(long)]
+    to: Option<String>,
+}
+
+pub fn run(args: &MvArgs) -> Result<()> {
+    let repo_dir = repo_project_path()?;
+    let (task, target_dir) = locked(&repo_dir, || {
+        // The task may sit in any sub-scope of the repository, not just the current one.
+        let (path, task) = find_task(&repo_dir, &args.id)?;
+        let target_dir = match (&args.scope, &args.to) {
+            (Some(scope), _) => {
+                let root = repo_dir.clone();
+                match scope_name(scope.trim_start_matches("./")) {
+                    name if name.is_empty() => root,
+                    name if name.starts_with('.') => return Err(anyhow!("'{}' is not a usable scope", scope)),
+                    name => root.join(name),
+                }
+            }
+            (None, Some(project)) => find_project(project)?,
+            (None, None) => unreachable!("clap requires a destination"),
+        };
+        if path.parent() == Some(target_dir.as_path()) {
+            return Err(anyhow!("The task {} is already in {}", task.short_id(), target_dir.display()));
+        }
+        fs::create_dir_all(&target_dir)?;
+        if load_index(&target_dir)?.iter().any(|entry| entry.metadata.id == task.metadata.id) {
+            return Err(anyhow!("{} already has a task with the id {}", target_dir.display(), task.metadata.id));
+        }
+        let target = free_task_path(&target_dir, &task, None);
+        crypt::move_task(&path, &target, &task)?;
+        let changes = format!("moved from {} to {}", location(&path)?, location(&target)?);
+        journal::record(&target, task.metadata.id, &task.metadata.title, &changes);
+        if project_root(&target_dir)? != project_root(path.parent().unwrap_or(&path))? {
+            journal::record(&path, task.metadata.id, &task.metadata.title, &changes);
+        }
+        Ok((task, target_dir))
+    })?;
+    say!("Moved {} {} to {}", task.short_id(), task.metadata.title, target_dir.display());
+    Ok(())
+}
+
+/// Where a task file lives, as the project name and its sub-scope.
+fn location(task_path: &Path) -> Result<String> {
+    let dir = task_path.parent().unwrap_or(task_path);
+    let name = project_name(dir);
+    match dir.strip_prefix(project_root(dir)?) {
+        Ok(scope) if !scope.as_os_str().is_empty() => Ok(format!("{}/{}", name, scope.display())),
+        _ => Ok(name),
+    }
+}
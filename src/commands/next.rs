@@ -0,0 +1,64 @@
+use clap::Args;
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::commands::{mark, show};
+use crate::context;
+use crate::filter::Filter;
+use crate::project::{get_project_path, load_scoped_tasks};
+use crate::task::{Task, TaskStatus};
+use crate::urgency;
+
+#[derive(Args, Debug)]
+pub struct NextArgs {
+    /// Also mark the suggested task as DOING
+    #[arg(long)]
+    start: bool,
+    /// How many suggestions to show
+    #[arg(long, short = 'n', default_value_t = 1)]
+    count: usize,
+}
+
+pub fn run(args: &NextArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let tasks: Vec<Task> = load_scoped_tasks(&project_dir)?.into_iter().map(|(_, task)| task).collect();
+    let statuses: HashMap<Uuid, TaskStatus> = tasks.iter().map(|t| (t.metadata.id, t.metadata.status)).collect();
+    let mut filter = Filter::default();
+    let context = context::apply(&mut filter)?;
+    let scores = urgency::scores(tasks.iter().map(|t| &t.metadata));
+    let now = Utc::now();
+
+    let mut candidates: Vec<(f64, &Task)> = tasks
+        .iter()
+        .filter(|t| t.metadata.status != TaskStatus::DONE)
+        .filter(|t| !t.metadata.is_snoozed(now))
+        .filter(|t| filter.matches(&t.metadata))
+        .filter(|t| !t.metadata.is_blocked(|id| statuses.get(&id).copied()))
+        .map(|t| (scores[&t.metadata.id], t))
+        .collect();
+    // Most urgent first, the oldest task wins a tie.
+    candidates.sort_by(|(a_score, a), (b_score, b)| {
+        b_score.total_cmp(a_score).then(a.metadata.created_at.cmp(&b.metadata.created_at))
+    });
+
+    let Some((_, first)) = candidates.first() else {
+        match context {
+            Some(context) => println!("Nothing to do in the context '{}': every matching open task is blocked or snoozed", context),
+            None => println!("Nothing to do: every open task is blocked or snoozed"),
+        }
+        return Ok(());
+    };
+    for (i, (_, task)) in candidates.iter().take(args.count.max(1)).enumerate() {
+        if i > 0 {
+            println!();
+        }
+        show::print_task(task, &tasks);
+    }
+    if args.start && first.metadata.status != TaskStatus::DOING {
+        println!();
+        mark::run(&first.metadata.id.to_string(), TaskStatus::DOING)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use chrono::NaiveDate;
+use clap::Args;
+use std::{collections::BTreeMap, fs, process::Command};
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::dates;
+use crate::index::load_index;
+use crate::project::{list_projects, td_home};
+
+/// Remembers the day each task was last notified, so a timer running every
+/// hour only raises a notification once a day.
+const STATE_FILE: &str = ".notified.json";
+
+#[derive(Args, Debug)]
+pub struct NotifyArgs {
+    /// Notify deadlines up to this many days ahead instead of `within_days` from the config
+    #[arg(long)]
+    within: Option<i64>,
+    /// Raise a single notification listing all the tasks
+    #[arg(long)]
+    summary: bool,
+    /// Print what would be notified without notifying or remembering anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Raises desktop notifications for the due and overdue tasks of every
+/// project. Meant for timers, so without a notification service it prints
+/// the notifications instead of failing.
+pub fn run(args: &NotifyArgs) -> Result<()> {
+    let settings = &config::get().notify;
+    let within = args.within.unwrap_or(settings.within_days);
+    let today = dates::today();
+    let state_path = td_home()?.join(STATE_FILE);
+    let mut notified: BTreeMap<String, NaiveDate> = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    notified.retain(|_, day| *day == today);
+
+    let mut pending = Vec::new();
+    for (project, dir) in list_projects()? {
+        for task in load_index(&dir)? {
+            let meta = task.metadata;
+            if meta.due_within(within) && !notified.contains_key(&meta.id.to_string()) {
+                let due = meta.due.map(dates::due_in).unwrap_or_default();
+                pending.push((meta.id.to_string(), project.clone(), format!("{} ({})", meta.title, due)));
+            }
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let notifications: Vec<(String, String)> = if args.summary || settings.summary {
+        let body: Vec<String> = pending.iter().map(|(_, project, line)| format!("{}: {}", project, line)).collect();
+        vec![(format!("td: {} task(s) due", pending.len()), body.join("\n"))]
+    } else {
+        pending.iter().map(|(_, project, line)| (format!("td: {}", project), line.clone())).collect()
+    };
+    if args.dry_run {
+        notifications.iter().for_each(|(title, body)| println!("Would notify {}: {}", title, body));
+        return Ok(());
+    }
+    let mut desktop = true;
+    for (title, body) in &notifications {
+        if desktop && let Err(e) = send(title, body) {
+            eprintln!("note: no desktop notifications ({}), printing them instead", e);
+            desktop = false;
+        }
+        if !desktop {
+            println!("{}: {}", title, body);
+        }
+    }
+
+    notified.extend(pending.into_iter().map(|(id, _, _)| (id, today)));
+    let temp_path = state_path.with_file_name(format!("{}.tmp", STATE_FILE));
+    fs::write(&temp_path, serde_json::to_string(&notified)?)?;
+    fs::rename(&temp_path, &state_path)?;
+    Ok(())
+}
+
+/// Raises one notification with the desktop's own tool.
+fn send(title: &str, body: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut command = Command::new("osascript");
+        command.args(["-e", &format!("display notification {} with title {}", quote(body), quote(title))]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", "td", title, body]);
+        command
+    };
+    let output = command.output().map_err(|e| anyhow!("could not run {}: {}", command.get_program().to_string_lossy(), e))?;
+    if !output.status.success() {
+        return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use clap::Args;
+use std::process::Command;
+use anyhow::{Result, anyhow};
+
+use crate::project::{find_task, get_project_path};
+
+#[derive(Args, Debug)]
+pub struct OpenArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// Which of the task's links to open, counting from 1 as `td show` lists
+    /// them. Can be left out when the task has a single link
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "0")]
+    url: Option<usize>,
+}
+
+/// Opens a link of the task in the browser, through the platform's opener.
+pub fn run(args: &OpenArgs) -> Result<()> {
+    let (_, task) = find_task(&get_project_path()?, &args.id)?;
+    let links = &task.metadata.links;
+    let url = match (args.url.unwrap_or(0), links.len()) {
+        (_, 0) => return Err(anyhow!("{} has no links, add one with `td link-url {} <url>`", task.short_id(), task.short_id())),
+        (0, 1) => &links[0],
+        (0, count) => {
+            let list: Vec<String> = links.iter().enumerate().map(|(i, link)| format!("  {}. {}", i + 1, link)).collect();
+            return Err(anyhow!("{} has {} links, pick one with --url <n>:\n{}", task.short_id(), count, list.join("\n")));
+        }
+        (n, count) if n > count => return Err(anyhow!("{} has only {} link(s)", task.short_id(), count)),
+        (n, _) => &links[n - 1],
+    };
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url);
+    let output = command.output().map_err(|e| anyhow!("Could not run {}: {}", command.get_program().to_string_lossy(), e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Could not open {}: {}", url, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+use crate::output;
+use crate::project::{find_task_path, get_project_path};
+
+pub fn run(id: &str, print0: bool) -> Result<()> {
+    let path = find_task_path(&get_project_path()?, id)?;
+    output::print_record(&path.display().to_string(), print0);
+    Ok(())
+}
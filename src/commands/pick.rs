@@ -0,0 +1,68 @@
+use clap::Args;
+use std::collections::HashMap;
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::commands::{mark, show};
+use crate::context;
+use crate::filter::FilterArgs;
+use crate::output;
+use crate::project::{get_project_path, load_scoped_tasks};
+use crate::task::{Task, TaskStatus};
+use crate::urgency::{self, Rng};
+
+#[derive(Args, Debug)]
+pub struct PickArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Give every task the same chance instead of favoring urgent ones
+    #[arg(long)]
+    uniform: bool,
+    /// Also mark the picked task as DOING
+    #[arg(long)]
+    start: bool,
+    /// Pick the same task for the same seed, as long as the tasks and their
+    /// urgency stay the same
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+/// Picks one open, unblocked task at random, the more urgent the likelier.
+/// Exits with 1 when no task is left to pick from.
+pub fn run(args: &PickArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let tasks: Vec<Task> = load_scoped_tasks(&project_dir)?.into_iter().map(|(_, task)| task).collect();
+    let statuses: HashMap<Uuid, TaskStatus> = tasks.iter().map(|t| (t.metadata.id, t.metadata.status)).collect();
+    let mut filter = args.filter.filter()?;
+    let context = context::apply(&mut filter)?;
+    let scores = urgency::scores(tasks.iter().map(|t| &t.metadata));
+
+    let mut pool: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| t.metadata.status != TaskStatus::DONE)
+        .filter(|t| filter.matches_task(t))
+        .filter(|t| !t.metadata.is_blocked(|id| statuses.get(&id).copied()))
+        .collect();
+    // The files are read in no particular order, a seed needs a fixed one.
+    pool.sort_by_key(|t| (t.metadata.created_at, t.metadata.id));
+
+    let weights = if args.uniform {
+        vec![1.0; pool.len()]
+    } else {
+        urgency::pick_weights(&pool.iter().map(|t| scores[&t.metadata.id]).collect::<Vec<_>>())
+    };
+    let mut rng = args.seed.map(Rng::new).unwrap_or_else(Rng::from_time);
+    let Some(picked) = urgency::pick_weighted(&weights, &mut rng).map(|i| pool[i]) else {
+        match context {
+            Some(context) => println!("Nothing to pick in the context '{}': no open, unblocked task matches", context),
+            None => println!("Nothing to pick: no open, unblocked task matches"),
+        }
+        output::exit(1);
+    };
+    show::print_task(picked, &tasks);
+    if args.start && picked.metadata.status != TaskStatus::DOING {
+        println!();
+        mark::run(&picked.metadata.id.to_string(), TaskStatus::DOING)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use clap::Subcommand;
+use std::fs;
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::crypt;
+use crate::index::INDEX_FILE;
+use crate::journal;
+use crate::project::{alias_for, find_project, free_task_path, get_repo_remote, get_repo_remote_url, load_tasks, locked, remote_key, sanitize_dir_name, td_home};
+use crate::say;
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectCommand {
+    /// Give the project of the current repository a short name
+    Alias {
+        /// The name to use with --project and in listings
+        name: String,
+    },
+    /// Rename a project directory, e.g. after the remote moved
+    Rename {
+        /// The alias or directory name of the project
+        old: String,
+        /// The new directory name, usually the new remote URL
+        new: String,
+        /// Show what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move every task of one project into another
+    Merge {
+        /// The alias or directory name of the project to empty
+        src: String,
+        /// The alias or directory name of the project receiving the tasks
+        dst: String,
+        /// Show what would move without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+pub fn run(command: &ProjectCommand) -> Result<()> {
+    match command {
+        ProjectCommand::Alias { name } => alias(name),
+        ProjectCommand::Rename { old, new, dry_run } => rename(old, new, *dry_run),
+        ProjectCommand::Merge { src, dst, dry_run } => merge(src, dst, *dry_run),
+    }
+}
+
+pub fn alias(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!("'{}' is not a valid alias, use letters, digits, '-' and '_'", name));
+    }
+    let remote = get_repo_remote_url().ok_or(anyhow!("Not in a git repository with a remote to give an alias"))?;
+    let aliases = &config::get().projects;
+    if let Some(existing) = aliases.get(name) {
+        return Err(anyhow!("The alias '{}' is already used for {}", name, existing));
+    }
+    if let Some(existing) = get_repo_remote().and_then(|dir| alias_for(&dir, aliases)) {
+        return Err(anyhow!("This project already has the alias '{}'", existing));
+    }
+    config::set_project_alias(name, &remote)?;
+    say!("{} is now known as {}", remote, name);
+    Ok(())
+}
+
+fn rename(old: &str, new: &str, dry_run: bool) -> Result<()> {
+    let home = td_home()?;
+    let from = find_project(old)?;
+    if from == home {
+        return Err(anyhow!("The global project can not be renamed"));
+    }
+    let dir_name = sanitize_dir_name(new);
+    if dir_name.is_empty() {
+        return Err(anyhow!("'{}' is not a usable project name", new));
+    }
+    let to = home.join(&dir_name);
+    if to.exists() {
+        return Err(anyhow!("{} already exists, use `td project merge` to combine the two", to.display()));
+    }
+    let old_dir = from.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let aliases: Vec<&String> = config::get().projects.iter()
+        .filter(|(_, remote)| remote_key(remote) == remote_key(&old_dir))
+        .map(|(alias, _)| alias)
+        .collect();
+
+    if dry_run {
+        println!("Would rename {} to {}", from.display(), to.display());
+        for alias in &aliases {
+            println!("Would point the alias {} at {}", alias, new);
+        }
+        return Ok(());
+    }
+    fs::rename(&from, &to)?;
+    for alias in &aliases {
+        config::set_project_alias(alias, new)?;
+    }
+    say!("Renamed {} to {}", from.display(), to.display());
+    for alias in &aliases {
+        say!("The alias {} now points at {}", alias, new);
+    }
+    Ok(())
+}
+
+fn merge(src: &str, dst: &str, dry_run: bool) -> Result<()> {
+    let from = find_project(src)?;
+    let to = find_project(dst)?;
+    if from == to {
+        return Err(anyhow!("{} and {} are the same project", src, dst));
+    }
+    let (moving, renamed) = locked(&from, || locked(&to, || {
+        let moving = load_tasks(&from)?;
+        let staying = load_tasks(&to)?;
+        if let Some((_, task)) = moving.iter().find(|(_, task)| staying.iter().any(|(_, other)| other.metadata.id == task.metadata.id)) {
+            return Err(anyhow!("Both projects have a task with the id {}, refusing to merge", task.metadata.id));
+        }
+
+        let mut renamed = 0;
+        for (path, task) in &moving {
+            let target = free_task_path(&to, task, None);
+            if target.file_name() != path.file_name() {
+                renamed += 1;
+            }
+            if dry_run {
+                println!("Would move {} to {}", path.display(), target.display());
+            } else {
+                crypt::move_task(path, &target, task)?;
+                journal::record(&target, task.metadata.id, &task.metadata.title, &format!("moved from {} to {}", src, dst));
+            }
+        }
+        Ok((moving, renamed))
+    }))?;
+    if dry_run {
+        println!("Would move {} task(s) from {} to {}, {} renamed to avoid collisions", moving.len(), src, dst, renamed);
+        return Ok(());
+    }
+    say!("Moved {} task(s) from {} to {}, {} renamed to avoid collisions", moving.len(), src, dst, renamed);
+
+    match fs::remove_file(from.join(INDEX_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    if fs::read_dir(&from)?.next().is_none() {
+        fs::remove_dir(&from)?;
+        say!("Removed the empty directory {}", from.display());
+    } else {
+        say!("Kept {}, it still contains other files", from.display());
+    }
+    Ok(())
+}
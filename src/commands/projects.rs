@@ -0,0 +1,40 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use anyhow::Result;
+
+use crate::index::load_index;
+use crate::output::{self, OutputArgs};
+use crate::project::list_projects;
+use crate::task::TaskStatus;
+
+#[derive(Debug, Serialize)]
+pub struct ProjectView {
+    pub name: String,
+    pub path: PathBuf,
+    pub tasks: usize,
+    pub open: usize,
+}
+
+pub fn run(args: &OutputArgs) -> Result<()> {
+    let projects = views()?;
+
+    if args.is_structured() {
+        return output::print(args.format(), &projects);
+    }
+    let width = projects.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    for project in &projects {
+        println!("{:<width$}  {} open / {} total", project.name, project.open, project.tasks, width = width);
+    }
+    Ok(())
+}
+
+/// Every project with how many tasks it has.
+pub fn views() -> Result<Vec<ProjectView>> {
+    let mut projects = Vec::new();
+    for (name, path) in list_projects()? {
+        let entries = load_index(&path)?;
+        let open = entries.iter().filter(|e| e.metadata.status != TaskStatus::DONE).count();
+        projects.push(ProjectView { name, path, tasks: entries.len(), open });
+    }
+    Ok(projects)
+}
@@ -0,0 +1,61 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::dates;
+use crate::index::load_entries;
+use crate::project::existing_project_path;
+use crate::task::TaskStatus;
+
+/// What `td prompt` prints without `--format`.
+const DEFAULT_FORMAT: &str = "td:{todo}▸{doing}";
+
+/// The placeholders `--format` fills in.
+const PLACEHOLDERS: &[&str] = &["todo", "doing", "overdue"];
+
+#[derive(Args, Debug)]
+pub struct PromptArgs {
+    /// What to print, with {todo}, {doing} and {overdue} replaced by the
+    /// number of such tasks
+    #[arg(long, default_value = DEFAULT_FORMAT)]
+    format: String,
+}
+
+/// Prints the task counts of the current project for a shell prompt or a
+/// status bar, and nothing outside of a project. Only the index is read and
+/// nothing is created, since this runs before every prompt.
+pub fn run(args: &PromptArgs) -> Result<()> {
+    check_format(&args.format)?;
+    let Ok(Some(project_dir)) = existing_project_path() else {
+        return Ok(());
+    };
+    let Ok(entries) = load_entries(&project_dir) else {
+        return Ok(());
+    };
+    let now = Utc::now();
+    let open: Vec<_> = entries.iter()
+        .map(|entry| &entry.metadata)
+        .filter(|meta| meta.status != TaskStatus::DONE && !meta.is_snoozed(now))
+        .collect();
+    let count = |status: TaskStatus| open.iter().filter(|meta| meta.status == status).count();
+    let overdue = open.iter().filter(|meta| meta.due.is_some_and(|due| dates::day_of(due) < dates::today())).count();
+    let line = args.format
+        .replace("{todo}", &count(TaskStatus::TODO).to_string())
+        .replace("{doing}", &count(TaskStatus::DOING).to_string())
+        .replace("{overdue}", &overdue.to_string());
+    println!("{}", line);
+    Ok(())
+}
+
+fn check_format(format: &str) -> Result<()> {
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else { break };
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(anyhow!("Unknown placeholder {{{}}} in the format, expected {{todo}}, {{doing}} or {{overdue}}", name));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::Result;
+
+use crate::config;
+
+/// Lists the queries from `[queries]` in the config.
+pub fn run() -> Result<()> {
+    let queries = &config::get().queries;
+    if queries.is_empty() {
+        println!("No queries are defined, add one to [queries] in {}", config::config_path()?.display());
+    }
+    let width = queries.keys().map(|name| name.chars().count()).max().unwrap_or(0);
+    for (name, query) in queries {
+        println!("{:<width$}  td ls {}", name, query, width = width);
+    }
+    Ok(())
+}
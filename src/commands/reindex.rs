@@ -0,0 +1,57 @@
+use clap::Args;
+use std::fs;
+use anyhow::Result;
+
+use crate::db;
+use crate::index::{INDEX_FILE, load_index};
+use crate::project::{get_project_path, load_tasks, locked, task_file_name};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct ReindexArgs {
+    /// Also rename task files to the `<slug>.<short id>.td` naming scheme
+    #[arg(long)]
+    rename: bool,
+    /// Also rebuild the SQLite index of every project, creating it the first
+    /// time (needs td built with the `sqlite` feature)
+    #[arg(long)]
+    full: bool,
+}
+
+/// Rebuilds the index of the current project from the task files.
+pub fn run(args: &ReindexArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let mut outdated = 0;
+    locked(&project_dir, || {
+        for (path, task) in load_tasks(&project_dir)? {
+            let expected = project_dir.join(task_file_name(&project_dir, &task));
+            if path == expected {
+                continue;
+            }
+            if !args.rename {
+                outdated += 1;
+            } else if expected.exists() {
+                eprintln!("warning: not renaming {}, {} already exists", path.display(), expected.display());
+            } else {
+                fs::rename(&path, &expected)?;
+                say!("Renamed {} to {}", path.display(), expected.display());
+            }
+        }
+        Ok(())
+    })?;
+
+    match fs::remove_file(project_dir.join(INDEX_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let tasks = load_index(&project_dir)?;
+    say!("Indexed {} tasks", tasks.len());
+    if args.full {
+        let (tasks, projects) = db::rebuild()?;
+        say!("Indexed {} tasks of {} projects in {}", tasks, projects, db::DB_FILE);
+    }
+    if outdated > 0 {
+        say!("{} file(s) are not named after their title, use --rename to rename them", outdated);
+    }
+    Ok(())
+}
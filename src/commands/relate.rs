@@ -0,0 +1,62 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::project::{find_task, get_project_path, locked, save_task};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct RelateArgs {
+    /// The id (or a unique prefix of it) of one task
+    id: String,
+    /// The id of the task it is related to
+    other: String,
+    /// Remove the link instead of adding it
+    #[arg(long)]
+    rm: bool,
+}
+
+/// Links two tasks in both directions. Unlike `td block` neither has to wait
+/// for the other, the link only shows up in `td show`.
+pub fn run(args: &RelateArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    locked(&project_dir, || {
+        let (path, mut task) = find_task(&project_dir, &args.id)?;
+        let (other_path, mut other) = find_task(&project_dir, &args.other)?;
+        if task.metadata.id == other.metadata.id {
+            return Err(anyhow!("A task can not be related to itself"));
+        }
+        let (a, b) = (task.metadata.id, other.metadata.id);
+        let linked = task.metadata.related.contains(&b) || other.metadata.related.contains(&a);
+        if args.rm {
+            if !linked {
+                say!("{} and {} are not related", task.short_id(), other.short_id());
+                return Ok(());
+            }
+            task.metadata.related.retain(|id| *id != b);
+            other.metadata.related.retain(|id| *id != a);
+        } else {
+            if task.metadata.related.contains(&b) && other.metadata.related.contains(&a) {
+                say!("{} and {} are already related", task.short_id(), other.short_id());
+                return Ok(());
+            }
+            if !task.metadata.related.contains(&b) {
+                task.metadata.related.push(b);
+            }
+            if !other.metadata.related.contains(&a) {
+                other.metadata.related.push(a);
+            }
+        }
+        for meta in [&mut task.metadata, &mut other.metadata] {
+            meta.updated_at = Some(Utc::now());
+        }
+        save_task(&path, &task)?;
+        save_task(&other_path, &other)?;
+        if args.rm {
+            say!("{} {} and {} {} are no longer related", task.short_id(), task.metadata.title, other.short_id(), other.metadata.title);
+        } else {
+            say!("{} {} and {} {} are now related", task.short_id(), task.metadata.title, other.short_id(), other.metadata.title);
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,67 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::Result;
+
+use crate::dates;
+use crate::index::{IndexEntry, load_index};
+use crate::output;
+use crate::project::{get_project_path, list_projects, project_name};
+use crate::sort::{SortKey, sort_tasks};
+
+#[derive(Args, Debug)]
+pub struct RemindArgs {
+    /// Include deadlines up to this many days ahead
+    #[arg(long, default_value_t = 3)]
+    days: i64,
+    /// Include the tasks of every project
+    #[arg(long)]
+    all_projects: bool,
+    /// Format the digest as markdown, ready to paste into chat
+    #[arg(long)]
+    markdown: bool,
+}
+
+/// Prints a digest of overdue and soon due tasks for cron mails. Prints
+/// nothing when nothing is due, and exits with 1 when something is overdue
+/// so wrapper scripts can escalate.
+pub fn run(args: &RemindArgs) -> Result<()> {
+    let projects = if args.all_projects {
+        list_projects()?
+    } else {
+        let dir = get_project_path()?;
+        vec![(project_name(&dir), dir)]
+    };
+
+    let mut overdue = false;
+    let mut first = true;
+    for (project, dir) in projects {
+        let mut tasks: Vec<IndexEntry> = load_index(&dir)?
+            .into_iter()
+            .filter(|task| task.metadata.due_within(args.days))
+            .collect();
+        if tasks.is_empty() {
+            continue;
+        }
+        sort_tasks(&mut tasks, SortKey::Due, false, |task| &task.metadata);
+        if !first {
+            println!();
+        }
+        first = false;
+        if args.markdown { println!("*{}*", project) } else { println!("{}", project) }
+        for task in &tasks {
+            let meta = &task.metadata;
+            let Some(due) = meta.due else { continue };
+            overdue |= dates::day_of(due) < dates::today();
+            let age = dates::age(meta.created_at, Utc::now());
+            if args.markdown {
+                println!("- {} _due {}, {}, open {}_", meta.title, dates::format_day(due), dates::due_in(due), age);
+            } else {
+                println!("  {}  due {} ({})  {}  age {}", meta.short_id(), dates::format_day(due), dates::due_in(due), meta.title, age);
+            }
+        }
+    }
+    if overdue {
+        output::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use chrono::Utc;
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::project::{get_project_path, update_task};
+use crate::say;
+
+#[derive(Args, Debug)]
+pub struct RenameArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// The new title
+    title: String,
+}
+
+pub fn run(args: &RenameArgs) -> Result<()> {
+    let title = args.title.trim();
+    if title.is_empty() {
+        return Err(anyhow!("The title can not be empty"));
+    }
+    let project_dir = get_project_path()?;
+    let (_, task) = update_task(&project_dir, &args.id, |task| {
+        task.metadata.title = title.to_string();
+        task.metadata.updated_at = Some(Utc::now());
+        Ok(())
+    })?;
+    say!("Renamed {} to {}", task.short_id(), task.metadata.title);
+    Ok(())
+}
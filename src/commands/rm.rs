@@ -0,0 +1,80 @@
+use chrono::Utc;
+use clap::Args;
+use std::{fs, io::{BufRead, IsTerminal, Write}, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow};
+
+use crate::events::{self, Event, EventKind};
+use crate::journal;
+use crate::project::{find_task, get_project_path, load_tasks, locked, save_task};
+use crate::say;
+use crate::task::Task;
+
+#[derive(Args, Debug)]
+pub struct RmArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// Delete without asking for confirmation
+    #[arg(long, short)]
+    yes: bool,
+    /// Delete an epic that still has tasks, which are then no longer filed
+    /// under any epic
+    #[arg(long)]
+    cascade: bool,
+}
+
+/// Deletes a task file. Subtasks and tasks it blocks keep their references,
+/// which are ignored once the task is gone. An epic with tasks is only
+/// deleted with --cascade, which takes the tasks out of it.
+pub fn run(args: &RmArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let (_, task) = find_task(&project_dir, &args.id)?;
+    members(&project_dir, &task, args.cascade)?;
+    if !args.yes && !confirm(&format!("Delete {} {}?", task.short_id(), task.metadata.title))? {
+        say!("Nothing deleted");
+        return Ok(());
+    }
+    let path = locked(&project_dir, || {
+        let (path, task) = find_task(&project_dir, &task.metadata.id.to_string())?;
+        let members = members(&project_dir, &task, args.cascade)?;
+        let children = load_tasks(&project_dir)?.into_iter()
+            .filter(|(_, other)| other.metadata.parent == Some(task.metadata.id))
+            .count();
+        if children > 0 {
+            eprintln!("warning: {} subtask(s) of {} are left without their parent", children, task.short_id());
+        }
+        for (member_path, mut member) in members {
+            member.metadata.epic = None;
+            member.metadata.updated_at = Some(Utc::now());
+            save_task(&member_path, &member)?;
+            say!("Took {} {} out of the epic", member.short_id(), member.metadata.title);
+        }
+        fs::remove_file(&path)?;
+        journal::record(&path, task.metadata.id, &task.metadata.title, "deleted");
+        Ok(path)
+    })?;
+    events::emit(Event::new(EventKind::Deleted, &path, &task.metadata, None))?;
+    say!("Deleted {} {}", task.short_id(), task.metadata.title);
+    Ok(())
+}
+
+/// The tasks of the epic `task`, which only goes with --cascade.
+fn members(project_dir: &Path, task: &Task, cascade: bool) -> Result<Vec<(PathBuf, Task)>> {
+    let members: Vec<(PathBuf, Task)> = load_tasks(project_dir)?.into_iter()
+        .filter(|(_, other)| other.metadata.epic == Some(task.metadata.id))
+        .collect();
+    if !members.is_empty() && !cascade {
+        return Err(anyhow!("{} is an epic with {} task(s), pass --cascade to delete it and take them out of it", task.short_id(), members.len()));
+    }
+    Ok(members)
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("Pass --yes to delete a task without a terminal to confirm on"));
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
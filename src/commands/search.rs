@@ -0,0 +1,79 @@
+use clap::Args;
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+
+use crate::color;
+use crate::context;
+use crate::db;
+use crate::filter::FilterArgs;
+use crate::normalize;
+use crate::output::{self, OutputArgs, TaskView};
+use crate::project::{get_project_path, load_task_files, load_tasks};
+use crate::sort::{SortKey, sort_tasks};
+use crate::task::Task;
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// The text to look for in titles and descriptions
+    query: String,
+    #[command(flatten)]
+    filter: FilterArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Print only the full id of each matching task, one per line
+    #[arg(long, conflicts_with_all = ["files", "output", "json", "yaml"])]
+    ids_only: bool,
+    /// Print only the path of each matching task file, one per line
+    #[arg(long, conflicts_with_all = ["output", "json", "yaml"])]
+    files: bool,
+    /// End each id or path with a NUL byte instead of a newline, for `xargs -0`
+    #[arg(short = '0', long)]
+    print0: bool,
+}
+
+/// Whether the title or description of the task contains `query`, compared
+/// the way `normalize` does unless matching is case sensitive.
+pub fn matches(task: &Task, query: &str, case_sensitive: bool) -> bool {
+    let needle = normalize::key(query, case_sensitive);
+    normalize::key(&task.metadata.title, case_sensitive).contains(&needle)
+        || normalize::key(&task.description, case_sensitive).contains(&needle)
+}
+
+pub fn run(args: &SearchArgs) -> Result<()> {
+    if args.print0 && !args.ids_only && !args.files {
+        return Err(anyhow!("--print0 only works together with --ids-only or --files"));
+    }
+    let mut filter = args.filter.filter()?;
+    context::apply(&mut filter)?;
+    let project_dir = get_project_path()?;
+    let tasks = match db::search(&project_dir, &args.query) {
+        Some(candidates) => load_task_files(candidates)?,
+        None => load_tasks(&project_dir)?,
+    };
+    let mut found: Vec<(PathBuf, Task)> = tasks
+        .into_iter()
+        .filter(|(_, task)| filter.matches_task(task))
+        .filter(|(_, task)| matches(task, &args.query, filter.case_sensitive))
+        .collect();
+    sort_tasks(&mut found, SortKey::Created, false, |(_, task)| &task.metadata);
+
+    if args.ids_only || args.files {
+        for (path, task) in &found {
+            if args.files {
+                output::print_record(&path.display().to_string(), args.print0);
+            } else {
+                output::print_record(&task.metadata.id.to_string(), args.print0);
+            }
+        }
+        return Ok(());
+    }
+    if args.output.is_structured() {
+        let views: Vec<TaskView> = found.iter().map(|(_, task)| TaskView::from_task(task)).collect();
+        return output::print(args.output.format(), &views);
+    }
+    for (_, task) in &found {
+        let status = format!("{:<5}", format!("{:?}", task.metadata.status));
+        println!("{}  {}  {}", task.short_id(), color::status(&status, task.metadata.status), task.metadata.title);
+    }
+    Ok(())
+}
@@ -0,0 +1,356 @@
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{io::Read, net::IpAddr, path::PathBuf, sync::atomic::{AtomicBool, Ordering}, time::Duration};
+use anyhow::{Result, anyhow};
+
+use crate::commands::{export, mark, projects};
+use crate::filter::Filter;
+use crate::index::load_index;
+use crate::output::TaskView;
+use crate::project::{find_project, find_task, find_task_path, get_project_path, list_projects, load_scoped_tasks, project_name, with_scopes};
+use crate::sort::{SortKey, sort_tasks};
+use crate::task::{Task, TaskStatus};
+
+/// A request larger than this is refused, nothing td accepts comes close.
+const MAX_REQUEST: usize = 64 * 1024;
+
+/// How often the loop looks whether Ctrl-C was pressed while no request comes in.
+const POLL: Duration = Duration::from_millis(200);
+
+/// Set by SIGINT or SIGTERM; the server stops once the current request is answered.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// The port to listen on
+    #[arg(long, default_value_t = 7777)]
+    port: u16,
+    /// The address to listen on. Anything but localhost lets others on the
+    /// network read and change the tasks; they reach it by IP address
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: String,
+}
+
+/// A parsed HTTP request, as much of it as the API needs.
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    host: Option<String>,
+    origin: Option<String>,
+    content_type: Option<String>,
+    body: String,
+}
+
+impl Request {
+    fn param(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.query.iter().filter(move |(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(value: &T) -> Result<Self> {
+        Ok(Response { status: 200, content_type: "application/json", body: serde_json::to_string_pretty(value)? })
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        #[derive(Serialize)]
+        struct Error<'a> {
+            error: &'a str,
+        }
+        let body = serde_json::to_string(&Error { error: message }).unwrap_or_default();
+        Response { status, content_type: "application/json", body }
+    }
+}
+
+/// Serves the board as a page and the tasks as JSON until Ctrl-C:
+///
+/// - `GET /` the board of the current project, or of `?project=<name>`
+/// - `GET /projects` every project with its task counts
+/// - `GET /projects/<name>/tasks` its tasks, narrowed by `?status=` and `?tag=`
+/// - `GET /tasks/<id>` one task, looked up in every project
+/// - `POST /tasks/<id>/status` moves it to the status in a JSON body like
+///   `{"status": "done"}`
+///
+/// Only requests addressed to this server are answered, so a web page can
+/// not reach it through a DNS name it controls, and changes need a JSON body
+/// from the same origin, which a page on another site can not send.
+pub fn run(args: &ServeArgs) -> Result<()> {
+    let server = tiny_http::Server::http((args.bind.as_str(), args.port))
+        .map_err(|e| anyhow!("Could not listen on {}:{}: {}", args.bind, args.port, e))?;
+    stop_on_interrupt();
+    println!("Serving on http://{}, Ctrl-C stops", server.server_addr());
+
+    // Requests are handled one after the other, so two changes through the
+    // API never race each other.
+    while !STOP.load(Ordering::Relaxed) {
+        let Some(mut incoming) = server.recv_timeout(POLL)? else {
+            continue;
+        };
+        let response = match read_request(&mut incoming) {
+            Ok(request) => answer(&request, &args.bind, args.port),
+            Err(e) => Response::error(400, &format!("{:#}", e)),
+        };
+        let content_type = format!("{}; charset=utf-8", response.content_type);
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type).expect("the content type is a valid header");
+        let reply = tiny_http::Response::from_string(response.body).with_status_code(response.status).with_header(header);
+        if let Err(e) = incoming.respond(reply) {
+            eprintln!("warning: could not answer a request: {}", e);
+        }
+    }
+    println!("Stopped");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn stop_on_interrupt() {
+    extern "C" fn interrupted(_: libc::c_int) {
+        STOP.store(true, Ordering::Relaxed);
+    }
+    let handler: extern "C" fn(libc::c_int) = interrupted;
+    // Only stores to an atomic, which is safe to do in a signal handler.
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+fn stop_on_interrupt() {}
+
+fn read_request(incoming: &mut tiny_http::Request) -> Result<Request> {
+    if incoming.body_length().is_some_and(|length| length > MAX_REQUEST) {
+        return Err(anyhow!("The request is too large"));
+    }
+    let mut body = Vec::new();
+    incoming.as_reader().take(MAX_REQUEST as u64 + 1).read_to_end(&mut body)?;
+    if body.len() > MAX_REQUEST {
+        return Err(anyhow!("The request is too large"));
+    }
+    let header = |name: &'static str| {
+        incoming.headers().iter().find(|header| header.field.equiv(name)).map(|header| header.value.as_str().to_string())
+    };
+    let (path, query) = incoming.url().split_once('?').unwrap_or((incoming.url(), ""));
+    Ok(Request {
+        method: incoming.method().as_str().to_string(),
+        path: decode(path),
+        query: query.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .map(|(key, value)| (decode(key), decode(value)))
+            .collect(),
+        host: header("Host"),
+        origin: header("Origin"),
+        content_type: header("Content-Type"),
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/// Routes the request, unless it is refused, and turns errors into answers.
+fn answer(request: &Request, bind: &str, port: u16) -> Response {
+    if let Some(refusal) = refusal(request, bind, port) {
+        return refusal;
+    }
+    route(request).unwrap_or_else(|e| {
+        let message = format!("{:#}", e);
+        let status = if message.starts_with("No task matches") || message.starts_with("No project named") { 404 } else { 400 };
+        Response::error(status, &message)
+    })
+}
+
+/// Why a request is not answered: it is addressed to another host, comes
+/// from a page of another origin, or changes something without JSON.
+fn refusal(request: &Request, bind: &str, port: u16) -> Option<Response> {
+    let host = request.host.as_deref().unwrap_or_default();
+    if !is_served_host(host, bind, port) {
+        return Some(Response::error(403, &format!("Refusing a request for the host '{}', td serves {}:{}", host, bind, port)));
+    }
+    if let Some(origin) = &request.origin && *origin != format!("http://{}", host) {
+        return Some(Response::error(403, &format!("Refusing a request from the origin '{}'", origin)));
+    }
+    let is_json = request.content_type.as_deref()
+        .is_some_and(|content_type| content_type.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case("application/json"));
+    if request.method == "POST" && !is_json {
+        return Some(Response::error(415, "Expected a body with Content-Type: application/json"));
+    }
+    None
+}
+
+/// Whether a Host header names this server: the address it is bound to,
+/// localhost or an IP address, with its port. Any other name may be one an
+/// attacker points at 127.0.0.1.
+fn is_served_host(host: &str, bind: &str, port: u16) -> bool {
+    let (name, host_port) = match host.rsplit_once(':') {
+        Some((name, host_port)) if !host_port.ends_with(']') => (name, host_port.parse().ok()),
+        _ => (host, Some(80)),
+    };
+    let name = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')).unwrap_or(name);
+    host_port == Some(port) && (name.eq_ignore_ascii_case("localhost") || name.eq_ignore_ascii_case(bind) || name.parse::<IpAddr>().is_ok())
+}
+
+/// Undoes the percent encoding of a URL part, with `+` for spaces.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let hex = |i: usize| bytes.get(i).and_then(|byte| (*byte as char).to_digit(16));
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                i += 2;
+            }
+            (b'+', _, _) => decoded.push(b' '),
+            (byte, _, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn route(request: &Request) -> Result<Response> {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", []) => board(request),
+        ("GET", ["projects"]) => Response::json(&projects::views()?),
+        ("GET", ["projects", name, "tasks"]) => project_tasks(request, name),
+        ("GET", ["tasks", id]) => {
+            let (_, task) = find_anywhere(id)?;
+            Response::json(&TaskView::from_task(&task))
+        }
+        ("POST", ["tasks", id, "status"]) => set_status(request, id),
+        (_, ["projects"] | ["projects", _, "tasks"] | ["tasks", _] | ["tasks", _, "status"]) | (_, []) => {
+            Ok(Response::error(405, &format!("{} is not supported on {}", request.method, request.path)))
+        }
+        _ => Ok(Response::error(404, &format!("There is nothing at {}", request.path))),
+    }
+}
+
+/// The read-only board `td export --format html` makes, for one project.
+fn board(request: &Request) -> Result<Response> {
+    let project_dir = match request.param("project").next() {
+        Some(name) => find_project(name)?,
+        None => get_project_path()?,
+    };
+    let mut tasks: Vec<Task> = load_scoped_tasks(&project_dir)?.into_iter().map(|(_, task)| task).collect();
+    sort_tasks(&mut tasks, SortKey::Priority, false, |task| &task.metadata);
+    let title = format!("{} tasks", project_name(&project_dir));
+    let body = export::html(&title, TaskStatus::value_variants(), &tasks);
+    Ok(Response { status: 200, content_type: "text/html", body })
+}
+
+fn project_tasks(request: &Request, name: &str) -> Result<Response> {
+    let project_dir = find_project(name)?;
+    let filter = Filter {
+        statuses: request.param("status").map(parse_status).collect::<Result<_>>()?,
+        tags: request.param("tag").map(str::to_string).collect(),
+        ..Filter::default()
+    };
+    let mut entries = Vec::new();
+    for dir in with_scopes(&project_dir)? {
+        entries.extend(load_index(&dir)?.into_iter().filter(|entry| filter.matches(&entry.metadata)));
+    }
+    sort_tasks(&mut entries, SortKey::Created, false, |entry| &entry.metadata);
+    let views: Vec<TaskView> = entries.iter().map(TaskView::from_entry).collect();
+    Response::json(&views)
+}
+
+/// The body of `POST /tasks/<id>/status`.
+#[derive(Deserialize)]
+struct StatusChange {
+    status: String,
+}
+
+fn set_status(request: &Request, id: &str) -> Result<Response> {
+    let change: StatusChange = serde_json::from_str(&request.body)
+        .map_err(|e| anyhow!("Expected a body like {{\"status\": \"done\"}}: {}", e))?;
+    let status = parse_status(&change.status)?;
+    let (project_dir, _) = find_anywhere(id)?;
+    let task = mark::mark(&project_dir, id, status)?;
+    Response::json(&TaskView::from_task(&task))
+}
+
+fn parse_status(text: &str) -> Result<TaskStatus> {
+    TaskStatus::parse(text)
+        .map(|(status, _)| status)
+        .ok_or_else(|| anyhow!("Unknown status '{}', expected todo, doing or done", text))
+}
+
+/// The project holding the task whose id starts with `id`, and the task.
+fn find_anywhere(id: &str) -> Result<(PathBuf, Task)> {
+    let mut found = Vec::new();
+    for (_, project_dir) in list_projects()? {
+        if find_task_path(&project_dir, id).is_ok() {
+            found.push(project_dir);
+        }
+    }
+    match found.len() {
+        0 => Err(anyhow!("No task matches the id '{}'", id)),
+        1 => {
+            let project_dir = found.remove(0);
+            let (_, task) = find_task(&project_dir, id)?;
+            Ok((project_dir, task))
+        }
+        n => Err(anyhow!("The id '{}' is ambiguous, it matches tasks in {} projects", id, n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, host: &str, origin: Option<&str>, content_type: Option<&str>) -> Request {
+        Request {
+            method: method.to_string(),
+            path: "/tasks/a1b2c3d4/status".to_string(),
+            query: Vec::new(),
+            host: Some(host.to_string()),
+            origin: origin.map(str::to_string),
+            content_type: content_type.map(str::to_string),
+            body: r#"{"status": "done"}"#.to_string(),
+        }
+    }
+
+    fn refused(request: &Request) -> Option<u16> {
+        refusal(request, "127.0.0.1", 7777).map(|response| response.status)
+    }
+
+    #[test]
+    fn only_hosts_naming_this_server_are_answered() {
+        for host in ["127.0.0.1:7777", "localhost:7777", "LOCALHOST:7777", "[::1]:7777", "192.168.1.5:7777"] {
+            assert!(is_served_host(host, "127.0.0.1", 7777), "{} was refused", host);
+        }
+        for host in ["evil.example:7777", "127.0.0.1:8080", "127.0.0.1", "localhost", "127.0.0.1.evil.example:7777", ""] {
+            assert!(!is_served_host(host, "127.0.0.1", 7777), "{} was answered", host);
+        }
+        assert!(is_served_host("devbox:7777", "devbox", 7777));
+        assert!(is_served_host("localhost", "127.0.0.1", 80));
+    }
+
+    #[test]
+    fn changes_need_json_from_the_same_origin() {
+        let json = Some("application/json; charset=utf-8");
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", Some("http://127.0.0.1:7777"), json)), None);
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", None, json)), None);
+        assert_eq!(refused(&request("POST", "evil.example:7777", None, json)), Some(403));
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", Some("http://evil.example"), json)), Some(403));
+        assert_eq!(refused(&request("GET", "127.0.0.1:7777", Some("null"), None)), Some(403));
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", None, Some("application/x-www-form-urlencoded"))), Some(415));
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", None, Some("text/plain"))), Some(415));
+        assert_eq!(refused(&request("POST", "127.0.0.1:7777", None, None)), Some(415));
+        assert_eq!(refused(&request("GET", "127.0.0.1:7777", None, None)), None);
+    }
+
+    #[test]
+    fn url_parts_are_percent_decoded() {
+        assert_eq!(decode("needs%20review+now"), "needs review now");
+        assert_eq!(decode("caf%C3%A9"), "café");
+        assert_eq!(decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,124 @@
+use clap::Args;
+use std::{io::{BufRead, IsTerminal, Write}, path::PathBuf};
+use anyhow::{Result, anyhow};
+
+use crate::assign::{self, Change};
+use crate::commands::epic;
+use crate::events::{self, Event, EventKind};
+use crate::filter::FilterArgs;
+use crate::project::{find_task, get_project_path, load_scoped_tasks, locked, save_task};
+use crate::say;
+use crate::task::{Task, TaskMetadata, TaskStatus};
+
+/// Bulk updates touching more tasks than this ask before writing.
+const CONFIRM_ABOVE: usize = 5;
+
+#[derive(Args, Debug)]
+pub struct SetArgs {
+    /// The id (or a unique prefix of it) of the task, followed by assignments
+    /// like `status=doing`, `due="next friday"`, `tags+=bug` or `tags-=wip`.
+    /// An empty value clears the field. With --where there is no id.
+    #[arg(required = true, value_name = "ID KEY=VALUE")]
+    args: Vec<String>,
+    /// Update every task matching the filter flags instead of a single task
+    #[arg(long = "where")]
+    where_: bool,
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Print the tasks that would change without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Don't ask for confirmation when many tasks match
+    #[arg(long, short)]
+    yes: bool,
+}
+
+pub fn run(args: &SetArgs) -> Result<()> {
+    if args.filter.is_set() && !args.where_ {
+        return Err(anyhow!("Filter flags like --status and --tag need --where"));
+    }
+    let project_dir = get_project_path()?;
+    let tasks = load_scoped_tasks(&project_dir)?;
+
+    let (targets, assignments) = if args.where_ {
+        let filter = args.filter.filter()?;
+        let targets: Vec<_> = tasks.iter().filter(|(_, task)| filter.matches_task(task)).cloned().collect();
+        (targets, &args.args[..])
+    } else {
+        let (id, assignments) = args.args.split_first().expect("clap requires at least one argument");
+        if assignments.is_empty() {
+            return Err(anyhow!("Nothing to set, expected assignments like status=doing after the id"));
+        }
+        (vec![find_task(&project_dir, id)?], assignments)
+    };
+    let changes = assign::parse_changes(assignments, &project_dir)?;
+
+    // Apply everything in memory first so a failure on any task leaves all
+    // files untouched.
+    let updated = apply_all(targets, &changes, &tasks)?;
+    if updated.is_empty() {
+        say!("No tasks match");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        for (_, task, _) in &updated {
+            println!("Would update {} {}", task.short_id(), task.metadata.title);
+        }
+        return Ok(());
+    }
+    if args.where_ && updated.len() > CONFIRM_ABOVE && !args.yes && !confirm(updated.len())? {
+        say!("Nothing changed");
+        return Ok(());
+    }
+    // Applied again to fresh copies under the lock, so a change another td
+    // made meanwhile is not overwritten.
+    let ids: Vec<String> = updated.iter().map(|(_, task, _)| task.metadata.id.to_string()).collect();
+    let saved = locked(&project_dir, || {
+        let tasks = load_scoped_tasks(&project_dir)?;
+        let targets = ids.iter().map(|id| find_task(&project_dir, id)).collect::<Result<Vec<_>>>()?;
+        let updated = apply_all(targets, &changes, &tasks)?;
+        // The project as it will be, for the warning about epics completed
+        // with open tasks.
+        let after: Vec<TaskMetadata> = tasks.iter()
+            .map(|(_, task)| updated.iter().find(|(_, new, _)| new.metadata.id == task.metadata.id).map_or(&task.metadata, |(_, new, _)| &new.metadata).clone())
+            .collect();
+        let mut saved = Vec::with_capacity(updated.len());
+        for (path, task, old_status) in updated {
+            if task.metadata.status == TaskStatus::DONE && old_status != TaskStatus::DONE {
+                epic::warn_open(&task.metadata, &after);
+            }
+            saved.push((save_task(&path, &task)?, task, old_status));
+        }
+        Ok(saved)
+    })?;
+    for (path, task, old_status) in &saved {
+        if task.metadata.status != *old_status {
+            events::emit(Event::new(EventKind::StatusChanged, path, &task.metadata, Some(*old_status)))?;
+        }
+        say!("Updated {} {}", task.short_id(), task.metadata.title);
+    }
+    Ok(())
+}
+
+/// Applies the changes to each target, keeping its status from before.
+fn apply_all(targets: Vec<(PathBuf, Task)>, changes: &[Change], tasks: &[(PathBuf, Task)]) -> Result<Vec<(PathBuf, Task, TaskStatus)>> {
+    let mut updated = Vec::with_capacity(targets.len());
+    for (path, mut task) in targets {
+        let old_status = task.metadata.status;
+        assign::apply(&mut task, changes, tasks)?;
+        updated.push((path, task, old_status));
+    }
+    Ok(updated)
+}
+
+fn confirm(count: usize) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("{} tasks match, pass --yes to update them without a terminal to confirm on", count));
+    }
+    print!("Update {} tasks? [y/N] ", count);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
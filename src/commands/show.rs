@@ -0,0 +1,136 @@
+use clap::Args;
+use std::{fs, io::Write};
+use anyhow::{Result, anyhow};
+
+use crate::checklist;
+use crate::color;
+use crate::dates;
+use crate::icons;
+use crate::output::{self, OutputArgs, TaskView};
+use crate::project::{find_task, find_task_path, get_project_path, load_tasks};
+use crate::task::{Task, TaskStatus};
+
+#[derive(Args, Debug)]
+pub struct ShowArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// Print the task file exactly as it is stored
+    #[arg(long, conflicts_with_all = ["output", "json", "yaml"])]
+    raw: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: &ShowArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    if args.raw {
+        let path = find_task_path(&project_dir, &args.id)?;
+        let content = fs::read(&path).map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&content)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    let (_, task) = find_task(&project_dir, &args.id)?;
+    if args.output.is_structured() {
+        return output::print(args.output.format(), &TaskView::from_task(&task));
+    }
+    let tasks: Vec<Task> = load_tasks(&project_dir)?.into_iter().map(|(_, task)| task).collect();
+    print_task(&task, &tasks);
+    Ok(())
+}
+
+/// How many cells the checklist progress bar takes.
+pub const PROGRESS_CELLS: usize = 10;
+
+/// Prints every detail of a task. `tasks` is the rest of the project, used to
+/// look up related tasks such as the parent and children.
+pub fn print_task(task: &Task, tasks: &[Task]) {
+    let meta = &task.metadata;
+    println!("{}", meta.title);
+    println!("id:       {}", meta.id);
+    let icons = icons::get();
+    let blocked = meta.is_blocked(|id| tasks.iter().find(|t| t.metadata.id == id).map(|t| t.metadata.status));
+    let status = format!("{} {:?}{}", icons.status(meta.status), meta.status, if blocked { format!("  {}", icons.blocked) } else { String::new() });
+    println!("status:   {}", color::status(&status, meta.status));
+    if let Some(priority) = meta.priority {
+        println!("priority: {}", format!("{:?}", priority).to_lowercase());
+    }
+    if let Some(due) = meta.due {
+        println!("due:      {}", dates::format_day(due));
+    }
+    if let Some(scheduled) = meta.scheduled {
+        println!("starts:   {}", dates::format_day(scheduled));
+    }
+    if let Some(until) = meta.snoozed_until {
+        println!("snoozed:  until {}", dates::format_time(until));
+    }
+    if !meta.tags.is_empty() {
+        println!("tags:     {}", color::tags(&meta.tags));
+    }
+    if let Some(branch) = &meta.branch {
+        println!("branch:   {}", branch);
+    }
+    if let Some(epic_id) = meta.epic {
+        match tasks.iter().find(|t| t.metadata.id == epic_id) {
+            Some(epic) => println!("epic:     {} {}", epic.short_id(), epic.metadata.title),
+            None => println!("epic:     {} (missing)", &epic_id.to_string()[..8]),
+        }
+    }
+    if let Some(milestone) = &meta.milestone {
+        println!("milestone: {}", milestone);
+    }
+    if meta.is_epic() {
+        let members: Vec<&Task> = tasks.iter().filter(|t| t.metadata.epic == Some(meta.id)).collect();
+        let done = members.iter().filter(|t| t.metadata.status == TaskStatus::DONE).count();
+        println!("epic:     {} {}/{} tasks done", icons.bar(done, members.len(), PROGRESS_CELLS), done, members.len());
+    }
+    let progress = checklist::progress(&task.description);
+    if progress.total > 0 {
+        println!("progress: {} {}", icons.bar(progress.done, progress.total, PROGRESS_CELLS), progress.fraction());
+    }
+    println!("created:  {}", dates::format_time(meta.created_at));
+    if let Some(updated_at) = meta.updated_at {
+        println!("updated:  {}", dates::format_time(updated_at));
+    }
+    if let Some(parent_id) = meta.parent {
+        match tasks.iter().find(|t| t.metadata.id == parent_id) {
+            Some(parent) => println!("parent:   {} {}", parent.short_id(), parent.metadata.title),
+            None => println!("parent:   {} (missing)", &parent_id.to_string()[..8]),
+        }
+    }
+
+    for blocker_id in &meta.blocked_by {
+        match tasks.iter().find(|t| t.metadata.id == *blocker_id) {
+            Some(blocker) => println!("blocked:  by {} {} ({:?})", blocker.short_id(), blocker.metadata.title, blocker.metadata.status),
+            None => println!("blocked:  by {} (missing)", &blocker_id.to_string()[..8]),
+        }
+    }
+
+    for related_id in &meta.related {
+        match tasks.iter().find(|t| t.metadata.id == *related_id) {
+            Some(related) => println!("related:  {} {} ({:?})", related.short_id(), related.metadata.title, related.metadata.status),
+            None => println!("related:  {} (missing)", &related_id.to_string()[..8]),
+        }
+    }
+
+    if !meta.links.is_empty() {
+        println!("links:");
+        for (i, link) in meta.links.iter().enumerate() {
+            println!("  {}. {}", i + 1, link);
+        }
+    }
+
+    let children: Vec<&Task> = tasks.iter().filter(|t| t.metadata.parent == Some(meta.id)).collect();
+    if !children.is_empty() {
+        println!("\nsubtasks:");
+        for child in children {
+            let status = format!("{:<5}", format!("{:?}", child.metadata.status));
+            println!("  {} {}  {}  {}", icons.status(child.metadata.status), child.short_id(), color::status(&status, child.metadata.status), child.metadata.title);
+        }
+    }
+
+    if !task.description.trim().is_empty() {
+        println!("\n{}", task.description.trim_end());
+    }
+}
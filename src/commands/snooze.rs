@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use anyhow::{Result, anyhow};
+
+use crate::dates;
+use crate::project::{get_project_path, update_task};
+use crate::say;
+use crate::task::TaskStatus;
+
+#[derive(Args, Debug)]
+pub struct SnoozeArgs {
+    /// The id (or a unique prefix of it) of the task
+    id: String,
+    /// When the task should show up again, e.g. tomorrow or "next monday"
+    #[arg(value_parser = dates::parse_when, required_unless_present = "clear")]
+    when: Option<DateTime<Utc>>,
+    /// Wake the task up right away
+    #[arg(long, conflicts_with = "when")]
+    clear: bool,
+}
+
+pub fn run(args: &SnoozeArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let (_, task) = update_task(&project_dir, &args.id, |task| {
+        if args.clear {
+            task.metadata.snoozed_until = None;
+        } else {
+            if task.metadata.status == TaskStatus::DONE {
+                return Err(anyhow!("{} is already done and can not be snoozed", task.short_id()));
+            }
+            task.metadata.snoozed_until = args.when;
+        }
+        task.metadata.updated_at = Some(Utc::now());
+        Ok(())
+    })?;
+
+    match task.metadata.snoozed_until {
+        Some(until) => say!("{} {} is snoozed until {}", task.short_id(), task.metadata.title, dates::format_time(until)),
+        None => say!("{} {} is no longer snoozed", task.short_id(), task.metadata.title),
+    }
+    Ok(())
+}
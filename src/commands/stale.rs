@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::io::{BufRead, IsTerminal, Write};
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::dates;
+use crate::index::{IndexEntry, load_index};
+use crate::output;
+use crate::project::{get_project_path, update_task};
+use crate::say;
+use crate::tags;
+
+/// How long an open task may go without a change when neither `--days` nor
+/// `stale_after_days` says otherwise.
+const DEFAULT_DAYS: u64 = 30;
+
+#[derive(Args, Debug)]
+pub struct StaleArgs {
+    /// How many days without a change make a task stale, `stale_after_days`
+    /// of the config or 30 by default
+    #[arg(long)]
+    days: Option<u64>,
+    /// Give every stale task this tag, like `someday`
+    #[arg(long, value_name = "TAG")]
+    tag: Option<String>,
+    /// Snooze every stale task until then, e.g. "next month"
+    #[arg(long, value_name = "WHEN", value_parser = dates::parse_when)]
+    snooze: Option<DateTime<Utc>>,
+    /// Don't ask for confirmation before changing the tasks
+    #[arg(long, short)]
+    yes: bool,
+}
+
+/// Lists the open tasks nobody touched for a while, the longest forgotten
+/// first. Exits with 1 while any are left unhandled, so it can nag from cron.
+pub fn run(args: &StaleArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let days = args.days.or(config::get().stale_after_days).unwrap_or(DEFAULT_DAYS);
+    let now = Utc::now();
+    let mut tasks: Vec<IndexEntry> = load_index(&project_dir)?
+        .into_iter()
+        .filter(|task| task.metadata.is_stale(days, now) && !task.metadata.is_snoozed(now))
+        .collect();
+    if tasks.is_empty() {
+        println!("No open task has gone {} days without a change", days);
+        return Ok(());
+    }
+    tasks.sort_by_key(|task| task.metadata.last_touched());
+
+    let width = tasks.iter().map(|task| untouched(task, now).len()).max().unwrap_or(0);
+    for task in &tasks {
+        let meta = &task.metadata;
+        println!("{:>width$}  {}  {:<5}  {}", untouched(task, now), meta.short_id(), format!("{:?}", meta.status), meta.title, width = width);
+    }
+    if args.tag.is_none() && args.snooze.is_none() {
+        output::exit(1);
+    }
+
+    let tag = args.tag.as_deref().map(tags::normalize).transpose()?;
+    let mut actions = Vec::new();
+    actions.extend(tag.as_ref().map(|tag| format!("tag with {}", tag)));
+    actions.extend(args.snooze.map(|until| format!("snooze until {}", dates::format_time(until))));
+    if !args.yes && !confirm(&format!("\n{} these {} tasks?", capitalize(&actions.join(" and ")), tasks.len()))? {
+        say!("Nothing changed");
+        output::exit(1);
+    }
+    for entry in &tasks {
+        update_task(&project_dir, &entry.metadata.id.to_string(), |task| {
+            if let Some(tag) = &tag {
+                task.metadata.add_tags([tag])?;
+            }
+            if args.snooze.is_some() {
+                task.metadata.snoozed_until = args.snooze;
+            }
+            task.metadata.updated_at = Some(now);
+            Ok(())
+        })?;
+    }
+    say!("Updated {} stale tasks", tasks.len());
+    Ok(())
+}
+
+/// How long the task has gone without a change, in days.
+fn untouched(task: &IndexEntry, now: DateTime<Utc>) -> String {
+    format!("{}d", (now - task.metadata.last_touched()).num_days())
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("Pass --yes to change the stale tasks without a terminal to confirm on"));
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
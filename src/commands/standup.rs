@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::collections::HashMap;
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::dates;
+use crate::index::{IndexEntry, load_index};
+use crate::project::{get_project_path, list_projects};
+use crate::task::{TaskStatus, TaskMetadata};
+
+#[derive(Args, Debug)]
+pub struct StandupArgs {
+    /// Report tasks finished since this day instead of the previous working day
+    #[arg(long, value_parser = dates::parse_since)]
+    since: Option<DateTime<Utc>>,
+    /// Format the summary as markdown, ready to paste into chat
+    #[arg(long)]
+    markdown: bool,
+    /// Include the tasks of every project
+    #[arg(long)]
+    all_projects: bool,
+}
+
+/// One line of the summary, with the project it belongs to when reporting
+/// on all projects.
+struct Item<'a> {
+    project: Option<&'a str>,
+    meta: &'a TaskMetadata,
+    note: Option<String>,
+}
+
+pub fn run(args: &StandupArgs) -> Result<()> {
+    let since = args.since.unwrap_or_else(|| dates::previous_working_day(dates::today()));
+    let projects = if args.all_projects {
+        list_projects()?.into_iter().map(|(name, path)| Ok((Some(name), load_index(&path)?))).collect::<Result<Vec<_>>>()?
+    } else {
+        vec![(None, load_index(&get_project_path()?)?)]
+    };
+
+    let mut done = Vec::new();
+    let mut doing = Vec::new();
+    let mut stuck = Vec::new();
+    for (project, tasks) in &projects {
+        let project = project.as_deref();
+        let by_id: HashMap<Uuid, &IndexEntry> = tasks.iter().map(|t| (t.metadata.id, t)).collect();
+        for task in tasks {
+            let meta = &task.metadata;
+            match meta.status {
+                TaskStatus::DONE => {
+                    if meta.updated_at.is_some_and(|updated_at| updated_at >= since) {
+                        done.push(Item { project, meta, note: None });
+                    }
+                    continue;
+                }
+                TaskStatus::DOING => doing.push(Item { project, meta, note: None }),
+                TaskStatus::TODO => {}
+            }
+            let blockers: Vec<String> = meta.blocked_by.iter()
+                .filter_map(|id| by_id.get(id))
+                .filter(|blocker| blocker.metadata.status != TaskStatus::DONE)
+                .map(|blocker| blocker.metadata.short_id())
+                .collect();
+            let mut notes = Vec::new();
+            if !blockers.is_empty() {
+                notes.push(format!("blocked by {}", blockers.join(", ")));
+            }
+            if let Some(due) = meta.due.filter(|due| dates::day_of(*due) < dates::today()) {
+                notes.push(dates::due_in(due));
+            }
+            if !notes.is_empty() {
+                stuck.push(Item { project, meta, note: Some(notes.join(", ")) });
+            }
+        }
+    }
+
+    let sections = [
+        (format!("Done since {}", dates::local(since).format("%a %Y-%m-%d")), done),
+        ("Doing".to_string(), doing),
+        ("Blocked or overdue".to_string(), stuck),
+    ];
+    for (i, (heading, items)) in sections.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if args.markdown {
+            print_markdown(heading, items);
+        } else {
+            print_text(heading, items);
+        }
+    }
+    Ok(())
+}
+
+fn print_text(heading: &str, items: &[Item]) {
+    println!("{}", heading);
+    if items.is_empty() {
+        println!("  (none)");
+    }
+    for item in items {
+        let project = item.project.map(|p| format!("[{}] ", p)).unwrap_or_default();
+        let note = item.note.as_ref().map(|n| format!("  ({})", n)).unwrap_or_default();
+        println!("  {}  {}{}{}", item.meta.short_id(), project, item.meta.title, note);
+    }
+}
+
+fn print_markdown(heading: &str, items: &[Item]) {
+    println!("*{}*", heading);
+    if items.is_empty() {
+        println!("- nothing");
+    }
+    for item in items {
+        let project = item.project.map(|p| format!("[{}] ", p)).unwrap_or_default();
+        let note = item.note.as_ref().map(|n| format!(" _{}_", n)).unwrap_or_default();
+        println!("- {}{}{}", project, item.meta.title, note);
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use anyhow::Result;
+
+use crate::dates;
+use crate::index::load_index;
+use crate::output::{self, OutputArgs};
+use crate::project::get_project_path;
+use crate::task::TaskStatus;
+
+#[derive(Debug, Default, Serialize)]
+struct Stats {
+    total: usize,
+    by_status: BTreeMap<String, usize>,
+    by_priority: BTreeMap<String, usize>,
+    by_tag: BTreeMap<String, usize>,
+    overdue: usize,
+    snoozed: usize,
+}
+
+pub fn run(args: &OutputArgs) -> Result<()> {
+    let project_dir = get_project_path()?;
+    let now = Utc::now();
+    let mut stats = Stats::default();
+    for status in [TaskStatus::TODO, TaskStatus::DOING, TaskStatus::DONE] {
+        stats.by_status.insert(format!("{:?}", status).to_lowercase(), 0);
+    }
+    for entry in load_index(&project_dir)? {
+        let meta = &entry.metadata;
+        stats.total += 1;
+        *stats.by_status.entry(format!("{:?}", meta.status).to_lowercase()).or_default() += 1;
+        let priority = meta.priority.map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_else(|| "none".to_string());
+        *stats.by_priority.entry(priority).or_default() += 1;
+        for tag in &meta.tags {
+            *stats.by_tag.entry(tag.clone()).or_default() += 1;
+        }
+        if meta.status != TaskStatus::DONE && meta.due.is_some_and(|due| dates::day_of(due) < dates::today()) {
+            stats.overdue += 1;
+        }
+        if meta.is_snoozed(now) {
+            stats.snoozed += 1;
+        }
+    }
+
+    if args.is_structured() {
+        return output::print(args.format(), &stats);
+    }
+    println!("{} tasks", stats.total);
+    for (status, count) in &stats.by_status {
+        println!("  {:<8} {}", status, count);
+    }
+    println!("overdue:  {}", stats.overdue);
+    println!("snoozed:  {}", stats.snoozed);
+    println!("\nby priority:");
+    for (priority, count) in &stats.by_priority {
+        println!("  {:<8} {}", priority, count);
+    }
+    if !stats.by_tag.is_empty() {
+        println!("\nby tag:");
+        for (tag, count) in &stats.by_tag {
+            println!("  {:<8} {}", tag, count);
+        }
+    }
+    Ok(())
+}
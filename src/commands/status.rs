@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::color;
+use crate::context;
+use crate::dates;
+use crate::index::{IndexEntry, load_index};
+use crate::milestone::{self, Milestone};
+use crate::project::{Head, current_scope, head, listing_dirs, project_name, repo_project_path};
+use crate::task::{TaskMetadata, TaskStatus};
+
+/// Prints a summary of the current project that fits on one screen. It only
+/// reads the index and never fails outside a repository, so it is cheap
+/// enough to run from a shell hook.
+pub fn run() -> Result<()> {
+    let name = project_name(&repo_project_path()?);
+    match current_scope() {
+        Some(scope) => println!("project: {} ({})", name, scope),
+        None => println!("project: {}", name),
+    }
+    if let Some((context, _)) = context::active()? {
+        println!("context: {}", context);
+    }
+
+    let mut tasks: Vec<IndexEntry> = Vec::new();
+    for dir in listing_dirs(false)? {
+        tasks.extend(load_index(&dir)?);
+    }
+    let open: Vec<&TaskMetadata> = tasks.iter().map(|t| &t.metadata).filter(|meta| meta.status != TaskStatus::DONE).collect();
+
+    match head() {
+        Some(Head::Branch(branch)) => {
+            println!("branch:  {}", branch);
+            for meta in open.iter().filter(|meta| meta.branch.as_ref() == Some(&branch)) {
+                println!("  {}", line(meta));
+            }
+        }
+        Some(Head::Detached) => println!("branch:  (detached HEAD)"),
+        None => {}
+    }
+
+    let count = |status: TaskStatus| tasks.iter().filter(|t| t.metadata.status == status).count();
+    println!("tasks:   {} todo, {} doing, {} done", count(TaskStatus::TODO), count(TaskStatus::DOING), count(TaskStatus::DONE));
+
+    let status_of: HashMap<Uuid, TaskStatus> = tasks.iter().map(|t| (t.metadata.id, t.metadata.status)).collect();
+    let sections: [(&str, Vec<&TaskMetadata>); 3] = [
+        ("In progress", open.iter().copied().filter(|meta| meta.status == TaskStatus::DOING).collect()),
+        ("Overdue", open.iter().copied().filter(|meta| meta.due.is_some_and(|due| dates::day_of(due) < dates::today())).collect()),
+        ("Blocked", open.iter().copied().filter(|meta| meta.is_blocked(|id| status_of.get(&id).copied())).collect()),
+    ];
+    for (heading, members) in sections.iter().filter(|(_, members)| !members.is_empty()) {
+        println!("\n{}:", heading);
+        for meta in members {
+            println!("  {}", line(meta));
+        }
+    }
+
+    // A broken milestones file leaves them out rather than failing the summary.
+    let overdue: Vec<(Milestone, usize)> = milestone::load(&repo_project_path()?).unwrap_or_default()
+        .into_iter()
+        .filter(Milestone::is_overdue)
+        .map(|milestone| {
+            let (open, _) = milestone.counts(tasks.iter().map(|t| &t.metadata));
+            (milestone, open)
+        })
+        .filter(|(_, open)| *open > 0)
+        .collect();
+    if !overdue.is_empty() {
+        println!("\nOverdue milestones:");
+        for (milestone, open) in overdue {
+            let due = milestone.due.map(dates::due_in).unwrap_or_default();
+            println!("  {}  {} open  ({})", milestone.name, open, due);
+        }
+    }
+    Ok(())
+}
+
+fn line(meta: &TaskMetadata) -> String {
+    let status = format!("{:<5}", format!("{:?}", meta.status));
+    let due = meta.due.map(|due| format!("  ({})", dates::due_in(due))).unwrap_or_default();
+    format!("{}  {}  {}{}", meta.short_id(), color::status(&status, meta.status), meta.title, due)
+}
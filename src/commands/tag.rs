@@ -0,0 +1,98 @@
+use chrono::Utc;
+use clap::Subcommand;
+use anyhow::Result;
+
+use crate::normalize;
+use crate::project::{find_task, get_project_path, load_tasks, locked, save_task};
+use crate::say;
+use crate::tags;
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommand {
+    /// Add a tag to one or more tasks
+    Add {
+        tag: String,
+        /// The ids (or unique prefixes) of the tasks
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Remove a tag from one or more tasks
+    Rm {
+        tag: String,
+        /// The ids (or unique prefixes) of the tasks
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Only remove the tag when it is spelled exactly like this
+        #[arg(long)]
+        case_sensitive: bool,
+    },
+    /// Rename a tag on every task in the project
+    Rename {
+        old: String,
+        new: String,
+        /// Also rename spellings that only differ in case (or accents)
+        #[arg(short, long)]
+        ignore_case: bool,
+    },
+}
+
+pub fn run(command: &TagCommand) -> Result<()> {
+    let project_dir = get_project_path()?;
+    locked(&project_dir, || {
+        match command {
+            TagCommand::Add { tag, ids } => {
+                let tag = &tags::normalize(tag)?;
+                let tasks = ids.iter().map(|id| find_task(&project_dir, id)).collect::<Result<Vec<_>>>()?;
+                for (path, mut task) in tasks {
+                    if task.metadata.tags.iter().any(|t| normalize::normalize(t) == normalize::normalize(tag)) {
+                        say!("{} {} already has {}", task.short_id(), task.metadata.title, tag);
+                        continue;
+                    }
+                    task.metadata.tags.push(tag.clone());
+                    task.metadata.updated_at = Some(Utc::now());
+                    save_task(&path, &task)?;
+                    say!("Tagged {} {} with {}", task.short_id(), task.metadata.title, tag);
+                }
+            }
+            TagCommand::Rm { tag, ids, case_sensitive } => {
+                let wanted = normalize::key(tag, *case_sensitive);
+                let tasks = ids.iter().map(|id| find_task(&project_dir, id)).collect::<Result<Vec<_>>>()?;
+                for (path, mut task) in tasks {
+                    let before = task.metadata.tags.len();
+                    task.metadata.tags.retain(|t| normalize::key(t, *case_sensitive) != wanted);
+                    if task.metadata.tags.len() == before {
+                        say!("{} {} does not have {}", task.short_id(), task.metadata.title, tag);
+                        continue;
+                    }
+                    task.metadata.updated_at = Some(Utc::now());
+                    save_task(&path, &task)?;
+                    say!("Removed {} from {} {}", tag, task.short_id(), task.metadata.title);
+                }
+            }
+            TagCommand::Rename { old, new, ignore_case } => {
+                let new = tags::normalize(new)?;
+                let new = new.as_str();
+                let wanted = normalize::key(old, !ignore_case);
+                let mut renamed = 0;
+                for (path, mut task) in load_tasks(&project_dir)? {
+                    if !task.metadata.tags.iter().any(|t| normalize::key(t, !ignore_case) == wanted) {
+                        continue;
+                    }
+                    let mut tags: Vec<String> = Vec::new();
+                    for t in &task.metadata.tags {
+                        let t = if normalize::key(t, !ignore_case) == wanted { new } else { t.as_str() };
+                        if !tags.iter().any(|existing| existing == t) {
+                            tags.push(t.to_string());
+                        }
+                    }
+                    task.metadata.tags = tags;
+                    task.metadata.updated_at = Some(Utc::now());
+                    save_task(&path, &task)?;
+                    renamed += 1;
+                }
+                say!("Renamed {} to {} on {} task(s)", old, new, renamed);
+            }
+        }
+        Ok(())
+    })
+}
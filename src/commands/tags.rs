@@ -0,0 +1,128 @@
+use clap::Args;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use anyhow::Result;
+
+use crate::color;
+use crate::index::load_index;
+use crate::normalize;
+use crate::output::{self, OutputArgs};
+use crate::project::get_project_path;
+use crate::tags::{self, SEPARATOR};
+use crate::task::TaskStatus;
+
+#[derive(Args, Debug)]
+pub struct TagsArgs {
+    /// Count tags that differ only in case separately
+    #[arg(long)]
+    case_sensitive: bool,
+    /// Show hierarchical tags like `area/backend` as a tree, counting every
+    /// task below a level towards it
+    #[arg(long)]
+    tree: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Debug, Serialize)]
+struct TagView {
+    tag: String,
+    tasks: usize,
+    open: usize,
+}
+
+/// The counts of one tag, or one level of hierarchical tags in a tree.
+#[derive(Debug, Default)]
+struct Count {
+    /// How often each spelling of the tag was seen
+    spellings: BTreeMap<String, usize>,
+    tasks: usize,
+    open: usize,
+}
+
+pub fn run(args: &TagsArgs) -> Result<()> {
+    // Spellings of a tag that normalize to the same key are counted together
+    // and shown with their most common spelling.
+    let mut by_key: BTreeMap<String, Count> = BTreeMap::new();
+    for entry in load_index(&get_project_path()?)? {
+        // In a tree a task counts once towards each level it is below, even
+        // with several tags there.
+        let mut seen = BTreeSet::new();
+        for tag in &entry.metadata.tags {
+            let levels = if args.tree { levels(tag) } else { vec![tag.as_str()] };
+            for level in levels {
+                let key = normalize::key(level, args.case_sensitive);
+                if !seen.insert(key.clone()) {
+                    continue;
+                }
+                let count = by_key.entry(key).or_default();
+                *count.spellings.entry(level.to_string()).or_default() += 1;
+                count.tasks += 1;
+                if entry.metadata.status != TaskStatus::DONE {
+                    count.open += 1;
+                }
+            }
+        }
+    }
+    let mut tags: Vec<TagView> = by_key
+        .into_values()
+        .map(|count| {
+            let tag = count.spellings.into_iter().max_by_key(|(_, count)| *count).map(|(tag, _)| tag).unwrap_or_default();
+            TagView { tag, tasks: count.tasks, open: count.open }
+        })
+        .collect();
+    tags.sort_by(|a, b| b.tasks.cmp(&a.tasks).then_with(|| a.tag.cmp(&b.tag)));
+    if args.tree {
+        tags = tree_order(tags, args.case_sensitive);
+    }
+
+    if args.output.is_structured() {
+        return output::print(args.output.format(), &tags);
+    }
+    // In a tree each level is indented below the one above and only shows
+    // its last part.
+    let label = |tag: &TagView| if args.tree {
+        ("  ".repeat(tag.tag.matches(SEPARATOR).count()), tag.tag.rsplit(SEPARATOR).next().unwrap_or_default().to_string())
+    } else {
+        (String::new(), tag.tag.clone())
+    };
+    let width = tags.iter().map(&label).map(|(indent, name)| indent.len() + name.chars().count()).max().unwrap_or(0);
+    for tag in &tags {
+        let (indent, name) = label(tag);
+        let padding = " ".repeat(width - indent.len() - name.chars().count());
+        println!("{}{}{}  {} ({} open)", indent, color::tag_as(&name, &tag.tag), padding, tag.tasks, tag.open);
+    }
+    Ok(())
+}
+
+/// A tag and every level above it, `area` and `area/backend` for the latter.
+fn levels(tag: &str) -> Vec<&str> {
+    let mut levels = vec![tag];
+    while let Some(parent) = tags::parent(levels[levels.len() - 1]) {
+        levels.push(parent);
+    }
+    levels.reverse();
+    levels
+}
+
+/// Puts every tag right after the one above it, keeping the order among
+/// siblings. The tags have to be sorted already and include every level.
+fn tree_order(tags: Vec<TagView>, case_sensitive: bool) -> Vec<TagView> {
+    let key = |tag: &str| normalize::key(tag, case_sensitive);
+    let mut children: BTreeMap<Option<String>, Vec<TagView>> = BTreeMap::new();
+    for tag in tags {
+        children.entry(tags::parent(&tag.tag).map(key)).or_default().push(tag);
+    }
+    let mut ordered = Vec::new();
+    let mut stack: Vec<TagView> = children.remove(&None).unwrap_or_default().into_iter().rev().collect();
+    while let Some(tag) = stack.pop() {
+        if let Some(below) = children.remove(&Some(key(&tag.tag))) {
+            stack.extend(below.into_iter().rev());
+        }
+        ordered.push(tag);
+    }
+    // Tags whose level above is spelled differently elsewhere, so it was
+    // not found: list them at the end rather than losing them.
+    ordered.extend(children.into_values().flatten());
+    ordered
+}
@@ -0,0 +1,30 @@
+use clap::Subcommand;
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::events::{self, Event, EventKind};
+use crate::project::{get_project_path, task_file_name};
+use crate::task::Task;
+
+#[derive(Subcommand, Debug)]
+pub enum WebhookCommand {
+    /// Send a sample payload to the configured webhook
+    Test,
+}
+
+pub fn run(command: &WebhookCommand) -> Result<()> {
+    match command {
+        WebhookCommand::Test => test(),
+    }
+}
+
+fn test() -> Result<()> {
+    let settings = &config::get().webhooks;
+    let url = settings.url.as_ref().ok_or(anyhow!("No webhook is configured, set url in the [webhooks] section of the config"))?;
+    let task = Task::new("Test from td webhook test");
+    let project_dir = get_project_path()?;
+    let sample = Event::new(EventKind::Created, &project_dir.join(task_file_name(&project_dir, &task)), &task.metadata, None);
+    let status = events::post(settings, url, &sample).map_err(|e| anyhow!("The webhook {} failed: {}", url, e))?;
+    println!("Sent a sample payload to {}, it answered with HTTP {}", url, status);
+    Ok(())
+}
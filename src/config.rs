@@ -0,0 +1,544 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{MapAccess, Visitor}, ser::SerializeMap};
+use serde_yaml::{Mapping, Value};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, sync::OnceLock};
+use anyhow::{Result, anyhow};
+
+use crate::color::Color;
+use crate::dates;
+use crate::events::{EventKind, HookFailures};
+use crate::icons::IconMode;
+use crate::project;
+use crate::task::{Frontmatter, TaskStatus};
+
+/// The name of the config file, both in `~/.td` and in a project directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Keys that decide where projects live, so a project can not override them.
+const GLOBAL_ONLY: &[&str] = &["data_dir", "projects", "remote_priority"];
+
+/// The user's settings from `~/.td/config.toml`, with the `config.toml` of
+/// the current project directory layered on top. Every key is optional.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the project directories are kept instead of `~/.td`
+    pub data_dir: Option<PathBuf>,
+    /// Short names for projects, mapping an alias to the remote of the repository
+    pub projects: BTreeMap<String, String>,
+    /// The remotes tried in order to decide which project a repository belongs to
+    pub remote_priority: Vec<String>,
+    /// Colors for tags by name, either a color name like `red` or a hex value
+    pub tag_colors: BTreeMap<String, String>,
+    /// The format string `ls` uses when `--format` is not given
+    pub default_format: Option<String>,
+    /// How urgent a task is, for `td next` and `ls --sort urgency`
+    pub urgency: UrgencyWeights,
+    /// How `td changelog` groups and filters the finished tasks
+    pub changelog: ChangelogConfig,
+    /// Whether `td add` looks for open tasks with a similar title first
+    pub check_duplicates: bool,
+    /// Whether search and tag matching also ignore accents, so `é` matches `e`
+    pub fold_diacritics: bool,
+    /// Whether tags are lowercased when they are written
+    pub lowercase_tags: bool,
+    /// After how many days without a change `ls` marks open tasks as stale,
+    /// and the default of `td stale --days`
+    pub stale_after_days: Option<u64>,
+    /// How long after their last change DONE tasks are moved to the archive,
+    /// like `30d`. Checked once a day when td runs in the project
+    pub auto_archive_after: Option<String>,
+    /// Whether `ls` leaves out DONE tasks unless --status asks for them
+    pub hide_done: bool,
+    /// Whether each subdirectory of a repository keeps its own tasks, for monorepos
+    pub subdirs: bool,
+    /// Whether new tasks are filed under the current git branch and `ls`
+    /// shows only the tasks of that branch
+    pub branch_scope: bool,
+    /// Tags `td add` gives every new task, usually set per project by `td init --tag`
+    pub default_tags: Vec<String>,
+    /// Where td reports changes to tasks
+    pub webhooks: WebhookConfig,
+    /// Whether a failing script in `~/.td/hooks` only warns (`warn`) or
+    /// undoes the change it was run for (`abort`)
+    pub hook_failures: HookFailures,
+    /// How long a hook script may run before it is stopped
+    pub hook_timeout_secs: u64,
+    /// Which tasks `td notify` raises desktop notifications for
+    pub notify: NotifyConfig,
+    /// How task files are written, `yaml` or `toml` frontmatter
+    pub frontmatter: Frontmatter,
+    /// Whether dates are shown and read in UTC instead of the local timezone
+    pub utc: bool,
+    /// The glyphs for statuses, progress bars and rules: `emoji`, `unicode`
+    /// or `ascii`. Unset means Unicode on a terminal and ASCII otherwise
+    pub icons: Option<IconMode>,
+    /// How days are shown, as a chrono format string like `%d/%m/%Y` or `%b %-d`
+    pub date_format: String,
+    /// How points in time are shown, like `%d/%m/%Y %H:%M`
+    pub datetime_format: String,
+    /// Named filters that `td context` applies to every listing
+    pub contexts: BTreeMap<String, ContextConfig>,
+    /// `ls` flags saved under a name, run with `td ls --query <name>` or `td q <name>`
+    pub queries: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_dir: None,
+            projects: BTreeMap::new(),
+            remote_priority: vec!["origin".to_string()],
+            tag_colors: BTreeMap::new(),
+            default_format: None,
+            urgency: UrgencyWeights::default(),
+            changelog: ChangelogConfig::default(),
+            check_duplicates: true,
+            fold_diacritics: false,
+            lowercase_tags: false,
+            stale_after_days: None,
+            auto_archive_after: None,
+            hide_done: false,
+            subdirs: false,
+            branch_scope: false,
+            default_tags: Vec::new(),
+            webhooks: WebhookConfig::default(),
+            hook_failures: HookFailures::Warn,
+            hook_timeout_secs: 10,
+            notify: NotifyConfig::default(),
+            frontmatter: Frontmatter::Yaml,
+            utc: false,
+            icons: None,
+            date_format: "%Y-%m-%d".to_string(),
+            datetime_format: "%Y-%m-%d %H:%M".to_string(),
+            contexts: BTreeMap::new(),
+            queries: BTreeMap::new(),
+        }
+    }
+}
+
+/// The filter a context adds to `ls`, `search` and `next` while it is active.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextConfig {
+    /// Only tasks carrying all of these tags
+    pub tags: Vec<String>,
+    /// Only tasks carrying none of these tags
+    pub not_tags: Vec<String>,
+    /// Only tasks with one of these statuses, unless the command asks for others
+    pub statuses: Vec<TaskStatus>,
+}
+
+/// A URL that is sent a JSON payload after tasks change.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: Option<String>,
+    /// The events to send, all of them when empty
+    pub events: Vec<EventKind>,
+    /// Sent as a bearer token in the Authorization header
+    pub token: Option<String>,
+    /// An environment variable holding the token, to keep it out of the file
+    pub token_env: Option<String>,
+    /// How long to wait for the receiver
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig { url: None, events: Vec::new(), token: None, token_env: None, timeout_secs: 5 }
+    }
+}
+
+impl WebhookConfig {
+    /// The bearer token, preferring the one from `token_env` when it is set.
+    pub fn token(&self) -> Option<String> {
+        self.token_env.as_ref()
+            .and_then(|name| std::env::var(name).ok())
+            .or_else(|| self.token.clone())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// How many days ahead a deadline is notified, 0 for only today's and overdue ones
+    pub within_days: i64,
+    /// Whether to raise one notification listing every task instead of one per task
+    pub summary: bool,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig { within_days: 1, summary: false }
+    }
+}
+
+/// The coefficients of a task's urgency, which `td next` and `ls --sort
+/// urgency` rank by. The defaults are taskwarrior's.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UrgencyWeights {
+    /// Added while the task is DOING (default 4.0)
+    pub doing: f64,
+    /// Added for high priority (default 6.0)
+    pub high: f64,
+    /// Added for medium priority (default 3.9)
+    pub medium: f64,
+    /// Added for low priority (default 1.8)
+    pub low: f64,
+    /// Scaled from a fifth two weeks before the deadline up to the full
+    /// value a week after it (default 12.0)
+    pub due: f64,
+    /// Scaled by how old the task is, reaching the full value at
+    /// `age_max_days` (default 2.0)
+    pub age: f64,
+    /// The age at which a task gets all of `age` (default 365)
+    pub age_max_days: f64,
+    /// Added while another task blocks this one, usually negative (default -5.0)
+    pub blocked: f64,
+    /// Added for each tag, like `tag.bug = 2.0` (default none)
+    pub tag: BTreeMap<String, f64>,
+}
+
+impl Default for UrgencyWeights {
+    fn default() -> Self {
+        UrgencyWeights {
+            doing: 4.0,
+            high: 6.0,
+            medium: 3.9,
+            low: 1.8,
+            due: 12.0,
+            age: 2.0,
+            age_max_days: 365.0,
+            blocked: -5.0,
+            tag: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangelogConfig {
+    /// Section headings by tag, in the order the sections are printed
+    #[serde(deserialize_with = "ordered_pairs", serialize_with = "pairs_as_table")]
+    pub headings: Vec<(String, String)>,
+    /// Tasks with any of these tags are left out
+    pub exclude: Vec<String>,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        ChangelogConfig {
+            headings: [("feature", "Features"), ("fix", "Fixes"), ("chore", "Chores")]
+                .iter()
+                .map(|(tag, heading)| (tag.to_string(), heading.to_string()))
+                .collect(),
+            exclude: vec!["internal".to_string()],
+        }
+    }
+}
+
+/// Reads a table of strings keeping the order the keys were written in.
+fn ordered_pairs<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<(String, String)>, D::Error> {
+    struct Pairs;
+    impl<'de> Visitor<'de> for Pairs {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a table of strings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+            let mut pairs = Vec::new();
+            while let Some(pair) = map.next_entry()? {
+                pairs.push(pair);
+            }
+            Ok(pairs)
+        }
+    }
+    deserializer.deserialize_map(Pairs)
+}
+
+fn pairs_as_table<S: Serializer>(pairs: &[(String, String)], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(pairs.len()))?;
+    for (key, value) in pairs {
+        map.serialize_entry(key, value)?;
+    }
+    map.end()
+}
+
+/// The effective config together with the file every key that was set came from.
+struct Loaded {
+    config: Config,
+    sources: BTreeMap<String, PathBuf>,
+}
+
+static CONFIG: OnceLock<Loaded> = OnceLock::new();
+
+/// The global config file, `~/.td/config.toml`.
+pub fn config_path() -> Result<PathBuf> {
+    Ok(project::default_home()?.join(CONFIG_FILE))
+}
+
+/// Parses a config file into a table, or `None` when it does not exist.
+fn read(path: &Path) -> Result<Option<Mapping>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let value = parse_toml(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    // Deserializing each file on its own points errors at the right file.
+    check(value.clone(), path)?;
+    match value {
+        Value::Mapping(table) => Ok(Some(table)),
+        _ => Err(anyhow!("{}: expected a table", path.display())),
+    }
+}
+
+fn check(value: Value, path: &Path) -> Result<Config> {
+    let config: Config = serde_yaml::from_value(value).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    for (key, format) in [("date_format", &config.date_format), ("datetime_format", &config.datetime_format)] {
+        if let Some(specifier) = dates::invalid_specifier(format) {
+            return Err(anyhow!("{}: '{}' in {} is not a date format specifier", path.display(), specifier, key));
+        }
+    }
+    for (tag, color) in &config.tag_colors {
+        if Color::parse(color).is_none() {
+            return Err(anyhow!("{}: '{}' in [tag_colors] for '{}' is not a color name or #rrggbb value", path.display(), color, tag));
+        }
+    }
+    Ok(config)
+}
+
+/// Copies the keys of `layer` over `base`, merging tables key by key and
+/// recording `source` for every value copied.
+fn merge(base: &mut Mapping, layer: Mapping, prefix: &str, source: &Path, sources: &mut BTreeMap<String, PathBuf>) {
+    for (key, value) in layer {
+        let name = format!("{}{}", prefix, key.as_str().unwrap_or_default());
+        match (base.get_mut(&key), value) {
+            (Some(Value::Mapping(existing)), Value::Mapping(table)) => {
+                merge(existing, table, &format!("{}.", name), source, sources);
+            }
+            (_, value) => {
+                sources.retain(|key, _| key != &name && !key.starts_with(&format!("{}.", name)));
+                record(&value, &name, source, sources);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record(value: &Value, name: &str, source: &Path, sources: &mut BTreeMap<String, PathBuf>) {
+    match value {
+        Value::Mapping(table) => {
+            for (key, value) in table {
+                record(value, &format!("{}.{}", name, key.as_str().unwrap_or_default()), source, sources);
+            }
+        }
+        _ => {
+            sources.insert(name.to_string(), source.to_path_buf());
+        }
+    }
+}
+
+/// Reads the global config and the one of the current project on top of it.
+/// Missing files mean the defaults.
+fn load() -> Result<Loaded> {
+    let global_path = config_path()?;
+    let mut merged = Mapping::new();
+    let mut sources = BTreeMap::new();
+    if let Some(global) = read(&global_path)? {
+        merge(&mut merged, global, "", &global_path, &mut sources);
+    }
+
+    let global = check(Value::Mapping(merged.clone()), &global_path)?;
+    let home = match &global.data_dir {
+        Some(dir) => project::expand_home(dir),
+        None => project::default_home()?,
+    };
+    let project_path = project::project_dir_in(&home, &global)?.join(CONFIG_FILE);
+    if project_path != global_path && let Some(layer) = read(&project_path)? {
+        if let Some(key) = GLOBAL_ONLY.iter().find(|key| layer.contains_key(**key)) {
+            return Err(anyhow!("{}: '{}' can only be set in the global config {}", project_path.display(), key, global_path.display()));
+        }
+        merge(&mut merged, layer, "", &project_path, &mut sources);
+    }
+
+    let config = check(Value::Mapping(merged), &project_path)?;
+    Ok(Loaded { config, sources })
+}
+
+/// Sets `alias = "remote"` in the `[projects]` table of the global config
+/// file, keeping everything else in the file as it is.
+pub fn set_project_alias(alias: &str, remote: &str) -> Result<()> {
+    set_value(&config_path()?, Some("projects"), alias, toml_value(&remote)?)
+}
+
+/// Writes the definition of a context into the `[contexts.<name>]` table of
+/// the global config file.
+pub fn set_context(name: &str, context: &ContextConfig) -> Result<()> {
+    let table = format!("contexts.{}", name);
+    let path = config_path()?;
+    set_value(&path, Some(&table), "statuses", toml_value(&context.statuses)?)?;
+    set_value(&path, Some(&table), "not_tags", toml_value(&context.not_tags)?)?;
+    set_value(&path, Some(&table), "tags", toml_value(&context.tags)?)
+}
+
+/// Sets `key` in the `config.toml` of a project directory, or of `~/.td` for
+/// the global one.
+pub fn set_project_value<T: Serialize>(project_dir: &Path, key: &str, value: &T) -> Result<()> {
+    set_value(&project_dir.join(CONFIG_FILE), None, key, toml_value(value)?)
+}
+
+/// Writes `key = value` into `table` of a config file, a dotted name like
+/// `contexts.work`, or among the top level keys when `table` is `None`. The
+/// rest of the file, comments and layout included, is kept as it is.
+fn set_value(path: &Path, table: Option<&str>, key: &str, value: toml_edit::Value) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut document: toml_edit::DocumentMut = content.parse().map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    let names: Vec<&str> = table.map(|table| table.split('.').collect()).unwrap_or_default();
+    let mut current = document.as_table_mut();
+    for (i, name) in names.iter().enumerate() {
+        let item = current.entry(name).or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            // Only the innermost table gets a `[header]` of its own.
+            table.set_implicit(i + 1 < names.len());
+            toml_edit::Item::Table(table)
+        });
+        current = item.as_table_mut()
+            .ok_or_else(|| anyhow!("Could not set {} in {}: '{}' is not a [table]", key, path.display(), names[..=i].join(".")))?;
+    }
+    current.insert(key, toml_edit::value(value));
+    let updated = document.to_string();
+    // Refuse to write a file the next run could not read.
+    check(parse_toml(&updated)?, path).map_err(|e| anyhow!("Could not set {} in {}: {}", key, path.display(), e))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", CONFIG_FILE));
+    fs::write(&temp_path, updated)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// A value as `set_value` writes it, e.g. a list as an inline array.
+fn toml_value<T: Serialize>(value: &T) -> Result<toml_edit::Value> {
+    Ok(value.serialize(toml_edit::ser::ValueSerializer::new())?)
+}
+
+/// Parses TOML, of a config file or a task's frontmatter, into the value
+/// YAML is read into, so both are deserialized the same way. Dates become
+/// strings.
+pub fn parse_toml(text: &str) -> Result<Value> {
+    fn convert(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(text) => Value::String(text),
+            toml::Value::Integer(number) => Value::Number(number.into()),
+            toml::Value::Float(number) => Value::Number(number.into()),
+            toml::Value::Boolean(flag) => Value::Bool(flag),
+            toml::Value::Datetime(date) => Value::String(date.to_string()),
+            toml::Value::Array(items) => Value::Sequence(items.into_iter().map(convert).collect()),
+            toml::Value::Table(table) => Value::Mapping(table.into_iter().map(|(key, value)| (Value::String(key), convert(value))).collect()),
+        }
+    }
+    Ok(convert(toml::Value::Table(text.parse::<toml::Table>()?)))
+}
+
+/// Writes a table as TOML. TOML has no null, so keys without a value are
+/// left out.
+pub fn to_toml(value: &Value) -> Result<String> {
+    fn without_nulls(value: &Value) -> Value {
+        match value {
+            Value::Mapping(map) => Value::Mapping(map.iter().filter(|(_, value)| !value.is_null()).map(|(key, value)| (key.clone(), without_nulls(value))).collect()),
+            Value::Sequence(items) => Value::Sequence(items.iter().filter(|value| !value.is_null()).map(without_nulls).collect()),
+            other => other.clone(),
+        }
+    }
+    Ok(toml::to_string(&without_nulls(value))?)
+}
+
+/// The saved queries of the config as it applies now, read without keeping
+/// the config, since expanding one may change which config applies.
+pub fn queries() -> Result<BTreeMap<String, String>> {
+    Ok(load()?.config.queries)
+}
+
+/// Loads the config for this invocation. Must be called before `get`.
+pub fn init() -> Result<()> {
+    let loaded = load()?;
+    let _ = CONFIG.set(loaded);
+    Ok(())
+}
+
+/// The config loaded by `init`, or the defaults if it was never called.
+pub fn get() -> &'static Config {
+    &CONFIG.get_or_init(|| Loaded { config: Config::default(), sources: BTreeMap::new() }).config
+}
+
+/// Every effective setting as a dotted key and its value, with the file it
+/// was set in or `None` for defaults.
+pub fn settings() -> Result<Vec<(String, String, Option<&'static Path>)>> {
+    let loaded = CONFIG.get_or_init(|| Loaded { config: Config::default(), sources: BTreeMap::new() });
+    let mut settings = Vec::new();
+    flatten(&serde_yaml::to_value(&loaded.config)?, "", &mut settings);
+    settings.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(settings
+        .into_iter()
+        .map(|(key, value)| {
+            let source = loaded.sources.get(&key).map(PathBuf::as_path);
+            (key, serde_json::to_string(&value).unwrap_or_default(), source)
+        })
+        .collect())
+}
+
+fn flatten(value: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Mapping(table) if !table.is_empty() => {
+            for (key, value) in table {
+                let name = format!("{}{}", prefix, key.as_str().unwrap_or_default());
+                flatten(value, &format!("{}.", name), out);
+            }
+        }
+        value => out.push((prefix.trim_end_matches('.').to_string(), value.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_value_keeps_the_rest_of_the_file() {
+        let dir = std::env::temp_dir().join(format!("td-set-value-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE);
+        let original = "# my settings\ndate_format = \"%d.%m.%Y\"\ntag_colors = { bug = \"red\", feature = \"green\" }\n\n[queries]\ntriage = \"\"\"--status todo\n--sort created\"\"\"\n";
+        fs::write(&path, original).unwrap();
+
+        set_value(&path, None, "utc", toml_value(&true).unwrap()).unwrap();
+        set_value(&path, Some("queries"), "mine", toml_value(&"--tag me").unwrap()).unwrap();
+        set_value(&path, Some("contexts.work"), "tags", toml_value(&["work"]).unwrap()).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# my settings\n"));
+        assert!(written.contains("tag_colors = { bug = \"red\", feature = \"green\" }\n"));
+        assert!(written.contains("triage = \"\"\"--status todo\n--sort created\"\"\"\n"));
+        assert!(written.contains("[contexts.work]\ntags = [\"work\"]\n"));
+        assert!(!written.contains("[contexts]\n"));
+
+        let config = check(parse_toml(&written).unwrap(), &path).unwrap();
+        assert!(config.utc);
+        assert_eq!(config.tag_colors["feature"], "green");
+        assert_eq!(config.queries["triage"], "--status todo\n--sort created");
+        assert_eq!(config.queries["mine"], "--tag me");
+        assert_eq!(config.contexts["work"].tags, ["work"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn toml_dates_read_as_strings() {
+        let value = parse_toml("created_at = 2024-06-01T12:00:00Z\n").unwrap();
+        assert_eq!(value.get("created_at").and_then(Value::as_str), Some("2024-06-01T12:00:00Z"));
+    }
+}
@@ -0,0 +1,70 @@
+use std::{fs, sync::atomic::{AtomicBool, Ordering}};
+use anyhow::Result;
+
+use crate::config::{self, ContextConfig};
+use crate::filter::Filter;
+use crate::project::td_home;
+
+/// Holds the name of the active context.
+const STATE_FILE: &str = ".context";
+
+/// Set by `--no-context` to list everything for this invocation.
+static CONTEXT_OFF: AtomicBool = AtomicBool::new(false);
+
+pub fn disable(off: bool) {
+    CONTEXT_OFF.store(off, Ordering::Relaxed);
+}
+
+/// The name of the context chosen with `td context`, even when `--no-context`
+/// is given or it is no longer defined.
+pub fn selected() -> Result<Option<String>> {
+    match fs::read_to_string(td_home()?.join(STATE_FILE)) {
+        Ok(name) => Ok(Some(name.trim().to_string()).filter(|name| !name.is_empty())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Makes `name` the active context, or clears it with `None`.
+pub fn select(name: Option<&str>) -> Result<()> {
+    let path = td_home()?.join(STATE_FILE);
+    match name {
+        Some(name) => fs::write(path, format!("{}\n", name))?,
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+/// The context listings apply in this invocation with its definition.
+pub fn active() -> Result<Option<(String, &'static ContextConfig)>> {
+    if CONTEXT_OFF.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+    let Some(name) = selected()? else {
+        return Ok(None);
+    };
+    match config::get().contexts.get(&name) {
+        Some(context) => Ok(Some((name, context))),
+        None => {
+            eprintln!("warning: the active context '{}' is not defined, listing everything", name);
+            Ok(None)
+        }
+    }
+}
+
+/// Narrows `filter` by the active context and returns its name. Statuses
+/// given on the command line win over those of the context.
+pub fn apply(filter: &mut Filter) -> Result<Option<String>> {
+    let Some((name, context)) = active()? else {
+        return Ok(None);
+    };
+    filter.tags.extend(context.tags.iter().cloned());
+    filter.not_tags.extend(context.not_tags.iter().cloned());
+    if filter.statuses.is_empty() {
+        filter.statuses = context.statuses.clone();
+    }
+    Ok(Some(name))
+}
@@ -0,0 +1,402 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce, aead::{Aead, KeyInit}};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, ops::Range, path::{Path, PathBuf}, sync::Mutex};
+use anyhow::{Result, anyhow};
+
+use crate::project::{project_name, project_root};
+use crate::task::{Frontmatter, Task};
+
+/// The file in a project directory that turns on encryption for its tasks.
+/// It holds what the key is derived with and never the key itself.
+pub const KEY_FILE: &str = ".encryption";
+
+/// The environment variable read for the passphrase before asking for it.
+pub const PASSPHRASE_ENV: &str = "TD_PASSPHRASE";
+
+/// Surrounds an encrypted description, or a whole encrypted task file.
+const BEGIN: &str = "-----BEGIN TD ENCRYPTED-----";
+const END: &str = "-----END TD ENCRYPTED-----";
+
+/// Starts the title of a task whose title is encrypted.
+const TITLE_PREFIX: &str = "td-encrypted:";
+
+/// Encrypted with the key when encryption is turned on, so a wrong
+/// passphrase is told apart from a damaged task file.
+const CHECK: &[u8] = b"td";
+
+const NONCE_LEN: usize = 24;
+
+/// How much of a task is encrypted, from just the description to the whole
+/// file. Listing can only do without the passphrase for `body`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// The description, the frontmatter stays readable
+    Body,
+    /// The description and the title
+    Title,
+    /// The whole file, frontmatter included
+    All,
+}
+
+/// The content of the key file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub scope: Scope,
+    /// The Argon2id salt and costs the key is derived with
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    check: String,
+}
+
+/// The keys derived in this process by project directory, so the passphrase
+/// is asked for once however many files are read.
+static KEYS: Mutex<BTreeMap<PathBuf, [u8; 32]>> = Mutex::new(BTreeMap::new());
+
+/// The encryption settings of the project a task directory belongs to,
+/// `None` when its tasks are stored as plain text.
+pub fn settings(dir: &Path) -> Result<Option<Settings>> {
+    let path = project_root(dir)?.join(KEY_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_str(&content).map(Some).map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))
+}
+
+/// Whether the tasks of the project are encrypted at all.
+pub fn is_encrypted(dir: &Path) -> bool {
+    project_root(dir).is_ok_and(|root| root.join(KEY_FILE).exists())
+}
+
+/// Whether the titles of the project's tasks are encrypted, so they must be
+/// kept out of file names, the index and the journal.
+pub fn hides_titles(dir: &Path) -> bool {
+    settings(dir).ok().flatten().is_some_and(|settings| settings.scope >= Scope::Title)
+}
+
+/// Turns on encryption for a project with a key derived from `passphrase`,
+/// or changes how much of its tasks is encrypted. The files are converted
+/// by `convert` afterwards.
+pub fn enable(project_dir: &Path, scope: Scope, passphrase: Option<&str>) -> Result<Settings> {
+    let settings = match settings(project_dir)? {
+        Some(existing) => Settings { scope, ..existing },
+        None => {
+            let passphrase = passphrase.ok_or_else(|| anyhow!("A passphrase is needed to turn on encryption"))?;
+            let params = Params::default();
+            let mut settings = Settings {
+                scope,
+                salt: BASE64.encode(random::<16>()?),
+                memory_kib: params.m_cost(),
+                iterations: params.t_cost(),
+                check: String::new(),
+            };
+            let key = derive(passphrase, &settings)?;
+            settings.check = BASE64.encode(encrypt(&key, CHECK)?);
+            KEYS.lock().unwrap_or_else(|e| e.into_inner()).insert(project_root(project_dir)?, key);
+            settings
+        }
+    };
+    let path = project_root(project_dir)?.join(KEY_FILE);
+    let temp_path = path.with_file_name(format!("{}.tmp", KEY_FILE));
+    fs::write(&temp_path, serde_json::to_string_pretty(&settings)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(settings)
+}
+
+/// Turns encryption off once every task file is plain text again.
+pub fn disable(project_dir: &Path) -> Result<()> {
+    fs::remove_file(project_root(project_dir)?.join(KEY_FILE))?;
+    Ok(())
+}
+
+/// Reads a task file as plain text, decrypting what is encrypted in it. A
+/// project can hold plain and encrypted files at once, while it is being
+/// converted, and both read the same. Files of a project without a key file
+/// are plain text whatever they say.
+pub fn read(path: &Path) -> Result<String> {
+    decrypt_file(path, fs::read_to_string(path)?, true)
+}
+
+/// Reads a task file like `read` for listing: an encrypted description is
+/// left out instead of asking for the passphrase, which is then only needed
+/// when the frontmatter is encrypted too.
+pub fn read_metadata(path: &Path) -> Result<String> {
+    decrypt_file(path, fs::read_to_string(path)?, false)
+}
+
+fn decrypt_file(path: &Path, content: String, with_description: bool) -> Result<String> {
+    let dir = path.parent().unwrap_or(path);
+    if !is_encrypted(dir) {
+        return Ok(content);
+    }
+    let damaged = |problem: &str| anyhow!("Could not decrypt {}: {}, the file is damaged or was changed outside td", path.display(), problem);
+    // A wrong passphrase is reported as it is, only files the right key
+    // does not open are damaged.
+    let open = |block: &str| -> Result<String> {
+        let key = key(dir)?;
+        open(&key, block).and_then(|plain| String::from_utf8(plain).map_err(|_| "the decrypted text is not UTF-8")).map_err(damaged)
+    };
+    if is_armored(&content) {
+        return open(&content);
+    }
+    let Ok((_, frontmatter, description)) = Task::split(&content) else {
+        return Ok(content);
+    };
+    let head = &content[..content.len() - description.len()];
+    let description = match is_armored(description) {
+        true if with_description => open(description)?,
+        true => String::new(),
+        false => description.to_string(),
+    };
+    // The title is encrypted in place, and a JSON string reads as both a
+    // YAML and a TOML string.
+    let offset = frontmatter.as_ptr() as usize - content.as_ptr() as usize;
+    let mut head = head.to_string();
+    if let Some((value, sealed)) = sealed_title(frontmatter) {
+        let title = open(&format!("{}\n{}\n{}", BEGIN, &frontmatter[sealed], END))?;
+        head.replace_range(offset + value.start..offset + value.end, &serde_json::to_string(&title)?);
+    }
+    Ok(head + &description)
+}
+
+/// Whether `text` starts with an armored block the way `armor` writes it,
+/// the marker on a line of its own. Text that only mentions the marker is
+/// plain text, a block that is cut off is still found and then reported.
+fn is_armored(text: &str) -> bool {
+    text.strip_prefix(BEGIN).is_some_and(|rest| rest.starts_with('\n'))
+}
+
+/// Where the value of the `title` key is in a frontmatter, quotes included,
+/// and where the encrypted title inside it is, when the whole value is
+/// `TITLE_PREFIX` followed by base64. A title that merely mentions the
+/// prefix is plain text.
+fn sealed_title(frontmatter: &str) -> Option<(Range<usize>, Range<usize>)> {
+    let mut line_start = 0;
+    for line in frontmatter.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+        let Some(value) = line.strip_prefix("title").map(|rest| rest.trim_start_matches(' ')).and_then(|rest| rest.strip_prefix([':', '='])) else {
+            continue;
+        };
+        let value = value.trim_start_matches(' ');
+        let value_start = start + line.len() - value.len();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''));
+        let sealed = value[quote.map_or(0, char::len_utf8)..].strip_prefix(TITLE_PREFIX)?;
+        let sealed_len = sealed.find(|c: char| !is_base64(c)).unwrap_or(sealed.len());
+        let sealed_start = value_start + quote.map_or(0, char::len_utf8) + TITLE_PREFIX.len();
+        let rest = &sealed[sealed_len..];
+        let rest = match quote {
+            Some(quote) => rest.strip_prefix(quote)?,
+            None => rest,
+        };
+        if sealed_len == 0 || !rest.trim().is_empty() {
+            return None;
+        }
+        let value_end = start + line.len() - rest.len();
+        return Some((value_start..value_end, sealed_start..sealed_start + sealed_len));
+    }
+    None
+}
+
+/// How much of a task file is encrypted as it is stored, `None` for plain text.
+pub fn stored_scope(path: &Path) -> Result<Option<Scope>> {
+    let content = fs::read_to_string(path)?;
+    if is_armored(&content) {
+        return Ok(Some(Scope::All));
+    }
+    let Ok((_, frontmatter, description)) = Task::split(&content) else {
+        return Ok(None);
+    };
+    Ok(match (is_armored(description), sealed_title(frontmatter).is_some()) {
+        (_, true) => Some(Scope::Title),
+        (true, false) => Some(Scope::Body),
+        (false, false) => None,
+    })
+}
+
+/// The content a task is stored with in `dir`: as `Task::to_string_as` writes
+/// it, with the parts the project encrypts encrypted.
+pub fn seal(dir: &Path, task: &Task, style: Frontmatter) -> Result<String> {
+    let Some(settings) = settings(dir)? else {
+        return task.to_string_as(style);
+    };
+    let key = key(dir)?;
+    if settings.scope == Scope::All {
+        return armor(&encrypt(&key, task.to_string_as(style)?.as_bytes())?);
+    }
+    let mut sealed = task.clone();
+    sealed.description = armor(&encrypt(&key, task.description.as_bytes())?)?;
+    if settings.scope == Scope::Title {
+        sealed.metadata.title = format!("{}{}", TITLE_PREFIX, BASE64.encode(encrypt(&key, task.metadata.title.as_bytes())?));
+    }
+    sealed.to_string_as(style)
+}
+
+/// Moves a task file to `target`, encrypting or decrypting it when the two
+/// projects do not store tasks the same way.
+pub fn move_task(path: &Path, target: &Path, task: &Task) -> Result<()> {
+    let from = project_root(path.parent().unwrap_or(path))?;
+    let target_dir = target.parent().unwrap_or(target);
+    if from == project_root(target_dir)? || (!is_encrypted(&from) && !is_encrypted(target_dir)) {
+        fs::rename(path, target)?;
+        return Ok(());
+    }
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = target.with_file_name(format!(".{}.tmp", file_name));
+    fs::write(&temp_path, seal(target_dir, task, crate::config::get().frontmatter)?)?;
+    fs::rename(&temp_path, target)?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// The key of the project `dir` belongs to, asking for the passphrase the
+/// first time. A passphrase that does not open the check value is refused
+/// before any file is looked at.
+fn key(dir: &Path) -> Result<[u8; 32]> {
+    let root = project_root(dir)?;
+    // Held while asking, so threads reading other files wait for the one
+    // passphrase instead of asking again.
+    let mut keys = KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = keys.get(&root) {
+        return Ok(*key);
+    }
+    let name = project_name(&root);
+    let settings = settings(&root)?
+        .ok_or_else(|| anyhow!("A task of {} is encrypted, but the project has no {} to decrypt it with", name, KEY_FILE))?;
+    let passphrase = passphrase(&format!("Passphrase for {}: ", name))?;
+    let key = derive(&passphrase, &settings)?;
+    let opens = BASE64.decode(&settings.check).is_ok_and(|check| decrypt(&key, &check).is_ok_and(|plain| plain == CHECK));
+    if !opens {
+        return Err(anyhow!("Wrong passphrase for the encrypted project {}", name));
+    }
+    keys.insert(root, key);
+    Ok(key)
+}
+
+fn derive(passphrase: &str, settings: &Settings) -> Result<[u8; 32]> {
+    let salt = BASE64.decode(&settings.salt).map_err(|_| anyhow!("The salt in {} is not valid base64", KEY_FILE))?;
+    let params = Params::new(settings.memory_kib, settings.iterations, 1, Some(32)).map_err(|e| anyhow!("Invalid key derivation costs in {}: {}", KEY_FILE, e))?;
+    let mut key = [0; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| anyhow!("Could not derive the key: {}", e))?;
+    Ok(key)
+}
+
+/// The passphrase from `TD_PASSPHRASE`, or typed into the terminal without
+/// showing it.
+pub fn passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    read_hidden(prompt)
+}
+
+#[cfg(unix)]
+fn read_hidden(prompt: &str) -> Result<String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::fd::AsRawFd;
+
+    let mut tty = fs::OpenOptions::new().read(true).write(true).open("/dev/tty")
+        .map_err(|_| anyhow!("The tasks are encrypted: set {} or run td in a terminal to enter the passphrase", PASSPHRASE_ENV))?;
+    let fd = tty.as_raw_fd();
+    // Only reads and writes the terminal settings of a descriptor we own.
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    let hidden = unsafe { libc::tcgetattr(fd, &mut original) } == 0;
+    if hidden {
+        let mut silent = original;
+        silent.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) };
+    }
+    write!(tty, "{}", prompt)?;
+    tty.flush()?;
+    let mut line = String::new();
+    let read = BufReader::new(&tty).read_line(&mut line);
+    if hidden {
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    }
+    writeln!(tty)?;
+    read?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(not(unix))]
+fn read_hidden(_prompt: &str) -> Result<String> {
+    Err(anyhow!("The tasks are encrypted: set {} to the passphrase", PASSPHRASE_ENV))
+}
+
+fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("Could not get random bytes: {}", e))?;
+    Ok(bytes)
+}
+
+/// A random nonce followed by the ciphertext.
+fn encrypt(key: &[u8; 32], plain: &[u8]) -> Result<Vec<u8>> {
+    let nonce = random::<NONCE_LEN>()?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let mut sealed = nonce.to_vec();
+    sealed.extend(cipher.encrypt(XNonce::from_slice(&nonce), plain).map_err(|_| anyhow!("Could not encrypt"))?);
+    Ok(sealed)
+}
+
+fn decrypt(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, chacha20poly1305::Error> {
+    if sealed.len() < NONCE_LEN {
+        return Err(chacha20poly1305::Error);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(Key::from_slice(key)).decrypt(XNonce::from_slice(nonce), ciphertext)
+}
+
+/// Decrypts an armored block, telling what is wrong with it when it can not be.
+fn open(key: &[u8; 32], block: &str) -> Result<Vec<u8>, &'static str> {
+    let inner = block.trim_end().strip_prefix(BEGIN).and_then(|rest| rest.strip_suffix(END))
+        .ok_or("the encrypted block is cut off")?;
+    let sealed = BASE64.decode(inner.split_whitespace().collect::<String>()).map_err(|_| "the encrypted block is not valid base64")?;
+    decrypt(key, &sealed).map_err(|_| "the encrypted block does not decrypt with the key of the project")
+}
+
+/// Base64 between the markers, in lines of 64 characters.
+fn armor(sealed: &[u8]) -> Result<String> {
+    let encoded = BASE64.encode(sealed);
+    let lines: Vec<&str> = encoded.as_bytes().chunks(64).map(|line| std::str::from_utf8(line)).collect::<Result<_, _>>()?;
+    Ok(format!("{}\n{}\n{}\n", BEGIN, lines.join("\n"), END))
+}
+
+fn is_base64(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_title_only_matches_the_value() {
+        let yaml = "title: td-encrypted:QUJD\nstatus: todo\n";
+        assert_eq!(sealed_title(yaml), Some((7..24, 20..24)));
+        let toml = "id = \"x\"\ntitle = \"td-encrypted:QUJD\"\n";
+        let (value, sealed) = sealed_title(toml).unwrap();
+        assert_eq!((&toml[value], &toml[sealed]), ("\"td-encrypted:QUJD\"", "QUJD"));
+        assert_eq!(sealed_title("title: About td-encrypted:QUJD\n"), None);
+        assert_eq!(sealed_title("title: td-encrypted:QUJD and more\n"), None);
+        assert_eq!(sealed_title("title: plain\nnote: td-encrypted:QUJD\n"), None);
+        assert_eq!(sealed_title("titles: td-encrypted:QUJD\n"), None);
+    }
+
+    #[test]
+    fn armored_blocks_round_trip() {
+        let key = [7; 32];
+        let block = armor(&encrypt(&key, b"secret plans").unwrap()).unwrap();
+        assert!(is_armored(&block));
+        assert!(!is_armored(&format!("{} in a sentence", BEGIN)));
+        assert_eq!(open(&key, &block).unwrap(), b"secret plans");
+        assert!(open(&[8; 32], &block).is_err());
+    }
+}
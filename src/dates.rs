@@ -0,0 +1,277 @@
+use chrono::{DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono::format::{Item, StrftimeItems};
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{Result, anyhow};
+
+use crate::config;
+
+/// Set by `--utc` or the `utc` config key to show and read dates in UTC
+/// instead of the local timezone.
+static UTC: AtomicBool = AtomicBool::new(false);
+
+pub fn use_utc(utc: bool) {
+    UTC.store(utc, Ordering::Relaxed);
+}
+
+/// A point in time in the timezone dates are shown in. Files and machine
+/// readable output always use UTC.
+pub fn local(time: DateTime<Utc>) -> DateTime<FixedOffset> {
+    if UTC.load(Ordering::Relaxed) {
+        time.fixed_offset()
+    } else {
+        time.with_timezone(&Local).fixed_offset()
+    }
+}
+
+/// A day in the configured `date_format`, `2024-06-01` by default.
+pub fn format_day(time: DateTime<Utc>) -> String {
+    local(time).format(&config::get().date_format).to_string()
+}
+
+/// A day and time in the configured `datetime_format`, `2024-06-01 14:30`
+/// by default.
+pub fn format_time(time: DateTime<Utc>) -> String {
+    local(time).format(&config::get().datetime_format).to_string()
+}
+
+/// The first specifier chrono does not know in a format string, like `%Q`.
+/// Formatting with one would panic, so formats are checked when the config
+/// is loaded.
+pub fn invalid_specifier(format: &str) -> Option<String> {
+    let valid = |spec: &str| !StrftimeItems::new(spec).any(|item| item == Item::Error);
+    if valid(format) {
+        return None;
+    }
+    for (start, _) in format.match_indices('%') {
+        let rest = &format[start..];
+        // Padding and width modifiers make a specifier up to four characters long.
+        let ends: Vec<usize> = rest.char_indices().map(|(i, c)| i + c.len_utf8()).skip(1).take(4).collect();
+        if !ends.iter().any(|end| valid(&rest[..*end])) {
+            return Some(rest[..ends.first().copied().unwrap_or(rest.len())].to_string());
+        }
+    }
+    Some(format.to_string())
+}
+
+/// Parses a date expression like `2024-06-01`, `tomorrow`, `next friday` or
+/// `in 3 days` into a point in time. Expressions that only name a day resolve
+/// to the start of that day.
+pub fn parse_when(input: &str) -> Result<DateTime<Utc>> {
+    parse_when_from(input, Utc::now())
+}
+
+pub fn parse_when_from(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let input = input.trim().to_lowercase();
+    let today = day_of(now);
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(&input.to_uppercase()) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(&input, format) {
+            return Ok(from_local(dt));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Ok(start_of_day(date));
+    }
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["now"] => return Ok(now),
+        ["today"] => today,
+        ["tomorrow"] => today + Days::new(1),
+        ["yesterday"] => today - Days::new(1),
+        ["next", "week"] => next_weekday(today, Weekday::Mon),
+        ["next", "month"] => first_of_month(today) + Months::new(1),
+        ["next", day] | [day] if parse_weekday(day).is_some() => {
+            next_weekday(today, parse_weekday(day).expect("checked above"))
+        }
+        ["in", amount, unit] => offset(today, amount, unit)?,
+        ["in", compact] | [compact] => {
+            let compact = compact.trim_start_matches('+');
+            let split = compact.find(|c: char| !c.is_ascii_digit())
+                .filter(|&split| split > 0)
+                .ok_or_else(|| anyhow!("Could not understand the date '{}'", input))?;
+            offset(today, &compact[..split], &compact[split..])?
+        }
+        _ => return Err(anyhow!("Could not understand the date '{}'", input)),
+    };
+    Ok(start_of_day(date))
+}
+
+/// Parses the start of a window reaching back to now. Weekday names mean the
+/// most recent one before today, so `monday` on a Wednesday is two days ago;
+/// everything else is read like `parse_when`.
+pub fn parse_since(input: &str) -> Result<DateTime<Utc>> {
+    let now = Utc::now();
+    let lower = input.trim().to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    match words.as_slice() {
+        ["last", day] | [day] if parse_weekday(day).is_some() => {
+            let weekday = parse_weekday(day).expect("checked above");
+            Ok(start_of_day(next_weekday(day_of(now), weekday) - Days::new(7)))
+        }
+        _ => parse_when_from(input, now),
+    }
+}
+
+/// The point a period like `30d`, `2w` or `3 months` reaches back to from
+/// `now`, counted in whole days.
+pub fn before(period: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let ahead = parse_when_from(&format!("in {}", period.trim()), now)?;
+    Ok(now - chrono::Duration::days((day_of(ahead) - day_of(now)).num_days()))
+}
+
+/// The start of the last working day before `day`: Friday for a Monday or a
+/// weekend, otherwise the day before.
+pub fn previous_working_day(day: NaiveDate) -> DateTime<Utc> {
+    let back = match day.weekday() {
+        Weekday::Mon => 3,
+        Weekday::Sun => 2,
+        _ => 1,
+    };
+    start_of_day(day - Days::new(back))
+}
+
+/// The current calendar day, which date-only comparisons like "due today"
+/// and "overdue" are made against. Days start at local midnight.
+pub fn today() -> NaiveDate {
+    day_of(Utc::now())
+}
+
+/// The calendar day a point in time falls on in the displayed timezone.
+pub fn day_of(time: DateTime<Utc>) -> NaiveDate {
+    if UTC.load(Ordering::Relaxed) { day_in(time, &Utc) } else { day_in(time, &Local) }
+}
+
+fn day_in<Tz: TimeZone>(time: DateTime<Utc>, zone: &Tz) -> NaiveDate {
+    time.with_timezone(zone).date_naive()
+}
+
+/// A short, rounded duration like `45m`, `3h`, `2d` or `5w`.
+pub fn short_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes().abs();
+    match minutes {
+        m if m < 60 => format!("{}m", m),
+        m if m < 60 * 24 => format!("{}h", m / 60),
+        m if m < 60 * 24 * 14 => format!("{}d", m / (60 * 24)),
+        m if m < 60 * 24 * 365 => format!("{}w", m / (60 * 24 * 7)),
+        m => format!("{}y", m / (60 * 24 * 365)),
+    }
+}
+
+/// How long ago `since` was, e.g. `3d`.
+pub fn age(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    short_duration(now - since)
+}
+
+/// How far away a due date is in days: `today`, `in 3d` or `2d overdue`.
+pub fn due_in(due: DateTime<Utc>) -> String {
+    let days = (day_of(due) - today()).num_days();
+    match days {
+        0 => "today".to_string(),
+        d if d > 0 => format!("in {}d", d),
+        d => format!("{}d overdue", -d),
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    from_local(date.and_time(NaiveTime::MIN))
+}
+
+/// Reads a wall clock time in the displayed timezone. A time skipped by a
+/// daylight saving change is taken as UTC rather than failing.
+fn from_local(time: NaiveDateTime) -> DateTime<Utc> {
+    if UTC.load(Ordering::Relaxed) {
+        return Utc.from_utc_datetime(&time);
+    }
+    from_zone(time, &Local)
+}
+
+fn from_zone<Tz: TimeZone>(time: NaiveDateTime, zone: &Tz) -> DateTime<Utc> {
+    zone.from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&time))
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("every month has a first day")
+}
+
+/// The next date after `from` falling on `weekday`, never `from` itself.
+fn next_weekday(from: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+    from + Days::new(if ahead == 0 { 7 } else { ahead as u64 })
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn offset(from: NaiveDate, amount: &str, unit: &str) -> Result<NaiveDate> {
+    let amount: u32 = amount.parse()
+        .map_err(|_| anyhow!("'{}' is not a number of days, weeks or months", amount))?;
+    let date = match unit {
+        "d" | "day" | "days" => from.checked_add_days(Days::new(amount as u64)),
+        "w" | "week" | "weeks" => from.checked_add_days(Days::new(amount as u64 * 7)),
+        "m" | "month" | "months" => from.checked_add_months(Months::new(amount)),
+        _ => return Err(anyhow!("Unknown unit '{}', expected days, weeks or months", unit)),
+    };
+    date.ok_or_else(|| anyhow!("The date is out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn days_turn_at_local_midnight() {
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        // 23:30 on the 14th in New York is already the 15th in UTC.
+        assert_eq!(day_in(utc("2025-01-15T04:30:00Z"), &new_york), day("2025-01-14"));
+        assert_eq!(day_in(utc("2025-01-15T04:30:00Z"), &Utc), day("2025-01-15"));
+        assert_eq!(day_in(utc("2025-01-15T05:00:00Z"), &new_york), day("2025-01-15"));
+        // Midnight in Tokyo is 15:00 UTC the day before.
+        assert_eq!(day_in(utc("2025-01-14T14:59:59Z"), &tokyo), day("2025-01-14"));
+        assert_eq!(day_in(utc("2025-01-14T15:00:00Z"), &tokyo), day("2025-01-15"));
+    }
+
+    #[test]
+    fn due_today_is_decided_by_the_local_day() {
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        // Added at 9pm for the same evening: due today, not tomorrow.
+        let now = utc("2025-01-15T02:00:00Z");
+        let due = utc("2025-01-15T04:00:00Z");
+        assert_eq!(day_in(due, &new_york), day_in(now, &new_york));
+        assert!(day_in(utc("2025-01-14T04:59:59Z"), &new_york) < day_in(now, &new_york));
+    }
+
+    #[test]
+    fn a_day_starts_at_midnight_in_the_zone() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let midnight = day("2025-01-15").and_time(NaiveTime::MIN);
+        assert_eq!(from_zone(midnight, &tokyo), utc("2025-01-14T15:00:00Z"));
+        assert_eq!(from_zone(midnight, &Utc), utc("2025-01-15T00:00:00Z"));
+        assert_eq!(day_in(from_zone(midnight, &tokyo), &tokyo), day("2025-01-15"));
+    }
+}
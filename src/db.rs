@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use crate::index::IndexEntry;
+
+/// The SQLite copy of the index of every project, kept in the data directory
+/// and only used once `td reindex --full` created it. The task files stay
+/// what counts: the database is refreshed from them whenever a file
+/// changed, and deleting it loses nothing. Encrypted projects are
+/// kept out of it.
+pub const DB_FILE: &str = "index.db";
+
+/// The tasks of a task directory as the database has them, bringing it up
+/// to date first. `None` means the files have to be read instead, because
+/// there is no database or it could not be used.
+pub fn entries(dir: &Path) -> Option<Vec<IndexEntry>> {
+    #[cfg(feature = "sqlite")]
+    return sqlite::fallback(sqlite::entries(dir));
+    #[cfg(not(feature = "sqlite"))]
+    {
+        let _ = dir;
+        None
+    }
+}
+
+/// The task files of a directory whose title or description may contain
+/// `query`, found with the full text index. Every task that matches is
+/// among them, `None` means all of them have to be looked at.
+pub fn search(dir: &Path, query: &str) -> Option<Vec<PathBuf>> {
+    #[cfg(feature = "sqlite")]
+    return sqlite::fallback(sqlite::search(dir, query));
+    #[cfg(not(feature = "sqlite"))]
+    {
+        let _ = (dir, query);
+        None
+    }
+}
+
+/// Creates the database anew from the task files of every project, returning
+/// how many tasks and projects it holds.
+pub fn rebuild() -> Result<(usize, usize)> {
+    #[cfg(feature = "sqlite")]
+    return sqlite::rebuild();
+    #[cfg(not(feature = "sqlite"))]
+    Err(anyhow::anyhow!("This td was built without SQLite support, build it with `--features sqlite` to use {}", DB_FILE))
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use rusqlite::{Connection, OptionalExtension, params};
+    use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}, time::{Duration, SystemTime, UNIX_EPOCH}};
+    use anyhow::{Result, anyhow};
+
+    use super::DB_FILE;
+    use crate::checklist;
+    use crate::config;
+    use crate::crypt;
+    use crate::index::IndexEntry;
+    use crate::normalize;
+    use crate::parallel;
+    use crate::project::{TASK_EXTENSION, list_projects, td_home, with_scopes};
+    use crate::task::Task;
+
+    /// Directories changed more recently than this may change again without
+    /// their modification time moving, so they are not marked as up to date.
+    const RACY: Duration = Duration::from_secs(2);
+
+    /// The trigram index only finds substrings of at least this many characters.
+    const MIN_QUERY: usize = 3;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS dirs (dir TEXT PRIMARY KEY, modified INTEGER NOT NULL);
+        CREATE TABLE IF NOT EXISTS tasks (
+            path TEXT PRIMARY KEY,
+            dir TEXT NOT NULL,
+            modified INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            metadata TEXT NOT NULL,
+            checklist TEXT NOT NULL,
+            problem TEXT
+        );
+        CREATE INDEX IF NOT EXISTS tasks_by_dir ON tasks (dir);
+        CREATE TABLE IF NOT EXISTS tags (path TEXT NOT NULL, tag TEXT NOT NULL);
+        CREATE INDEX IF NOT EXISTS tags_by_path ON tags (path);
+        CREATE VIRTUAL TABLE IF NOT EXISTS text USING fts5 (path UNINDEXED, dir UNINDEXED, title, description, tokenize = 'trigram');
+    ";
+
+    /// Errors of the database itself are reported, anything else is left
+    /// for reading the files to run into again.
+    pub fn fallback<T>(result: Result<Option<T>>) -> Option<T> {
+        match result {
+            Ok(value) => value,
+            Err(e) => {
+                if e.downcast_ref::<rusqlite::Error>().is_some() {
+                    eprintln!("warning: not using {}: {:#}", DB_FILE, e);
+                }
+                None
+            }
+        }
+    }
+
+    fn open() -> Result<Option<Connection>> {
+        let path = td_home()?.join(DB_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(connect(&path)?))
+    }
+
+    fn connect(path: &Path) -> Result<Connection> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(2))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+
+    pub fn entries(dir: &Path) -> Result<Option<Vec<IndexEntry>>> {
+        let Some(mut conn) = open()? else {
+            return Ok(None);
+        };
+        if !usable(&conn, dir)? {
+            return Ok(None);
+        }
+        sync(&mut conn, dir)?;
+        let mut statement = conn.prepare("SELECT path, metadata, checklist, problem FROM tasks WHERE dir = ?1 ORDER BY path")?;
+        let rows = statement.query_map([key(dir)], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, Option<String>>(3)?)))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (path, metadata, progress, problem) = row?;
+            entries.push(IndexEntry {
+                path: PathBuf::from(path),
+                metadata: serde_json::from_str(&metadata)?,
+                checklist: serde_json::from_str(&progress)?,
+                problem,
+            });
+        }
+        Ok(Some(entries))
+    }
+
+    pub fn search(dir: &Path, query: &str) -> Result<Option<Vec<PathBuf>>> {
+        // The text is stored case folded only, accents have to be compared
+        // by reading the files.
+        let needle = normalize::fold_case(query.trim());
+        if config::get().fold_diacritics || needle.chars().count() < MIN_QUERY {
+            return Ok(None);
+        }
+        let Some(mut conn) = open()? else {
+            return Ok(None);
+        };
+        if !usable(&conn, dir)? {
+            return Ok(None);
+        }
+        sync(&mut conn, dir)?;
+        let phrase = format!("\"{}\"", needle.replace('"', "\"\""));
+        let mut statement = conn.prepare("SELECT path FROM text WHERE dir = ?1 AND text MATCH ?2 ORDER BY path")?;
+        let paths = statement.query_map(params![key(dir), format!("{{title description}} : {}", phrase)], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(paths))
+    }
+
+    pub fn rebuild() -> Result<(usize, usize)> {
+        let path = td_home()?.join(DB_FILE);
+        let temp_path = path.with_file_name(format!(".{}.tmp", DB_FILE));
+        match fs::remove_file(&temp_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let mut conn = connect(&temp_path)?;
+        let projects: Vec<_> = list_projects()?.into_iter().filter(|(_, project_dir)| !crypt::is_encrypted(project_dir)).collect();
+        for (_, project_dir) in &projects {
+            for dir in with_scopes(project_dir)? {
+                sync(&mut conn, &dir)?;
+            }
+        }
+        let tasks: i64 = conn.query_row("SELECT count(*) FROM tasks", [], |row| row.get(0))?;
+        drop(conn);
+        fs::rename(&temp_path, &path)?;
+        Ok((tasks as usize, projects.len()))
+    }
+
+    /// Whether the tasks of `dir` may be kept in the database, dropping what
+    /// it has of them once their project is encrypted.
+    fn usable(conn: &Connection, dir: &Path) -> Result<bool> {
+        if !crypt::is_encrypted(dir) {
+            return Ok(true);
+        }
+        conn.execute_batch("BEGIN")?;
+        for statement in [
+            "DELETE FROM tags WHERE path IN (SELECT path FROM tasks WHERE dir = ?1)",
+            "DELETE FROM text WHERE dir = ?1",
+            "DELETE FROM tasks WHERE dir = ?1",
+            "DELETE FROM dirs WHERE dir = ?1",
+        ] {
+            conn.execute(statement, [key(dir)])?;
+        }
+        conn.execute_batch("COMMIT")?;
+        Ok(false)
+    }
+
+    fn key(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    fn modified(metadata: &fs::Metadata) -> Result<i64> {
+        Ok(metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as i64)
+    }
+
+    /// Brings the rows of a task directory up to date, parsing only the
+    /// files whose modification time or size changed. The directory listing
+    /// is only read again when the directory itself changed, which is when a
+    /// file was added, removed or renamed; a file edited in place leaves it
+    /// alone.
+    fn sync(conn: &mut Connection, dir: &Path) -> Result<()> {
+        let dir_key = key(dir);
+        let dir_metadata = fs::metadata(dir)?;
+        let dir_modified = modified(&dir_metadata)?;
+        let stored: Option<i64> = conn.query_row("SELECT modified FROM dirs WHERE dir = ?1", [&dir_key], |row| row.get(0)).optional()?;
+
+        let mut known: HashMap<String, (i64, i64)> = HashMap::new();
+        {
+            let mut statement = conn.prepare("SELECT path, modified, size FROM tasks WHERE dir = ?1")?;
+            for row in statement.query_map([&dir_key], |row| Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?))))? {
+                let (path, stamp) = row?;
+                known.insert(path, stamp);
+            }
+        }
+        let listed = stored == Some(dir_modified);
+        let paths: Vec<PathBuf> = match listed {
+            true => known.keys().map(PathBuf::from).collect(),
+            false => fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION))
+                .collect(),
+        };
+        let mut present = HashSet::new();
+        let mut changed = Vec::new();
+        for path in paths {
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                // Removed since the directory was looked at.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let stamp = (modified(&metadata)?, metadata.len() as i64);
+            if known.get(&key(&path)) != Some(&stamp) {
+                changed.push((path.clone(), stamp));
+            }
+            present.insert(key(&path));
+        }
+        if listed && changed.is_empty() && present.len() == known.len() {
+            return Ok(());
+        }
+        let parsed = parallel::map(&changed, |(path, _)| {
+            let content = fs::read_to_string(path)?;
+            Task::from_str_lenient(&content).map_err(|e| anyhow!("Could not parse {}: {}", path.display(), e))
+        });
+
+        let tx = conn.transaction()?;
+        let forget = |path: &str| -> rusqlite::Result<()> {
+            tx.execute("DELETE FROM tasks WHERE path = ?1", [path])?;
+            tx.execute("DELETE FROM tags WHERE path = ?1", [path])?;
+            tx.execute("DELETE FROM text WHERE path = ?1", [path])?;
+            Ok(())
+        };
+        for path in known.keys().filter(|path| !present.contains(*path)) {
+            forget(path)?;
+        }
+        for ((path, (file_modified, size)), result) in changed.iter().zip(parsed) {
+            let (task, problem) = result?;
+            let path = key(path);
+            forget(&path)?;
+            tx.execute(
+                "INSERT INTO tasks (path, dir, modified, size, metadata, checklist, problem) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![path, dir_key, file_modified, size, serde_json::to_string(&task.metadata)?, serde_json::to_string(&checklist::progress(&task.description))?, problem],
+            )?;
+            for tag in &task.metadata.tags {
+                tx.execute("INSERT INTO tags (path, tag) VALUES (?1, ?2)", params![path, tag])?;
+            }
+            tx.execute(
+                "INSERT INTO text (path, dir, title, description) VALUES (?1, ?2, ?3, ?4)",
+                params![path, dir_key, normalize::fold_case(&task.metadata.title), normalize::fold_case(&task.description)],
+            )?;
+        }
+        let racy = dir_metadata.modified().ok()
+            .and_then(|time| SystemTime::now().duration_since(time).ok())
+            .is_none_or(|age| age < RACY);
+        if racy {
+            tx.execute("DELETE FROM dirs WHERE dir = ?1", [&dir_key])?;
+        } else {
+            tx.execute("INSERT OR REPLACE INTO dirs (dir, modified) VALUES (?1, ?2)", params![dir_key, dir_modified])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::task::Frontmatter;
+
+        fn titles(conn: &Connection, dir: &Path) -> Vec<String> {
+            let mut statement = conn.prepare("SELECT metadata FROM tasks WHERE dir = ?1 ORDER BY path").unwrap();
+            statement.query_map([key(dir)], |row| row.get::<_, String>(0)).unwrap()
+                .map(|metadata| serde_json::from_str::<crate::task::TaskMetadata>(&metadata.unwrap()).unwrap().title)
+                .collect()
+        }
+
+        #[test]
+        fn files_edited_in_place_are_read_again() {
+            let dir = std::env::temp_dir().join(format!("td-db-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join(format!("a.{}", TASK_EXTENSION));
+            let mut task = Task::new("before");
+            fs::write(&path, task.to_string_as(Frontmatter::Yaml).unwrap()).unwrap();
+            // Old enough for the directory to be marked as up to date.
+            let past = SystemTime::now() - Duration::from_secs(60);
+            fs::File::open(&dir).unwrap().set_modified(past).unwrap();
+
+            let mut conn = connect(Path::new(":memory:")).unwrap();
+            sync(&mut conn, &dir).unwrap();
+            assert_eq!(titles(&conn, &dir), ["before"]);
+
+            task.metadata.title = "after the edit".to_string();
+            fs::write(&path, task.to_string_as(Frontmatter::Yaml).unwrap()).unwrap();
+            // Rewritten in place, the directory did not change.
+            assert_eq!(fs::metadata(&dir).unwrap().modified().unwrap(), past);
+            sync(&mut conn, &dir).unwrap();
+            assert_eq!(titles(&conn, &dir), ["after the edit"]);
+
+            fs::remove_file(&path).unwrap();
+            sync(&mut conn, &dir).unwrap();
+            assert!(titles(&conn, &dir).is_empty());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
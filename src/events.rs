@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::Write, path::{Path, PathBuf}, process::{Command, Stdio}, sync::atomic::{AtomicBool, Ordering}, thread, time::{Duration, Instant}};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::config::{self, WebhookConfig};
+use crate::crypt;
+use crate::journal;
+use crate::project::{self, save_task};
+use crate::task::{Task, TaskMetadata, TaskStatus};
+
+/// The directory in the data directory holding the scripts run after changes,
+/// named after the event like `on-add`.
+pub const HOOKS_DIR: &str = "hooks";
+
+/// What a failing hook script does to the change it was run for, the
+/// `hook_failures` config key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailures {
+    /// The change is kept and the failure printed as a warning
+    #[default]
+    Warn,
+    /// The change is undone and the command fails
+    Abort,
+}
+
+/// The kinds of changes to a task that are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    StatusChanged,
+    Deleted,
+}
+
+/// What is reported about a change, also the JSON body of a webhook.
+#[derive(Debug, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    pub project: String,
+    pub task_id: Uuid,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_status: Option<TaskStatus>,
+    pub new_status: TaskStatus,
+    pub timestamp: DateTime<Utc>,
+    /// The task as it is after the change, given to hook scripts
+    #[serde(skip)]
+    pub task: TaskMetadata,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, task_path: &Path, meta: &TaskMetadata, old_status: Option<TaskStatus>) -> Self {
+        Event {
+            event: kind,
+            project: project::project_name(task_path.parent().unwrap_or(task_path)),
+            task_id: meta.id,
+            title: meta.title.clone(),
+            old_status,
+            new_status: meta.status,
+            timestamp: Utc::now(),
+            task: meta.clone(),
+            path: task_path.to_path_buf(),
+        }
+    }
+}
+
+/// Set by `--no-webhook` to keep this invocation quiet.
+static WEBHOOKS_OFF: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--no-hooks` to run no hook scripts in this invocation.
+static HOOKS_OFF: AtomicBool = AtomicBool::new(false);
+
+pub fn disable_webhooks(off: bool) {
+    WEBHOOKS_OFF.store(off, Ordering::Relaxed);
+}
+
+pub fn disable_hooks(off: bool) {
+    HOOKS_OFF.store(off, Ordering::Relaxed);
+}
+
+/// Reports a change once it is on disk, to the webhook and the hook
+/// scripts. Failing to deliver it only prints a warning, the change itself
+/// has already succeeded, unless `hook_failures = "abort"` makes a failing
+/// script undo it and fail the command.
+pub fn emit(event: Event) -> Result<()> {
+    send_webhook(&event);
+    for hook in hooks(&event) {
+        let Err(e) = run_hook(&hook, &event) else { continue };
+        let name = hook.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if config::get().hook_failures == HookFailures::Warn {
+            eprintln!("warning: the {} hook failed: {}", name, e);
+            continue;
+        }
+        undo(&event).map_err(|undo_error| anyhow!("The {} hook failed: {}, and undoing the change failed too: {}", name, e, undo_error))?;
+        return Err(anyhow!("The {} hook failed, the change was undone: {}", name, e));
+    }
+    Ok(())
+}
+
+fn send_webhook(event: &Event) {
+    let settings = &config::get().webhooks;
+    let Some(url) = &settings.url else {
+        return;
+    };
+    if WEBHOOKS_OFF.load(Ordering::Relaxed) || !(settings.events.is_empty() || settings.events.contains(&event.event)) {
+        return;
+    }
+    if let Err(e) = post(settings, url, event) {
+        eprintln!("warning: the webhook {} failed: {}", url, e);
+    }
+}
+
+/// The executable scripts in the hooks directory that want to hear about
+/// the event: `on-add` for new tasks, `on-status-change` for any change of
+/// status and `on-done` when that change finished the task.
+fn hooks(event: &Event) -> Vec<PathBuf> {
+    if HOOKS_OFF.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    let names: &[&str] = match event.event {
+        EventKind::Created => &["on-add"],
+        EventKind::StatusChanged if event.new_status == TaskStatus::DONE => &["on-status-change", "on-done"],
+        EventKind::StatusChanged => &["on-status-change"],
+        EventKind::Deleted => &[],
+    };
+    let Ok(dir) = project::td_home().map(|home| home.join(HOOKS_DIR)) else {
+        return Vec::new();
+    };
+    names.iter().map(|name| dir.join(name)).filter(|path| is_executable(path)).collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs a hook script with the task as JSON on stdin and the basics in
+/// `TD_*` variables, killing it once `hook_timeout_secs` have passed.
+fn run_hook(hook: &Path, event: &Event) -> Result<()> {
+    let status = |status: Option<TaskStatus>| status.map(|status| format!("{:?}", status).to_lowercase()).unwrap_or_default();
+    let mut child = Command::new(hook)
+        .env("TD_EVENT", serde_json::to_string(&event.event)?.trim_matches('"'))
+        .env("TD_TASK_ID", event.task_id.to_string())
+        .env("TD_TASK_TITLE", &event.title)
+        .env("TD_TASK_PATH", &event.path)
+        .env("TD_PROJECT", &event.project)
+        .env("TD_OLD_STATUS", status(event.old_status))
+        .env("TD_NEW_STATUS", status(Some(event.new_status)))
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("could not run {}: {}", hook.display(), e))?;
+    // Written from a thread so a script that never reads its input can not
+    // block td before the timeout is checked; it may also exit without
+    // reading, which is fine.
+    let input = serde_json::to_string(&event.task)?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+
+    let timeout = Duration::from_secs(config::get().hook_timeout_secs);
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("it did not finish within {}s and was stopped", timeout.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(anyhow!("it exited with {}", code)),
+        None => Err(anyhow!("it was killed by a signal")),
+    }
+}
+
+/// Takes back the change an event reported: a new task is deleted again
+/// and a changed status is set back.
+fn undo(event: &Event) -> Result<()> {
+    match (event.event, event.old_status) {
+        (EventKind::Created, _) => {
+            fs::remove_file(&event.path)?;
+            journal::record(&event.path, event.task_id, &event.title, "deleted, a hook failed");
+        }
+        (EventKind::StatusChanged, Some(old_status)) => project::locked(&event.path, || {
+            let mut task = Task::from_str(&crypt::read(&event.path)?)?;
+            task.metadata.status = old_status;
+            task.metadata.updated_at = Some(Utc::now());
+            save_task(&event.path, &task)?;
+            Ok(())
+        })?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// POSTs `body` as JSON to `url`, returning the HTTP status.
+pub fn post<T: Serialize>(settings: &WebhookConfig, url: &str, body: &T) -> Result<u16> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(settings.timeout_secs)))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.post(url).content_type("application/json");
+    if let Some(token) = settings.token() {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    let status = request.send(&serde_json::to_string(body)?)?.status().as_u16();
+    if status >= 400 {
+        return Err(anyhow!("the receiver answered with HTTP {}", status));
+    }
+    Ok(status)
+}
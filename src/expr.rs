@@ -0,0 +1,292 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use anyhow::{Result, anyhow};
+
+use crate::dates;
+use crate::normalize;
+use crate::tags;
+use crate::task::{Priority, TaskMetadata, TaskStatus};
+
+/// The keys a term can filter on, listed in error messages.
+const KEYS: &[&str] = &["status", "tag", "priority", "due", "created", "scheduled", "branch"];
+
+/// One whitespace separated part of a filter expression like `status:todo`,
+/// `due<7d` or `!tag:wontfix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub negated: bool,
+    pub criterion: Criterion,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Criterion {
+    Status(TaskStatus),
+    Tag(String),
+    Branch(String),
+    /// Compares the priority, a task without one ranks below `low`
+    Priority(Comparison, Option<Priority>),
+    /// Compares the day of a date, `None` matching tasks without it
+    Date(DateField, Comparison, Option<NaiveDate>),
+    /// A bare word, looked for in the title and the description
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Created,
+    Scheduled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn holds<T: Ord>(self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl Term {
+    /// Whether the task satisfies the term. Text is only looked for in the
+    /// description when it is given.
+    pub fn matches(&self, meta: &TaskMetadata, description: Option<&str>, case_sensitive: bool) -> bool {
+        let key = |text: &str| normalize::key(text, case_sensitive);
+        let holds = match &self.criterion {
+            Criterion::Status(status) => meta.status == *status,
+            Criterion::Tag(tag) => meta.tags.iter().any(|t| tags::matches(t, tag, case_sensitive, false)),
+            Criterion::Branch(branch) => meta.branch.as_ref() == Some(branch),
+            Criterion::Priority(comparison, priority) => comparison.holds(meta.priority, *priority),
+            Criterion::Date(field, comparison, day) => {
+                let date = match field {
+                    DateField::Due => meta.due,
+                    DateField::Created => Some(meta.created_at),
+                    DateField::Scheduled => meta.scheduled,
+                };
+                match (date.map(dates::day_of), day) {
+                    (Some(date), Some(day)) => comparison.holds(date, *day),
+                    (date, None) => date.is_none(),
+                    (None, Some(_)) => false,
+                }
+            }
+            Criterion::Text(text) => {
+                let needle = key(text);
+                key(&meta.title).contains(&needle) || description.is_some_and(|d| key(d).contains(&needle))
+            }
+        };
+        holds != self.negated
+    }
+}
+
+/// A word of the expression and the column it starts at.
+struct Token {
+    text: String,
+    quoted: bool,
+    start: usize,
+    len: usize,
+}
+
+/// Parses a filter expression into the terms that all have to hold.
+pub fn parse(input: &str) -> Result<Vec<Term>> {
+    tokens(input)?
+        .iter()
+        .map(|token| term(token).map_err(|message| anyhow!("{}\n    {}\n    {}{}", message, input, " ".repeat(token.start), "^".repeat(token.len.max(1)))))
+        .collect()
+}
+
+fn tokens(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut text = String::new();
+        let mut quoted = false;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            if chars[i] == '"' || chars[i] == '\'' {
+                let quote = chars[i];
+                let Some(end) = chars[i + 1..].iter().position(|c| *c == quote) else {
+                    return Err(anyhow!("Unclosed quote in the filter\n    {}\n    {}^", input, " ".repeat(i)));
+                };
+                text.extend(&chars[i + 1..i + 1 + end]);
+                quoted = true;
+                i += end + 2;
+            } else {
+                text.push(chars[i]);
+                i += 1;
+            }
+        }
+        tokens.push(Token { text, quoted, start, len: i - start });
+    }
+    Ok(tokens)
+}
+
+fn term(token: &Token) -> std::result::Result<Term, String> {
+    let (negated, text) = match token.text.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, token.text.as_str()),
+    };
+    if text.is_empty() {
+        return Err("Expected a term after '!'".to_string());
+    }
+    let operator = text.find([':', '<', '>', '=']).filter(|_| !token.quoted);
+    let Some(at) = operator else {
+        return Ok(Term { negated, criterion: Criterion::Text(text.to_string()) });
+    };
+    let key = &text[..at];
+    let rest = &text[at..];
+    let (comparison, value) = [("<=", Comparison::LessOrEqual), (">=", Comparison::GreaterOrEqual), ("<", Comparison::Less), (">", Comparison::Greater), (":", Comparison::Equal), ("=", Comparison::Equal)]
+        .iter()
+        .find_map(|(symbol, comparison)| rest.strip_prefix(symbol).map(|value| (*comparison, value)))
+        .expect("the term contains an operator at this position");
+    if value.is_empty() {
+        return Err(format!("Expected a value after '{}'", key));
+    }
+    let exact = || if comparison == Comparison::Equal { Ok(()) } else { Err(format!("'{}' can only be compared with ':'", key)) };
+
+    let criterion = match key {
+        "status" => {
+            exact()?;
+            let status = match value.to_lowercase().as_str() {
+                "todo" => TaskStatus::TODO,
+                "doing" => TaskStatus::DOING,
+                "done" => TaskStatus::DONE,
+                _ => return Err(format!("'{}' is not a status, expected todo, doing or done", value)),
+            };
+            Criterion::Status(status)
+        }
+        "tag" => {
+            exact()?;
+            Criterion::Tag(value.to_string())
+        }
+        "branch" => {
+            exact()?;
+            Criterion::Branch(value.to_string())
+        }
+        "priority" | "pri" => {
+            let priority = match value.to_lowercase().as_str() {
+                "none" => None,
+                "low" => Some(Priority::Low),
+                "medium" => Some(Priority::Medium),
+                "high" => Some(Priority::High),
+                _ => return Err(format!("'{}' is not a priority, expected low, medium, high or none", value)),
+            };
+            Criterion::Priority(comparison, priority)
+        }
+        "due" | "created" | "scheduled" => {
+            let field = match key {
+                "due" => DateField::Due,
+                "created" => DateField::Created,
+                _ => DateField::Scheduled,
+            };
+            let day = if value == "none" {
+                exact()?;
+                None
+            } else {
+                Some(day(value).map_err(|e| e.to_string())?)
+            };
+            Criterion::Date(field, comparison, day)
+        }
+        _ => return Err(format!("Unknown key '{}' in the filter, expected one of {}", key, KEYS.join(", "))),
+    };
+    Ok(Term { negated, criterion })
+}
+
+/// Reads a day like `today`, `2024-06-01` or `7d`, with a leading `-` for
+/// days in the past like `-7d`.
+fn day(value: &str) -> Result<NaiveDate> {
+    let today = dates::today();
+    match value.strip_prefix('-') {
+        Some(ago) => {
+            let ahead: DateTime<Utc> = dates::parse_when(ago)?;
+            let days = (dates::day_of(ahead) - today).num_days().max(0) as u64;
+            Ok(today - Days::new(days))
+        }
+        None => Ok(dates::day_of(dates::parse_when(value)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+
+    fn one(input: &str) -> Term {
+        let mut terms = parse(input).unwrap();
+        assert_eq!(terms.len(), 1, "{}", input);
+        terms.remove(0)
+    }
+
+    fn plain(criterion: Criterion) -> Term {
+        Term { negated: false, criterion }
+    }
+
+    /// The last two lines of the error: the input and the carets under it.
+    fn pointer(input: &str) -> String {
+        let message = parse(input).unwrap_err().to_string();
+        message.lines().skip(1).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn keys_take_a_value_after_a_colon() {
+        assert_eq!(one("status:doing"), plain(Criterion::Status(TaskStatus::DOING)));
+        assert_eq!(one("tag:bug"), plain(Criterion::Tag("bug".to_string())));
+        assert_eq!(one("branch=main"), plain(Criterion::Branch("main".to_string())));
+        assert_eq!(parse("status:todo tag:ui").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn dates_and_priorities_compare() {
+        let in_a_week = dates::day_of(dates::parse_when("7d").unwrap());
+        assert_eq!(one("due<7d"), plain(Criterion::Date(DateField::Due, Comparison::Less, Some(in_a_week))));
+        assert_eq!(one("due:none"), plain(Criterion::Date(DateField::Due, Comparison::Equal, None)));
+        assert_eq!(one("priority>=medium"), plain(Criterion::Priority(Comparison::GreaterOrEqual, Some(Priority::Medium))));
+        assert_eq!(one("pri<=low"), plain(Criterion::Priority(Comparison::LessOrEqual, Some(Priority::Low))));
+
+        let mut meta = Task::new("task").metadata;
+        meta.priority = Some(Priority::High);
+        assert!(one("priority>=medium").matches(&meta, None, false));
+        meta.priority = None;
+        assert!(!one("priority>=medium").matches(&meta, None, false));
+    }
+
+    #[test]
+    fn quoted_words_are_text() {
+        assert_eq!(one("\"status:todo\""), plain(Criterion::Text("status:todo".to_string())));
+        assert_eq!(one("'two words'"), plain(Criterion::Text("two words".to_string())));
+        assert!(parse("\"open").unwrap_err().to_string().contains("Unclosed quote"));
+    }
+
+    #[test]
+    fn a_bang_negates() {
+        let term = one("!tag:wontfix");
+        assert_eq!(term, Term { negated: true, criterion: Criterion::Tag("wontfix".to_string()) });
+        let mut meta = Task::new("task").metadata;
+        assert!(term.matches(&meta, None, false));
+        meta.tags.push("wontfix".to_string());
+        assert!(!term.matches(&meta, None, false));
+        assert!(parse("!").is_err());
+    }
+
+    #[test]
+    fn errors_point_at_the_term() {
+        assert_eq!(pointer("status:todo colour:red"), "    status:todo colour:red\n                ^^^^^^^^^^");
+        assert_eq!(pointer("tag:ui status<done"), "    tag:ui status<done\n           ^^^^^^^^^^^");
+        assert!(parse("status<done").unwrap_err().to_string().starts_with("'status' can only be compared with ':'"));
+    }
+}
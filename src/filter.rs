@@ -0,0 +1,132 @@
+use chrono::Utc;
+use clap::Args;
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::expr::{self, Criterion, Term};
+use crate::tags;
+use crate::project;
+use crate::task::{Task, TaskMetadata, TaskStatus};
+
+/// The criteria used to select tasks for listing. Every command that selects
+/// tasks goes through this so they all agree on what matches.
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// Only tasks with one of these statuses, or any status when empty
+    pub statuses: Vec<TaskStatus>,
+    /// Only tasks carrying all of these tags
+    pub tags: Vec<String>,
+    /// Only tasks carrying none of these tags
+    pub not_tags: Vec<String>,
+    /// Also match tasks that are snoozed
+    pub snoozed: bool,
+    /// Compare tags exactly instead of ignoring case
+    pub case_sensitive: bool,
+    /// Match only the tags given, not those below them like `area/backend`
+    /// for `area`
+    pub exact_tags: bool,
+    /// Only tasks filed under this git branch
+    pub branch: Option<String>,
+    /// Only tasks belonging to this epic
+    pub epic: Option<Uuid>,
+    /// Terms of a filter expression like `due<7d !tag:wontfix`, all of
+    /// which have to hold
+    pub terms: Vec<Term>,
+}
+
+impl Filter {
+    /// Whether the task matches, looking for the words of an expression in
+    /// the title only. Use `matches_task` when the description is at hand.
+    pub fn matches(&self, meta: &TaskMetadata) -> bool {
+        self.matches_with(meta, None)
+    }
+
+    pub fn matches_task(&self, task: &Task) -> bool {
+        self.matches_with(&task.metadata, Some(&task.description))
+    }
+
+    /// Whether an expression looks for words, which `matches` can only find
+    /// in titles.
+    pub fn needs_description(&self) -> bool {
+        self.terms.iter().any(|term| matches!(term.criterion, Criterion::Text(_)))
+    }
+
+    fn matches_with(&self, meta: &TaskMetadata, description: Option<&str>) -> bool {
+        if !self.terms.iter().all(|term| term.matches(meta, description, self.case_sensitive)) {
+            return false;
+        }
+        if !self.statuses.is_empty() && !self.statuses.contains(&meta.status) {
+            return false;
+        }
+        if !self.snoozed && meta.is_snoozed(Utc::now()) {
+            return false;
+        }
+        if self.branch.is_some() && meta.branch != self.branch {
+            return false;
+        }
+        if self.epic.is_some() && meta.epic != self.epic {
+            return false;
+        }
+        let has = |wanted: &String| meta.tags.iter().any(|tag| tags::matches(tag, wanted, self.case_sensitive, self.exact_tags));
+        self.tags.iter().all(has) && !self.not_tags.iter().any(has)
+    }
+}
+
+/// The command line flags that build a `Filter`, shared by every command
+/// that selects tasks.
+#[derive(Args, Debug, Default, Clone)]
+pub struct FilterArgs {
+    /// Only tasks with this status (can be repeated)
+    #[arg(long, short, value_enum)]
+    pub status: Vec<TaskStatus>,
+    /// Only tasks with this tag or one below it, like `area/backend` for
+    /// `area` (can be repeated). A trailing `/` matches only the tag itself
+    #[arg(long)]
+    pub tag: Vec<String>,
+    /// Leave out tasks with this tag (can be repeated)
+    #[arg(long)]
+    pub not_tag: Vec<String>,
+    /// Match --tag and --not-tag only exactly, not the tags below them
+    #[arg(long)]
+    pub exact_tag: bool,
+    /// Include snoozed tasks
+    #[arg(long)]
+    pub snoozed: bool,
+    /// Match tags and text exactly instead of ignoring case
+    #[arg(long)]
+    pub case_sensitive: bool,
+    /// Only tasks filed under this git branch, the current one without a name
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    pub branch: Option<String>,
+    /// Only tasks matching a filter expression like
+    /// 'status:todo priority>=medium due<7d !tag:wontfix "login"'
+    #[arg(long = "match", value_name = "EXPR")]
+    pub expression: Option<String>,
+}
+
+impl FilterArgs {
+    pub fn filter(&self) -> Result<Filter> {
+        Ok(Filter {
+            statuses: self.status.clone(),
+            tags: self.tag.clone(),
+            not_tags: self.not_tag.clone(),
+            snoozed: self.snoozed,
+            case_sensitive: self.case_sensitive,
+            exact_tags: self.exact_tag,
+            branch: match self.branch.as_deref() {
+                Some("") => project::current_branch(),
+                branch => branch.map(str::to_string),
+            },
+            epic: None,
+            terms: match &self.expression {
+                Some(expression) => expr::parse(expression)?,
+                None => Vec::new(),
+            },
+        })
+    }
+
+    /// Whether any filter flag was given.
+    pub fn is_set(&self) -> bool {
+        !self.status.is_empty() || !self.tag.is_empty() || !self.not_tag.is_empty() || self.snoozed || self.branch.is_some() || self.expression.is_some()
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::task::TaskMetadata;
+
+/// The `blocked_by` edges of a project, for walking the dependency graph.
+/// Ids without a task are treated as having no blockers of their own.
+pub struct Dependencies<'a> {
+    tasks: HashMap<Uuid, &'a TaskMetadata>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Finished,
+}
+
+impl<'a> Dependencies<'a> {
+    pub fn new(tasks: impl IntoIterator<Item = &'a TaskMetadata>) -> Self {
+        Dependencies { tasks: tasks.into_iter().map(|meta| (meta.id, meta)).collect() }
+    }
+
+    fn blockers(&self, id: Uuid) -> &[Uuid] {
+        self.tasks.get(&id).map(|meta| meta.blocked_by.as_slice()).unwrap_or_default()
+    }
+
+    /// The title of a task for messages, with its short id.
+    pub fn describe(&self, id: Uuid) -> String {
+        match self.tasks.get(&id) {
+            Some(meta) => format!("{} {}", meta.short_id(), meta.title),
+            None => format!("{} (missing)", &id.to_string()[..8]),
+        }
+    }
+
+    /// The shortest chain of blockers from `from` to `to`, both included,
+    /// when `from` waits for `to` directly or through other tasks.
+    pub fn chain(&self, from: Uuid, to: Uuid) -> Option<Vec<Uuid>> {
+        let mut came_from: HashMap<Uuid, Uuid> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut chain = vec![to];
+                while let Some(previous) = came_from.get(chain.last().expect("the chain is never empty")) {
+                    chain.push(*previous);
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            for next in self.blockers(current) {
+                if *next != from && !came_from.contains_key(next) {
+                    came_from.insert(*next, current);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Every cycle found along the edges, each listed from the task where it
+    /// was entered to the one that leads back to it.
+    pub fn cycles(&self) -> Vec<Vec<Uuid>> {
+        let mut ids: Vec<Uuid> = self.tasks.keys().copied().collect();
+        ids.sort();
+        let mut visits = HashMap::new();
+        let mut cycles = Vec::new();
+        for id in ids {
+            if !visits.contains_key(&id) {
+                self.visit(id, &mut visits, &mut Vec::new(), &mut cycles);
+            }
+        }
+        cycles
+    }
+
+    fn visit(&self, id: Uuid, visits: &mut HashMap<Uuid, Visit>, path: &mut Vec<Uuid>, cycles: &mut Vec<Vec<Uuid>>) {
+        visits.insert(id, Visit::InProgress);
+        path.push(id);
+        for next in self.blockers(id) {
+            match visits.get(next) {
+                Some(Visit::InProgress) => {
+                    let start = path.iter().position(|step| step == next).expect("tasks in progress are on the path");
+                    cycles.push(path[start..].to_vec());
+                }
+                Some(Visit::Finished) => {}
+                None if self.tasks.contains_key(next) => self.visit(*next, visits, path, cycles),
+                None => {}
+            }
+        }
+        path.pop();
+        visits.insert(id, Visit::Finished);
+    }
+
+    /// A cycle as lines for messages, closing with the task it started from.
+    pub fn describe_cycle(&self, cycle: &[Uuid]) -> String {
+        let mut lines: Vec<String> = cycle.iter().map(|id| self.describe(*id)).collect();
+        lines.push(self.describe(cycle[0]));
+        lines.join("\n    is blocked by ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+
+    /// Tasks named by the letters, each blocked by the tasks of the letters
+    /// in its entry.
+    fn fixture(edges: &[(&str, &str)]) -> (Vec<TaskMetadata>, impl Fn(&str) -> Uuid) {
+        let mut tasks: Vec<TaskMetadata> = edges.iter().map(|(name, _)| Task::new(name).metadata).collect();
+        let ids: HashMap<String, Uuid> = tasks.iter().map(|meta| (meta.title.clone(), meta.id)).collect();
+        for (meta, (_, blockers)) in tasks.iter_mut().zip(edges) {
+            meta.blocked_by = blockers.chars().map(|name| ids[&name.to_string()]).collect();
+        }
+        (tasks, move |name: &str| ids[name])
+    }
+
+    #[test]
+    fn an_acyclic_graph_has_chains_but_no_cycles() {
+        let (tasks, id) = fixture(&[("a", "b"), ("b", "c"), ("c", ""), ("d", "c")]);
+        let graph = Dependencies::new(&tasks);
+        assert!(graph.cycles().is_empty());
+        assert_eq!(graph.chain(id("a"), id("c")), Some(vec![id("a"), id("b"), id("c")]));
+        assert_eq!(graph.chain(id("c"), id("a")), None);
+        assert_eq!(graph.chain(id("d"), id("b")), None);
+    }
+
+    #[test]
+    fn cycles_are_found_with_their_path() {
+        let (tasks, id) = fixture(&[("a", "b"), ("b", "c"), ("c", "a"), ("d", "a"), ("e", "e")]);
+        let graph = Dependencies::new(&tasks);
+        let mut cycles = graph.cycles();
+        cycles.iter_mut().for_each(|cycle| cycle.sort());
+        cycles.sort();
+        let mut abc = vec![id("a"), id("b"), id("c")];
+        abc.sort();
+        let mut expected = vec![abc, vec![id("e")]];
+        expected.sort();
+        assert_eq!(cycles, expected);
+        // Making c wait for d would close a second loop through a.
+        assert_eq!(graph.chain(id("d"), id("c")), Some(vec![id("d"), id("a"), id("b"), id("c")]));
+        let describe = |name| graph.describe(id(name));
+        assert_eq!(
+            graph.describe_cycle(&[id("a"), id("b"), id("c")]),
+            format!("{}\n    is blocked by {}\n    is blocked by {}\n    is blocked by {}", describe("a"), describe("b"), describe("c"), describe("a")),
+        );
+    }
+
+    #[test]
+    fn missing_blockers_are_leaves() {
+        let (mut tasks, id) = fixture(&[("a", "b"), ("b", "")]);
+        let orphan = Uuid::new_v4();
+        tasks[1].blocked_by.push(orphan);
+        let graph = Dependencies::new(&tasks);
+        assert!(graph.cycles().is_empty());
+        assert_eq!(graph.chain(id("a"), orphan), Some(vec![id("a"), id("b"), orphan]));
+        assert_eq!(graph.chain(orphan, id("a")), None);
+        assert!(graph.describe(orphan).ends_with("(missing)"));
+    }
+}
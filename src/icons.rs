@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::{io::IsTerminal, sync::OnceLock};
+
+use crate::config;
+use crate::task::TaskStatus;
+
+/// Which glyphs the output is drawn with, the `icons` config key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconMode {
+    Emoji,
+    Unicode,
+    Ascii,
+}
+
+/// Every glyph td prints, so renderers can not drift apart between modes.
+#[derive(Debug)]
+pub struct Icons {
+    pub todo: &'static str,
+    pub doing: &'static str,
+    pub done: &'static str,
+    /// Marks tasks that wait for an open blocker
+    pub blocked: &'static str,
+    /// The filled and empty cells of a progress bar
+    pub bar_full: &'static str,
+    pub bar_empty: &'static str,
+    /// Repeated to draw a horizontal rule
+    pub rule: &'static str,
+    /// Ends text that was cut to fit
+    pub ellipsis: &'static str,
+}
+
+const ASCII: Icons = Icons {
+    todo: "[ ]",
+    doing: "[>]",
+    done: "[x]",
+    blocked: "(blocked)",
+    bar_full: "#",
+    bar_empty: "-",
+    rule: "-",
+    ellipsis: "...",
+};
+
+const UNICODE: Icons = Icons {
+    todo: "○",
+    doing: "◐",
+    done: "●",
+    blocked: "⊘ blocked",
+    bar_full: "█",
+    bar_empty: "░",
+    rule: "─",
+    ellipsis: "…",
+};
+
+const EMOJI: Icons = Icons {
+    todo: "⬜",
+    doing: "🔄",
+    done: "✅",
+    blocked: "⛔",
+    bar_full: "🟩",
+    bar_empty: "⬜",
+    rule: "─",
+    ellipsis: "…",
+};
+
+static MODE: OnceLock<IconMode> = OnceLock::new();
+
+/// Picks the glyphs for this invocation: ASCII with `--ascii`, otherwise the
+/// configured mode, falling back to Unicode on a terminal and ASCII when
+/// stdout is piped.
+pub fn init(ascii: bool) {
+    let mode = match config::get().icons {
+        _ if ascii => IconMode::Ascii,
+        Some(mode) => mode,
+        None if std::io::stdout().is_terminal() => IconMode::Unicode,
+        None => IconMode::Ascii,
+    };
+    let _ = MODE.set(mode);
+}
+
+pub fn get() -> &'static Icons {
+    match MODE.get() {
+        Some(IconMode::Emoji) => &EMOJI,
+        Some(IconMode::Unicode) => &UNICODE,
+        Some(IconMode::Ascii) | None => &ASCII,
+    }
+}
+
+impl Icons {
+    pub fn status(&self, status: TaskStatus) -> &'static str {
+        match status {
+            TaskStatus::TODO => self.todo,
+            TaskStatus::DOING => self.doing,
+            TaskStatus::DONE => self.done,
+        }
+    }
+
+    /// A bar of `cells` glyphs showing how much of `total` is done.
+    pub fn bar(&self, done: usize, total: usize, cells: usize) -> String {
+        let full = if total == 0 { 0 } else { (done * cells).div_ceil(total).min(cells) };
+        format!("{}{}", self.bar_full.repeat(full), self.bar_empty.repeat(cells - full))
+    }
+
+    pub fn rule(&self, width: usize) -> String {
+        self.rule.repeat(width)
+    }
+}
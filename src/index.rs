@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, sync::Mutex, time::UNIX_EPOCH};
+use anyhow::{Result, anyhow};
+
+use crate::checklist::{self, Progress};
+use crate::crypt;
+use crate::db;
+use crate::parallel;
+use crate::project::TASK_EXTENSION;
+use crate::task::{Task, TaskMetadata};
+
+/// The cache of parsed frontmatter kept in every project directory.
+pub const INDEX_FILE: &str = ".index.json";
+
+/// The task files left out of this invocation and why, each kept once
+/// however often its directory is read.
+static SKIPPED: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+/// What listing commands need to know about a task, without its description.
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub metadata: TaskMetadata,
+    pub checklist: Progress,
+    /// Why the task could not be read as it is, like an unknown status
+    pub problem: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    entries: BTreeMap<String, CachedEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEntry {
+    modified: u64,
+    size: u64,
+    metadata: TaskMetadata,
+    checklist: Progress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    problem: Option<String>,
+}
+
+/// Lists the tasks of a project like `load_entries`, leaving out those that
+/// could not be read for `report_skipped` to warn about.
+pub fn load_index(project_dir: &Path) -> Result<Vec<IndexEntry>> {
+    let (entries, unreadable): (Vec<IndexEntry>, Vec<IndexEntry>) = load_entries(project_dir)?
+        .into_iter()
+        .partition(|entry| entry.problem.is_none());
+    for entry in unreadable {
+        skip(entry.path, entry.problem.unwrap_or_default());
+    }
+    Ok(entries)
+}
+
+/// Leaves a task file out of this invocation, see `report_skipped`.
+pub fn skip(path: PathBuf, problem: String) {
+    SKIPPED.lock().unwrap_or_else(|e| e.into_inner()).insert(path, problem);
+}
+
+/// Warns once about the task files that were left out, naming the first.
+/// `td ls` lists every one of them with its problem.
+pub fn report_skipped() {
+    if let Some(warning) = skipped_warning(&SKIPPED.lock().unwrap_or_else(|e| e.into_inner())) {
+        eprintln!("{}", warning);
+    }
+}
+
+fn skipped_warning(skipped: &BTreeMap<PathBuf, String>) -> Option<String> {
+    let (path, problem) = skipped.iter().next()?;
+    Some(match skipped.len() {
+        1 => format!("warning: skipped {}: {}", path.display(), problem),
+        count => format!("warning: skipped {} task files that can not be read as they are, like {}: {}; `td ls` lists them all", count, path.display(), problem),
+    })
+}
+
+/// Lists the tasks of a project, only parsing files that changed since the
+/// index was last written, or from the SQLite index when there is one. The
+/// index is rewritten when anything changed, but failing to write it never
+/// fails the listing. Tasks with an unknown status
+/// are included, read as TODO with the problem set.
+pub fn load_entries(project_dir: &Path) -> Result<Vec<IndexEntry>> {
+    if let Some(entries) = db::entries(project_dir) {
+        return Ok(entries);
+    }
+    let index_path = project_dir.join(INDEX_FILE);
+    // The index would hold the titles in plain text, so projects encrypting
+    // them read every file instead.
+    let private = crypt::hides_titles(project_dir);
+    if private && index_path.exists() {
+        fs::remove_file(&index_path)?;
+    }
+    let cached: Index = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(project_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    // Looking at and parsing the files is what takes long on a slow disk, so
+    // it is spread over threads. Sorted so the first error does not depend
+    // on the order the directory lists its files in.
+    paths.sort();
+    let mut index = Index::default();
+    let mut changed = false;
+    for loaded in parallel::map(&paths, |path| load_entry(path, &cached)) {
+        let (file_name, entry, parsed) = loaded?;
+        changed |= parsed;
+        index.entries.insert(file_name, entry);
+    }
+    changed |= index.entries.len() != cached.entries.len();
+
+    if changed && !private && let Err(e) = write_index(&index_path, &index) {
+        eprintln!("warning: could not update the index: {}", e);
+    }
+
+    Ok(index.entries
+        .into_iter()
+        .map(|(file_name, cached)| IndexEntry {
+            path: project_dir.join(file_name),
+            metadata: cached.metadata,
+            checklist: cached.checklist,
+            problem: cached.problem,
+        })
+        .collect())
+}
+
+/// The entry of one task file, from the cached index when the file did not
+/// change since. Also tells whether the file had to be parsed.
+fn load_entry(path: &Path, cached: &Index) -> Result<(String, CachedEntry, bool)> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let file_meta = fs::metadata(path)?;
+    let modified = file_meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+    let size = file_meta.len();
+
+    if let Some(entry) = cached.entries.get(&file_name).filter(|cached| cached.modified == modified && cached.size == size) {
+        return Ok((file_name, entry.clone(), false));
+    }
+    let content = crypt::read_metadata(path)?;
+    let (task, problem) = Task::from_str_lenient(&content)
+        .map_err(|e| anyhow!("Could not parse {}: {}", path.display(), e))?;
+    let entry = CachedEntry {
+        modified,
+        size,
+        checklist: checklist::progress(&task.description),
+        metadata: task.metadata,
+        problem,
+    };
+    Ok((file_name, entry, true))
+}
+
+fn write_index(index_path: &Path, index: &Index) -> Result<()> {
+    let temp_path = index_path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string(index)?)?;
+    fs::rename(&temp_path, index_path)?;
+    Ok(())
+}
+
+/// Whether the index of a directory names task files that no longer exist,
+/// or can not be read at all. Listing rewrites it, so it only goes stale in
+/// directories nothing lists anymore.
+pub fn is_stale(project_dir: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(project_dir.join(INDEX_FILE)) else {
+        return false;
+    };
+    match serde_json::from_str::<Index>(&content) {
+        Ok(index) => index.entries.keys().any(|file_name| !project_dir.join(file_name).exists()),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_files_are_summed_up_in_one_warning() {
+        let mut skipped = BTreeMap::new();
+        assert_eq!(skipped_warning(&skipped), None);
+        skipped.insert(PathBuf::from("b.td"), "Unknown status 'later'".to_string());
+        assert_eq!(skipped_warning(&skipped).unwrap(), "warning: skipped b.td: Unknown status 'later'");
+        // Reading a directory again does not count its files twice.
+        skipped.insert(PathBuf::from("b.td"), "Unknown status 'later'".to_string());
+        skipped.insert(PathBuf::from("a.td"), "Unknown status 'soon'".to_string());
+        assert_eq!(
+            skipped_warning(&skipped).unwrap(),
+            "warning: skipped 2 task files that can not be read as they are, like a.td: Unknown status 'soon'; `td ls` lists them all",
+        );
+    }
+}
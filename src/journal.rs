@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}};
+use uuid::Uuid;
+use anyhow::Result;
+
+use crate::crypt::{self, Scope};
+use crate::project;
+use crate::task::Task;
+
+/// The file in a project directory every change to its tasks is appended to.
+pub const JOURNAL_FILE: &str = "journal.log";
+
+/// Once the journal grows past this, it is moved to `journal.log.1` and a
+/// new one is started, so at most twice this is kept.
+const MAX_BYTES: u64 = 512 * 1024;
+
+/// One line of the journal.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: DateTime<Utc>,
+    /// The arguments td was run with
+    pub command: String,
+    pub task_id: Uuid,
+    pub title: String,
+    /// What changed, like `status todo->done, tags +bug`
+    pub changes: String,
+}
+
+/// The journal of the project a task directory belongs to. Sub-scopes share
+/// the one of their repository.
+pub fn journal_path(dir: &Path) -> Result<PathBuf> {
+    Ok(project::project_root(dir)?.join(JOURNAL_FILE))
+}
+
+/// Appends a change of the task stored in `task_path`. The change itself has
+/// already been written, so failing to record it only prints a warning.
+pub fn record(task_path: &Path, id: Uuid, title: &str, changes: &str) {
+    if let Err(e) = append(task_path, id, title, changes) {
+        eprintln!("warning: could not write to the journal: {:#}", e);
+    }
+}
+
+/// Records the difference between two versions of a task, nothing when they
+/// are the same. Projects that encrypt their tasks only get the names of the
+/// fields that changed.
+pub fn record_change(task_path: &Path, old: Option<&Task>, new: &Task) {
+    let scope = crypt::settings(task_path.parent().unwrap_or(task_path)).ok().flatten().map(|settings| settings.scope);
+    let changes = match old {
+        Some(old) => diff(old, new, scope),
+        None => "created".to_string(),
+    };
+    if !changes.is_empty() {
+        record(task_path, new.metadata.id, &new.metadata.title, &changes);
+    }
+}
+
+fn append(task_path: &Path, id: Uuid, title: &str, changes: &str) -> Result<()> {
+    let dir = task_path.parent().unwrap_or(task_path);
+    let path = journal_path(dir)?;
+    let hidden = crypt::hides_titles(dir);
+    let title = if hidden { "" } else { title };
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_BYTES) {
+        fs::rename(&path, rotated(&path))?;
+    }
+    let entry = Entry {
+        timestamp: Utc::now(),
+        command: command_line(hidden),
+        task_id: id,
+        title: title.to_string(),
+        changes: changes.to_string(),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+fn rotated(path: &Path) -> PathBuf {
+    path.with_file_name(format!("{}.1", JOURNAL_FILE))
+}
+
+/// The entries of a journal from oldest to newest, including the rotated
+/// part. Lines that can not be read are skipped.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for path in [rotated(path), path.to_path_buf()] {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        entries.extend(content.lines().filter_map(|line| serde_json::from_str::<Entry>(line).ok()));
+    }
+    Ok(entries)
+}
+
+/// The arguments of this invocation, quoting those with spaces. Only the
+/// command is kept when they may hold an encrypted title.
+fn command_line(hidden: bool) -> String {
+    if hidden {
+        let command = std::env::args().skip(1).find(|arg| !arg.starts_with('-')).unwrap_or_default();
+        return format!("td {}", command);
+    }
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .map(|arg| if arg.is_empty() || arg.contains(char::is_whitespace) { format!("'{}'", arg) } else { arg })
+        .collect();
+    format!("td {}", args.join(" "))
+}
+
+/// A short summary of what differs between two versions of a task, leaving
+/// out the values `scope` encrypts.
+fn diff(old: &Task, new: &Task, scope: Option<Scope>) -> String {
+    let (a, b) = (&old.metadata, &new.metadata);
+    let hidden = |name: &str| scope == Some(Scope::All) || (name == "title" && scope >= Some(Scope::Title));
+    let mut changes = Vec::new();
+    let mut field = |name: &str, from: Option<String>, to: Option<String>| {
+        if from != to && hidden(name) {
+            changes.push(format!("{} edited", name));
+        } else if from != to {
+            let show = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+            changes.push(format!("{} {}->{}", name, show(from), show(to)));
+        }
+    };
+    let day = |time: Option<DateTime<Utc>>| time.map(|time| time.format("%Y-%m-%d").to_string());
+    fn lower(value: Option<impl std::fmt::Debug>) -> Option<String> {
+        value.map(|value| format!("{:?}", value).to_lowercase())
+    }
+    field("title", Some(format!("'{}'", a.title)), Some(format!("'{}'", b.title)));
+    field("status", lower(Some(a.status)), lower(Some(b.status)));
+    field("priority", lower(a.priority), lower(b.priority));
+    field("due", day(a.due), day(b.due));
+    field("scheduled", day(a.scheduled), day(b.scheduled));
+    field("snoozed", day(a.snoozed_until), day(b.snoozed_until));
+    field("parent", a.parent.map(short), b.parent.map(short));
+    field("branch", a.branch.clone(), b.branch.clone());
+    field("epic", a.epic.map(short), b.epic.map(short));
+    field("milestone", a.milestone.clone(), b.milestone.clone());
+    for key in a.extra.keys().chain(b.extra.keys().filter(|key| !a.extra.contains_key(*key))) {
+        let show = |value: Option<&serde_yaml::Value>| value.and_then(|value| serde_yaml::to_string(value).ok()).map(|text| text.trim_end().to_string());
+        let name = serde_yaml::to_string(key).unwrap_or_default();
+        field(&format!("extra.{}", name.trim_end()), show(a.extra.get(key)), show(b.extra.get(key)));
+    }
+    let fields = changes.len();
+    set_changes(&mut changes, "tags", &a.tags, &b.tags);
+    set_changes(&mut changes, "blocked_by", &a.blocked_by.iter().map(|id| short(*id)).collect::<Vec<_>>(), &b.blocked_by.iter().map(|id| short(*id)).collect::<Vec<_>>());
+    set_changes(&mut changes, "related", &a.related.iter().map(|id| short(*id)).collect::<Vec<_>>(), &b.related.iter().map(|id| short(*id)).collect::<Vec<_>>());
+    set_changes(&mut changes, "links", &a.links, &b.links);
+    set_changes(&mut changes, "commits", &a.commits.iter().map(|sha| abbreviate(sha)).collect::<Vec<_>>(), &b.commits.iter().map(|sha| abbreviate(sha)).collect::<Vec<_>>());
+    if hidden("lists") {
+        for change in &mut changes[fields..] {
+            let name = change.split(' ').next().unwrap_or_default().to_string();
+            *change = format!("{} edited", name);
+        }
+    }
+    if old.description != new.description {
+        changes.push("description edited".to_string());
+    }
+    changes.join(", ")
+}
+
+fn short(id: Uuid) -> String {
+    id.to_string()[..8].to_string()
+}
+
+/// The first seven characters of a commit, counted as characters because
+/// a hand edited file may hold anything there.
+fn abbreviate(sha: &str) -> String {
+    sha.chars().take(7).collect()
+}
+
+/// Lists what was added to and removed from a list as `name +x -y`.
+fn set_changes<T: PartialEq + Display>(changes: &mut Vec<String>, name: &str, old: &[T], new: &[T]) {
+    let added = new.iter().filter(|item| !old.contains(item)).map(|item| format!("+{}", item));
+    let removed = old.iter().filter(|item| !new.contains(item)).map(|item| format!("-{}", item));
+    let parts: Vec<String> = added.chain(removed).collect();
+    if !parts.is_empty() {
+        changes.push(format!("{} {}", name, parts.join(" ")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_abbreviates_commits_by_character() {
+        let old = Task::new("a");
+        let mut new = old.clone();
+        new.metadata.commits = vec!["ééééééééé".to_string(), "0123456789abcdef".to_string()];
+        assert_eq!(diff(&old, &new, None), "commits +ééééééé +0123456");
+    }
+
+    #[test]
+    fn diff_hides_encrypted_titles() {
+        let old = Task::new("a");
+        let mut new = old.clone();
+        new.metadata.title = "b".to_string();
+        assert_eq!(diff(&old, &new, None), "title 'a'->'b'");
+        assert_eq!(diff(&old, &new, Some(Scope::Title)), "title edited");
+    }
+}
@@ -0,0 +1,31 @@
+pub mod archive;
+pub mod assign;
+pub mod backup;
+pub mod checklist;
+pub mod color;
+pub mod commands;
+pub mod config;
+pub mod context;
+pub mod crypt;
+pub mod dates;
+pub mod db;
+pub mod events;
+pub mod expr;
+pub mod filter;
+pub mod graph;
+pub mod icons;
+pub mod index;
+pub mod journal;
+pub mod markdown;
+pub mod milestone;
+pub mod normalize;
+pub mod output;
+pub mod parallel;
+pub mod project;
+pub mod query;
+pub mod sort;
+pub mod tags;
+pub mod task;
+pub mod template;
+pub mod urgency;
+pub mod width;
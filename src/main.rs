@@ -1,87 +1,47 @@
-use chrono::{DateTime, Utc};
-use clap::{Args, Command, Parser, Subcommand};
-use git2::Repository;
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-use std::{env, fs, io::{self, Read, Write}, path::PathBuf, process::Stdio};
-use anyhow::{Result, anyhow};
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum TaskStatus {
-    TODO,
-    DOING,
-    DONE
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TaskMetadata {
-    pub title: String,
-    pub status: TaskStatus,
-    pub created_at: DateTime<Utc>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub updated_at: Option<DateTime<Utc>>,
-    pub id: Uuid,
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub tags: Vec<String>,
-}
-
-#[derive(Debug)]
-struct Task {
-    pub metadata: TaskMetadata,
-    pub description: String
-}
-
-impl  Task {
-    pub fn from_str(content: &str) -> Result<Self> {
-        if !content.starts_with("---\n") {
-            return Err(anyhow!("The task file does not start with '---' followed by a new line"))
-        }
-        let end_of_frontmatter = content[4..]
-            .find("---\n")
-            .ok_or_else(|| anyhow::anyhow!("Missing closing '---'"))?;
-        let yaml_str = &content[4..4 + end_of_frontmatter];
-        let description = content[4 + end_of_frontmatter + 4..].to_string();
-        let metadata: TaskMetadata = serde_yaml::from_str(yaml_str)?;
-
-        Ok(Task {
-            metadata,
-            description,
-        })
-    }
-
-    pub fn to_string(&self) -> Result<String> {
-        let yaml_str = serde_yaml::to_string(&self.metadata)?;
-
-        // Combine the parts into the final file format.
-        Ok(format!(
-            "---\n{}---\n{}",
-            yaml_str, self.description
-        ))
-    }
-
-    pub fn new(args: &AddArgs) -> Self {
-        Task { 
-            metadata: TaskMetadata { 
-                title: args.title.clone(), 
-                status: TaskStatus::TODO, 
-                created_at: Utc::now(), 
-                id: Uuid::new_v4(), 
-                tags: args.tags.as_ref()
-                    .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
-                    .or(Some(Vec::new()))
-                    .expect("Tags will be an empty list if none was given"),
-                updated_at: None, 
-            } ,
-            description: args.desc.clone().or_else(|| Some(String::new())).expect("The description will be an empty string if none is given") }
-    }
-}
+use clap::{Parser, Subcommand};
+use anyhow::Result;
 
+use td::{archive, color, commands, config, context, dates, events, icons, index, output, project, query};
 
+use commands::{add::AddArgs, archive::ArchiveArgs, block::BlockArgs, changelog::ChangelogArgs, check::CheckArgs, clean::CleanArgs, context::ContextArgs, edit::EditArgs, encrypt::EncryptArgs, epic::EpicCommand, export::ExportArgs, history::HistoryArgs, hook::HookCommand, import::ImportArgs, init::InitArgs, link_url::LinkUrlArgs, ls::LsArgs, migrate::MigrateArgs, milestone::MilestoneCommand, mv::MvArgs, next::NextArgs, notify::NotifyArgs, open::OpenArgs, pick::PickArgs, project::ProjectCommand, prompt::PromptArgs, reindex::ReindexArgs, relate::RelateArgs, remind::RemindArgs, rename::RenameArgs, rm::RmArgs, search::SearchArgs, serve::ServeArgs, set::SetArgs, show::ShowArgs, snooze::SnoozeArgs, stale::StaleArgs, standup::StandupArgs, tag::TagCommand, tags::TagsArgs, webhook::WebhookCommand};
+use td::output::OutputArgs;
+use td::project::create_td_home;
+use td::task::TaskStatus;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Never color the output
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// Don't print confirmations after changing tasks, only report errors
+    #[arg(long, short, global = true)]
+    quiet: bool,
+    /// Work on the project with this alias or directory name instead of the current one
+    #[arg(long, global = true)]
+    project: Option<String>,
+    /// Find the repository as if td was started in this directory. Repeated
+    /// relative directories are joined, like `git -C`
+    #[arg(short = 'C', global = true, value_name = "PATH")]
+    dir: Vec<std::path::PathBuf>,
+    /// Don't send the configured webhook for the changes made by this command
+    #[arg(long, global = true)]
+    no_webhook: bool,
+    /// Don't run the scripts in `~/.td/hooks` for the changes made by this command
+    #[arg(long, global = true)]
+    no_hooks: bool,
+    /// Draw the output with ASCII characters only
+    #[arg(long, global = true)]
+    ascii: bool,
+    /// Show dates in UTC instead of the local timezone
+    #[arg(long, global = true)]
+    utc: bool,
+    /// List everything, ignoring the context activated with `td context`
+    #[arg(long, global = true)]
+    no_context: bool,
+    /// Don't archive old DONE tasks as configured by `auto_archive_after` this time
+    #[arg(long, global = true)]
+    no_auto_archive: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -91,163 +51,250 @@ enum Commands {
     /// Adds a new task to the current project
     Add(AddArgs),
     /// List tasks
-    Ls,
-    Edit
+    Ls(LsArgs),
+    /// Show all details of a task
+    Show(ShowArgs),
+    /// Edit the description of a task in $EDITOR, or change its dates
+    Edit(EditArgs),
+    /// Find tasks whose title or description contains some text
+    Search(SearchArgs),
+    /// List the tags in use with how many tasks carry them
+    Tags(TagsArgs),
+    /// Create, list and show epics, which group tasks under a larger piece of work
+    Epic {
+        #[command(subcommand)]
+        command: EpicCommand,
+    },
+    /// Add, list, show and rename milestones, target dates tasks are filed under
+    Milestone {
+        #[command(subcommand)]
+        command: MilestoneCommand,
+    },
+    /// Create tasks from files written elsewhere
+    Import(ImportArgs),
+    /// Add, remove or rename tags
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+    /// Change the title of a task
+    Rename(RenameArgs),
+    /// Delete a task
+    Rm(RmArgs),
+    /// Move DONE tasks out of the listings, `ls --archived` still shows them
+    Archive(ArchiveArgs),
+    /// Encrypt the task files of the project at rest, or decrypt them again
+    Encrypt(EncryptArgs),
+    /// Move a task to another sub-scope of the repository or to another project
+    Mv(MvArgs),
+    /// Print the path of a task's file
+    Path {
+        /// The id (or a unique prefix of it) of the task
+        id: String,
+        /// End the path with a NUL byte instead of a newline
+        #[arg(short = '0', long)]
+        print0: bool,
+    },
+    /// Make a task wait for other tasks to be done
+    Block(BlockArgs),
+    /// Link two related tasks, without either waiting for the other
+    Relate(RelateArgs),
+    /// Link a task to a URL, like its pull request or design doc
+    LinkUrl(LinkUrlArgs),
+    /// Open a link of a task in the browser
+    Open(OpenArgs),
+    /// Change several fields of a task at once, like `status=doing priority=high`
+    Set(SetArgs),
+    /// Mark a task as being worked on
+    Start {
+        /// The id (or a unique prefix of it) of the task
+        id: String,
+    },
+    /// Mark a task as done
+    Done {
+        /// The id (or a unique prefix of it) of the task
+        id: String,
+    },
+    /// Move a task back to todo
+    Todo {
+        /// The id (or a unique prefix of it) of the task
+        id: String,
+    },
+    /// Hide a task from listings until a later date
+    Snooze(SnoozeArgs),
+    /// Suggest the open task to work on next
+    Next(NextArgs),
+    /// Pick an open task at random, favoring urgent ones, when choosing is the hard part
+    Pick(PickArgs),
+    /// Summarize what was done, what is in progress and what is stuck
+    Standup(StandupArgs),
+    /// Show upcoming deadlines and scheduled starts
+    Due,
+    /// List open tasks nobody changed for a while, exiting with 1 when there are any
+    Stale(StaleArgs),
+    /// Raise desktop notifications for due and overdue tasks, e.g. from a timer
+    Notify(NotifyArgs),
+    /// Print a digest of overdue and soon due tasks for cron, silent when nothing is due
+    Remind(RemindArgs),
+    /// Show the tasks as a kanban board
+    Board,
+    /// Print the open task counts for a shell prompt, nothing outside a project
+    Prompt(PromptArgs),
+    /// Export the tasks as a document to share, like an HTML board
+    Export(ExportArgs),
+    /// Serve the board and a JSON API over HTTP on localhost
+    Serve(ServeArgs),
+    /// Show the recent changes to tasks from the project's journal
+    History(HistoryArgs),
+    /// Print a markdown changelog section of the tasks finished since a date or git tag
+    Changelog(ChangelogArgs),
+    /// Convert the task files of the current project, e.g. to TOML frontmatter
+    Migrate(MigrateArgs),
+    /// Rebuild the index of the current project, optionally renaming old task files
+    Reindex(ReindexArgs),
+    /// Set up the project of the current repository, or the global list with --global
+    Init(InitArgs),
+    /// Show which project, remote and config apply here and check the task files
+    Doctor,
+    /// Report and remove empty project directories, leftover temporary files and stale indexes
+    Clean(CleanArgs),
+    /// Look for dependency cycles, blockers that no longer exist and invalid tags
+    Check(CheckArgs),
+    /// Show the effective configuration and where each value is set
+    Config,
+    /// Summarize the current project: branch, work in progress and what needs attention
+    Status,
+    /// Show counts of tasks by status, priority and tag
+    Stats(OutputArgs),
+    /// List the projects that have tasks
+    Projects(OutputArgs),
+    /// Run the `ls` query saved under a name, like `td ls --query <name>`
+    Q {
+        /// The name of the query in `[queries]` of the config
+        name: String,
+        /// More `ls` flags, overriding those of the query
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// List the saved queries with the flags they stand for
+    Queries,
+    /// Activate a saved filter for every listing, or define one
+    Context(ContextArgs),
+    /// Manage projects
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommand,
+    },
+    /// Check the webhook from the config
+    Webhook {
+        #[command(subcommand)]
+        command: WebhookCommand,
+    },
+    /// Entry points for the git hooks installed by `td init --hooks`
+    #[command(name = "__hook", hide = true)]
+    Hook {
+        #[command(subcommand)]
+        command: HookCommand,
+    },
 }
 
-#[derive(Args, Debug)]
-struct AddArgs {
-    /// The title of the task
-    title: String,
-    /// A description of the task
-    #[arg(long, short)]
-    desc: Option<String>,
-    // Comma-seperated list of tags
-    #[arg(long, short)]
-    tags: Option<String>
-}
-
-fn main() {
-    let td_dir = create_td_home().unwrap();
-
+fn main() -> Result<()> {
     let cli = Cli::parse();
-
-    dbg!(&cli.command);
-    match &cli.command {
-        Some(Commands::Add(args)) => {
-            println!("add command");
-            add_task(args)
-        }
-        Some(Commands::Ls) => {
-            println!("ls command");
-            list_task().unwrap()
-        }
-        Some(Commands::Edit) => {
-            edit_task().unwrap()
-        }
-        /*Some(Commands::Ls { project }) => {
-            if project.is_some() && project.clone().unwrap().is_empty() {
-                fs::read_dir(td_dir_path).unwrap().for_each(|folder_content| {
-                     let file = folder_content.unwrap().file_name().into_string().unwrap();
-                     println!("{}", file)
-            });
-            }
-        }*/
-        None => {
-            println!("No command provided. Use --help for more information.");
-        }
+    // `td prompt` runs before every shell prompt and leaves nothing behind.
+    let prompt = matches!(cli.command, Some(Commands::Prompt(_)));
+    if !prompt {
+        create_td_home()?;
     }
-}
-
-fn add_task(args: &AddArgs) {
-    dbg!(args);
-    println!("add");
-    let mut project_dir = get_project_path().unwrap();
-    project_dir.push("test_file.td");
-    let mut task_file = std::fs::File::create(project_dir).unwrap();
-    let _ = task_file.write(Task::new(args).to_string().unwrap().as_bytes());
-}
-
-fn list_task() -> Result<()> {
-    println!("ls");
-    let project_dir = get_project_path().unwrap();
-    println!("project dir: {}", project_dir.to_str().unwrap());
-    for entry in fs::read_dir(project_dir)? {
-        let entry = entry?;
-        let name = entry.file_name();
-        println!("{}", name.to_str().ok_or(anyhow!("could not read file name"))?)
-    }
-    Ok(())
-}
-
-fn edit_task() -> Result<()> {
-    println!("edit");
-    let mut project_dir = get_project_path().unwrap();
-    project_dir.push("test_file.td");
-    //let mut task_file = std::fs::File::open(project_dir).unwrap();
-
-    let content = std::fs::read_to_string(&project_dir)?;
-    let mut task = Task::from_str(&content)?;
-
-    let mut temp_path = PathBuf::new();
-    temp_path.push(std::env::temp_dir());
-    temp_path.push(format!("td_{}_{}.md", task.metadata.id, Utc::now().timestamp()));
-    let mut temp_file = std::fs::File::create(&temp_path)?;
-    let _ = temp_file.write_all(task.description.as_bytes());
-
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
-    let mut command = std::process::Command::new(&editor);
-    command.arg(&temp_path);
-    command
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-
-    match command.spawn() {
-        Ok(mut child) => {
-            let status = child.wait().expect("child process should complete");
-            if !status.success() {eprintln!("Editor exited with non-zero status: {:?}", status)}
-            let mut edited_content = String::new();
-            let mut temp_file = std::fs::File::open(&temp_path)?;
-            let _ = temp_file.read_to_string(&mut edited_content);
-            println!("debug tmp: \n{}", edited_content);
-            task.description = edited_content;
-            task.metadata.updated_at = Some(Utc::now());
-            let mut task_file = std::fs::File::create(project_dir)?;
-            let _ = task_file.write_all(task.to_string().unwrap().as_bytes());
+    // Saved queries come from the config of the project the command line
+    // points at. Once they are expanded the command line is parsed again,
+    // and the project and config are set up from that, so `--project` and
+    // `-C` saved in a query count too.
+    project::select(cli.project.as_deref());
+    project::set_working_dir(&cli.dir)?;
+    let cli = match query::expand(&std::env::args_os().collect::<Vec<_>>(), config::queries)? {
+        Some(args) => {
+            let cli = Cli::parse_from(args);
+            project::select(cli.project.as_deref());
+            project::set_working_dir(&cli.dir)?;
+            cli
         }
-        Err(e) => eprintln!("Failed to launch editor '{}': '{}'", editor, e)
+        None => cli,
+    };
+    config::init()?;
+    color::init(cli.no_color);
+    icons::init(cli.ascii);
+    output::set_quiet(cli.quiet);
+    events::disable_webhooks(cli.no_webhook);
+    events::disable_hooks(cli.no_hooks);
+    context::disable(cli.no_context);
+    dates::use_utc(cli.utc || config::get().utc);
+    archive::disable(cli.no_auto_archive);
+    if cli.command.is_some() && !prompt {
+        archive::auto();
     }
 
-    Ok(())
-}
-
-fn get_project_path() -> Result<PathBuf> {
-    let mut project_dir = PathBuf::new();
-    project_dir.push(dirs::home_dir().ok_or(anyhow!("Could not find the home directory"))?);
-    project_dir.push(".td");
-    if let Some(origin) = get_repo_remote() {
-        project_dir.push(origin);
-    }
-    std::fs::create_dir_all(&project_dir)?;
-    return Ok(project_dir)
-}
-
-fn create_td_home() -> io::Result<PathBuf> {
-    let home_dir = dirs::home_dir()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find home directory"))?;
-
-    let td_dir_path = home_dir.join(".td");
-    if !td_dir_path.exists() {
-        fs::create_dir(&td_dir_path);
-    }
-    return Ok(td_dir_path);
-}
-
-fn get_repo_remote() -> Option<String> {
-    match Repository::open_from_env() {
-        Ok(repo) => {
-            match repo.find_remote("origin") {
-                Ok(remote) => match remote.url() {
-                    Some(url) => Some(sanitize_dir_name(url)),
-                    None => None
-                }
-                Err(err) => None
-            }
+    let result = match &cli.command {
+        Some(Commands::Add(args)) => commands::add::run(args),
+        Some(Commands::Ls(args)) => commands::ls::run(args),
+        Some(Commands::Show(args)) => commands::show::run(args),
+        Some(Commands::Edit(args)) => commands::edit::run(args),
+        Some(Commands::Search(args)) => commands::search::run(args),
+        Some(Commands::Tags(args)) => commands::tags::run(args),
+        Some(Commands::Epic { command }) => commands::epic::run(command),
+        Some(Commands::Milestone { command }) => commands::milestone::run(command),
+        Some(Commands::Import(args)) => commands::import::run(args),
+        Some(Commands::Tag { command }) => commands::tag::run(command),
+        Some(Commands::Rename(args)) => commands::rename::run(args),
+        Some(Commands::Rm(args)) => commands::rm::run(args),
+        Some(Commands::Archive(args)) => commands::archive::run(args),
+        Some(Commands::Encrypt(args)) => commands::encrypt::run(args),
+        Some(Commands::Mv(args)) => commands::mv::run(args),
+        Some(Commands::Path { id, print0 }) => commands::path::run(id, *print0),
+        Some(Commands::Block(args)) => commands::block::run(args),
+        Some(Commands::Relate(args)) => commands::relate::run(args),
+        Some(Commands::LinkUrl(args)) => commands::link_url::run(args),
+        Some(Commands::Open(args)) => commands::open::run(args),
+        Some(Commands::Set(args)) => commands::set::run(args),
+        Some(Commands::Start { id }) => commands::mark::run(id, TaskStatus::DOING),
+        Some(Commands::Done { id }) => commands::mark::run(id, TaskStatus::DONE),
+        Some(Commands::Todo { id }) => commands::mark::run(id, TaskStatus::TODO),
+        Some(Commands::Snooze(args)) => commands::snooze::run(args),
+        Some(Commands::Next(args)) => commands::next::run(args),
+        Some(Commands::Pick(args)) => commands::pick::run(args),
+        Some(Commands::Standup(args)) => commands::standup::run(args),
+        Some(Commands::Due) => commands::due::run(),
+        Some(Commands::Stale(args)) => commands::stale::run(args),
+        Some(Commands::Notify(args)) => commands::notify::run(args),
+        Some(Commands::Remind(args)) => commands::remind::run(args),
+        Some(Commands::Board) => commands::board::run(),
+        Some(Commands::Prompt(args)) => commands::prompt::run(args),
+        Some(Commands::Export(args)) => commands::export::run(args),
+        Some(Commands::Serve(args)) => commands::serve::run(args),
+        Some(Commands::History(args)) => commands::history::run(args),
+        Some(Commands::Changelog(args)) => commands::changelog::run(args),
+        Some(Commands::Migrate(args)) => commands::migrate::run(args),
+        Some(Commands::Reindex(args)) => commands::reindex::run(args),
+        Some(Commands::Init(args)) => commands::init::run(args),
+        Some(Commands::Doctor) => commands::doctor::run(),
+        Some(Commands::Clean(args)) => commands::clean::run(args),
+        Some(Commands::Check(args)) => commands::check::run(args),
+        Some(Commands::Config) => commands::config::run(),
+        Some(Commands::Status) => commands::status::run(),
+        Some(Commands::Stats(args)) => commands::stats::run(args),
+        Some(Commands::Projects(args)) => commands::projects::run(args),
+        Some(Commands::Q { .. }) => unreachable!("td q is expanded into td ls before parsing"),
+        Some(Commands::Queries) => commands::queries::run(),
+        Some(Commands::Context(args)) => commands::context::run(args),
+        Some(Commands::Project { command }) => commands::project::run(command),
+        Some(Commands::Webhook { command }) => commands::webhook::run(command),
+        Some(Commands::Hook { command }) => commands::hook::run(command),
+        None => {
+            println!("No command provided. Use --help for more information.");
+            Ok(())
         }
-        Err(err) => None,
+    };
+    if !prompt {
+        index::report_skipped();
     }
+    result
 }
-
-fn sanitize_dir_name(origin: &str) -> String {
-    let problematic_chars = ['/', '\\', ':', '*', '?', '"', '<', '>', '|', ' ', '@', '#', '$', '%', '^', '&', '+', '=', '~'];
-    let mut sanitized = origin.to_string();
-
-    for &c in problematic_chars.iter() {
-        sanitized = sanitized.replace(c, "_")
-    }
-    
-    sanitized = sanitized.trim_matches('.').to_string();
-    sanitized = sanitized.replace("..", "_");
-    sanitized
-}
\ No newline at end of file
@@ -0,0 +1,130 @@
+/// Renders the markdown of a task description as HTML for exports. Covers
+/// what descriptions use in practice: paragraphs, headings, bullet and
+/// numbered lists with checkboxes, fenced code, inline code, emphasis and
+/// links. Everything else is shown as escaped text.
+pub fn to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut code: Option<(&str, String)> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if let Some((fence, block)) = &mut code {
+            if trimmed.starts_with(*fence) {
+                html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(block)));
+                code = None;
+            } else {
+                block.push_str(line);
+                block.push('\n');
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            end_paragraph(&mut html, &mut paragraph);
+            end_list(&mut html, &mut list);
+            code = Some((&trimmed[..3], String::new()));
+        } else if trimmed.is_empty() {
+            end_paragraph(&mut html, &mut paragraph);
+            end_list(&mut html, &mut list);
+        } else if let Some((level, text)) = heading(trimmed) {
+            end_paragraph(&mut html, &mut paragraph);
+            end_list(&mut html, &mut list);
+            html.push_str(&format!("<h{level}>{}</h{level}>\n", inline(text)));
+        } else if let Some((kind, item)) = list_item(trimmed) {
+            end_paragraph(&mut html, &mut paragraph);
+            if list != Some(kind) {
+                end_list(&mut html, &mut list);
+                html.push_str(&format!("<{}>\n", kind));
+                list = Some(kind);
+            }
+            let item = match item.get(..4) {
+                Some("[ ] ") => format!("<input type=\"checkbox\" disabled> {}", inline(&item[4..])),
+                Some("[x] " | "[X] ") => format!("<input type=\"checkbox\" checked disabled> {}", inline(&item[4..])),
+                _ => inline(item),
+            };
+            html.push_str(&format!("<li>{}</li>\n", item));
+        } else {
+            end_list(&mut html, &mut list);
+            paragraph.push(trimmed);
+        }
+    }
+    if let Some((_, block)) = code {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&block)));
+    }
+    end_paragraph(&mut html, &mut paragraph);
+    end_list(&mut html, &mut list);
+    html
+}
+
+/// Escapes text for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn end_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        html.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join(" "))));
+        paragraph.clear();
+    }
+}
+
+fn end_list(html: &mut String, list: &mut Option<&str>) {
+    if let Some(kind) = list.take() {
+        html.push_str(&format!("</{}>\n", kind));
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+/// The kind of list a line is an item of, `ul` or `ol`, and the item text.
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(("ul", item));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let item = line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))?;
+    (digits > 0).then_some(("ol", item))
+}
+
+/// Renders code spans, strong and emphasized text and links, escaping the rest.
+fn inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let closing = |marker: &str| rest[marker.len()..].find(marker).filter(|end| *end > 0).map(|end| end + marker.len());
+        if c == '`' && let Some(end) = closing("`") {
+            html.push_str(&format!("<code>{}</code>", escape(&rest[1..end])));
+            rest = &rest[end + 1..];
+        } else if rest.starts_with("**") && let Some(end) = closing("**") {
+            html.push_str(&format!("<strong>{}</strong>", inline(&rest[2..end])));
+            rest = &rest[end + 2..];
+        } else if (c == '*' || c == '_') && let Some(end) = closing(&rest[..1]) {
+            html.push_str(&format!("<em>{}</em>", inline(&rest[1..end])));
+            rest = &rest[end + 1..];
+        } else if c == '[' && let Some((label, url, len)) = link(rest) {
+            html.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), inline(label)));
+            rest = &rest[len..];
+        } else {
+            html.push_str(&escape(&rest[..c.len_utf8()]));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    html
+}
+
+/// A `[label](url)` at the start of `text`, with the length it spans. Links
+/// that would run script are not links.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let url_end = label_end + 2 + text[label_end + 2..].find(')')?;
+    let url = text[label_end + 2..url_end].trim();
+    let scheme = url.split(':').next().unwrap_or_default().to_lowercase();
+    if url.contains(':') && !["http", "https", "mailto"].contains(&scheme.as_str()) {
+        return None;
+    }
+    Some((&text[1..label_end], url, url_end + 1))
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::{Path, PathBuf}};
+use anyhow::{Result, anyhow};
+
+use crate::dates;
+use crate::project;
+use crate::task::{TaskMetadata, TaskStatus};
+
+/// The file in a project directory listing its milestones.
+pub const MILESTONES_FILE: &str = "milestones.json";
+
+/// A named target that tasks are filed under with `--milestone`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Milestone {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Utc>>,
+}
+
+impl Milestone {
+    /// Whether the due date has passed.
+    pub fn is_overdue(&self) -> bool {
+        self.due.is_some_and(|due| dates::day_of(due) < dates::today())
+    }
+
+    /// How many of the tasks filed under the milestone are still open and
+    /// how many are done.
+    pub fn counts<'a>(&self, tasks: impl IntoIterator<Item = &'a TaskMetadata>) -> (usize, usize) {
+        let members: Vec<&TaskMetadata> = tasks.into_iter().filter(|meta| meta.milestone.as_ref() == Some(&self.name)).collect();
+        let done = members.iter().filter(|meta| meta.status == TaskStatus::DONE).count();
+        (members.len() - done, done)
+    }
+}
+
+/// The milestones file of the project a task directory belongs to. Sub-scopes
+/// share the one of their repository.
+fn path(dir: &Path) -> Result<PathBuf> {
+    Ok(project::project_root(dir)?.join(MILESTONES_FILE))
+}
+
+/// The milestones of the project, in the order they were added.
+pub fn load(dir: &Path) -> Result<Vec<Milestone>> {
+    let path = path(dir)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| anyhow!("Could not read {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save(dir: &Path, milestones: &[Milestone]) -> Result<()> {
+    let path = path(dir)?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(&milestones)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// The milestone with the name, failing with the names there are.
+pub fn find(dir: &Path, name: &str) -> Result<Milestone> {
+    let milestones = load(dir)?;
+    if let Some(milestone) = milestones.iter().find(|milestone| milestone.name == name) {
+        return Ok(milestone.clone());
+    }
+    if milestones.is_empty() {
+        return Err(anyhow!("There is no milestone '{}', add it with `td milestone add {}`", name, name));
+    }
+    let names: Vec<&str> = milestones.iter().map(|milestone| milestone.name.as_str()).collect();
+    Err(anyhow!("There is no milestone '{}', the milestones are: {}", name, names.join(", ")))
+}
@@ -0,0 +1,85 @@
+use icu_normalizer::DecomposingNormalizerBorrowed;
+
+use crate::config;
+
+/// The form text is compared in by search and tag matching, so `Bug`, `bug`
+/// and `BUG` are the same tag. Letters are case folded the way Unicode does
+/// it outside of Turkish: `ß` becomes `ss`, `İ` becomes `i` followed by a
+/// combining dot and the dotless `ı` stays distinct from `i`. With
+/// `fold_diacritics` in the config, accents are stripped as well so
+/// `Réfactor` matches `refactor` (and `İ` matches `i`).
+pub fn normalize(text: &str) -> String {
+    let folded = fold_case(text.trim());
+    if config::get().fold_diacritics {
+        strip_diacritics(&folded)
+    } else {
+        folded
+    }
+}
+
+/// `normalize`, or the text unchanged when matching is case sensitive.
+pub fn key(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive { text.trim().to_string() } else { normalize(text) }
+}
+
+/// Lowercases text, also applying the full case folds that `to_lowercase`
+/// leaves alone.
+pub fn fold_case(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ſ' => folded.push('s'),
+            'ς' => folded.push('σ'),
+            c => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+/// Decomposes text (NFKD) and drops the combining marks, leaving the base letters.
+fn strip_diacritics(text: &str) -> String {
+    DecomposingNormalizerBorrowed::new_nfkd()
+        .normalize(text)
+        .chars()
+        .filter(|c| !is_combining_mark(*c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn german_sharp_s_folds_to_ss() {
+        assert_eq!(fold_case("Straße"), "strasse");
+        assert_eq!(fold_case("STRAẞE"), fold_case("strasse"));
+        assert_eq!(normalize(" Maß "), normalize("MASS"));
+    }
+
+    #[test]
+    fn turkish_dotted_and_dotless_i_are_not_tailored() {
+        // Dotless ı is its own letter, not a lowercase I.
+        assert_eq!(fold_case("ı"), "ı");
+        assert_ne!(fold_case("ı"), fold_case("I"));
+        assert_eq!(fold_case("I"), "i");
+        // Dotted İ keeps its dot as a combining mark, which only stripping
+        // diacritics removes.
+        assert_eq!(fold_case("İ"), "i\u{307}");
+        assert_ne!(fold_case("İ"), fold_case("i"));
+        assert_eq!(strip_diacritics(&fold_case("İ")), "i");
+        assert_eq!(strip_diacritics("ı"), "ı");
+    }
+
+    #[test]
+    fn accents_are_only_stripped_on_request() {
+        assert_eq!(fold_case("Réfactor"), "réfactor");
+        assert_eq!(strip_diacritics(&fold_case("Réfactor")), "refactor");
+        assert_eq!(key(" Bug ", true), "Bug");
+        assert_eq!(key(" Bug ", false), "bug");
+    }
+}
@@ -0,0 +1,167 @@
+use clap::{Args, ValueEnum};
+use serde::{Serialize, Serializer};
+use serde_yaml::{Mapping, Value};
+use std::{io::Write, sync::atomic::{AtomicBool, Ordering}};
+use anyhow::Result;
+
+use crate::checklist::Progress;
+use crate::index::{self, IndexEntry};
+use crate::task::{Task, TaskMetadata};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Silences the confirmations commands print after changing tasks, for
+/// scripts that only look at the exit code.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Ends td with `code` from inside a command, flushing stdout and warning
+/// about skipped task files first, as returning to `main` would.
+pub fn exit(code: i32) -> ! {
+    let _ = std::io::stdout().flush();
+    index::report_skipped();
+    std::process::exit(code)
+}
+
+/// `println!` for a command's human chatter, which `--quiet` suppresses.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Prints one record of a list meant for other programs, ending it with a NUL
+/// byte instead of a newline for `-0` so it survives `xargs -0` whatever it contains.
+pub fn print_record(record: &str, print0: bool) {
+    print!("{}", terminated(record, print0));
+}
+
+fn terminated(record: &str, print0: bool) -> String {
+    format!("{}{}", record, if print0 { '\0' } else { '\n' })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable output
+    #[default]
+    Text,
+    Json,
+    Yaml,
+}
+
+/// The flags choosing between human readable and machine readable output.
+#[derive(Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    /// The output format
+    #[arg(long, value_enum, conflicts_with_all = ["json", "yaml"])]
+    output: Option<OutputFormat>,
+    /// Shorthand for --output json
+    #[arg(long, conflicts_with = "yaml")]
+    json: bool,
+    /// Shorthand for --output yaml
+    #[arg(long)]
+    yaml: bool,
+}
+
+impl OutputArgs {
+    pub fn format(&self) -> OutputFormat {
+        match (self.json, self.yaml) {
+            (true, _) => OutputFormat::Json,
+            (_, true) => OutputFormat::Yaml,
+            _ => self.output.unwrap_or_default(),
+        }
+    }
+
+    /// Whether output should be machine readable rather than text.
+    pub fn is_structured(&self) -> bool {
+        self.format() != OutputFormat::Text
+    }
+}
+
+/// Prints a value in one of the machine readable formats. Both go through the
+/// same `Serialize` implementation so they always carry the same data.
+pub fn print<T: Serialize>(format: OutputFormat, value: &T) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        OutputFormat::Text => unreachable!("text output is printed by each command"),
+    }
+    Ok(())
+}
+
+/// How a task is represented in JSON and YAML output.
+#[derive(Debug, Serialize)]
+pub struct TaskView<'a> {
+    #[serde(flatten, serialize_with = "known_keys")]
+    pub metadata: &'a TaskMetadata,
+    /// The keys td does not know, kept apart so they can not be mistaken
+    /// for its own fields
+    #[serde(skip_serializing_if = "Mapping::is_empty")]
+    pub extra: &'a Mapping,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checklist: Option<Progress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<&'a str>,
+}
+
+impl<'a> TaskView<'a> {
+    pub fn from_entry(entry: &'a IndexEntry) -> Self {
+        TaskView {
+            metadata: &entry.metadata,
+            extra: &entry.metadata.extra,
+            checklist: Some(entry.checklist).filter(|c| c.total > 0),
+            description: None,
+        }
+    }
+
+    pub fn from_task(task: &'a Task) -> Self {
+        TaskView {
+            metadata: &task.metadata,
+            extra: &task.metadata.extra,
+            checklist: Some(crate::checklist::progress(&task.description)).filter(|c| c.total > 0),
+            description: Some(task.description.as_str()),
+        }
+    }
+}
+
+/// The fields of a task without the unknown keys, which `TaskView` lists
+/// under `extra` instead.
+fn known_keys<S: Serializer>(metadata: &&TaskMetadata, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    let mut value = serde_yaml::to_value(metadata).map_err(serde::ser::Error::custom)?;
+    if let Value::Mapping(map) = &mut value {
+        map.retain(|key, _| !metadata.extra.contains_key(key));
+    }
+    value.serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, process::{Command, Stdio}};
+
+    #[test]
+    fn print0_records_survive_xargs() {
+        let records = ["plain", "with space", "two\nlines", "tab\there"];
+        let stream: String = records.iter().map(|record| terminated(record, true)).collect();
+        let mut xargs = Command::new("xargs")
+            .args(["-0", "printf", "%s\\0"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("xargs is installed");
+        xargs.stdin.take().unwrap().write_all(stream.as_bytes()).unwrap();
+        let output = xargs.wait_with_output().unwrap();
+        assert!(output.status.success());
+        let back: Vec<&str> = std::str::from_utf8(&output.stdout).unwrap().split_terminator('\0').collect();
+        assert_eq!(back, records);
+        assert_eq!(terminated("two\nlines", false), "two\nlines\n");
+    }
+}
@@ -0,0 +1,28 @@
+use std::{num::NonZeroUsize, thread};
+
+/// Below this many items the work stays on the calling thread, where starting
+/// threads would cost more than it saves.
+const MIN_PARALLEL: usize = 64;
+
+/// The most threads used, reading many files at once on a network share
+/// helps only up to a point.
+const MAX_THREADS: usize = 8;
+
+/// Applies `f` to every item, spreading them over a few threads when there
+/// are enough. The results are in the order of the items either way.
+pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get).min(MAX_THREADS);
+    if items.len() < MIN_PARALLEL || threads < 2 {
+        return items.iter().map(f).collect();
+    }
+    let chunk = items.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items.chunks(chunk)
+            .map(|part| scope.spawn(move || part.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    })
+}
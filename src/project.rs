@@ -0,0 +1,710 @@
+use git2::Repository;
+use std::{cell::RefCell, collections::BTreeMap, fs, io, path::{Path, PathBuf}, sync::RwLock};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::config::{self, Config};
+use crate::crypt;
+use crate::events::HOOKS_DIR;
+use crate::index::{self, load_entries};
+use crate::journal;
+use crate::parallel;
+use crate::task::Task;
+
+/// The file extension used for task files inside a project directory.
+pub const TASK_EXTENSION: &str = "td";
+
+/// The project chosen with `--project`, overriding the current repository.
+static SELECTED: RwLock<Option<String>> = RwLock::new(None);
+
+/// Makes every command use the project called `name`, an alias from the
+/// `[projects]` config table or a directory name, instead of the current one.
+/// Called again when a saved query changes the command line.
+pub fn select(name: Option<&str>) {
+    *SELECTED.write().unwrap_or_else(|e| e.into_inner()) = name.map(str::to_string);
+}
+
+fn selected() -> Option<String> {
+    SELECTED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The directory given with `-C`, standing in for the working directory
+/// when looking for the repository.
+static WORKING_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Makes td look for the repository as if it was started in `dirs`. Like
+/// `git -C`, each relative directory is taken relative to the one before.
+pub fn set_working_dir(dirs: &[PathBuf]) -> Result<()> {
+    let mut dir = None;
+    for next in dirs {
+        let joined = dir.unwrap_or(std::env::current_dir()?).join(next);
+        if !joined.is_dir() {
+            return Err(anyhow!("Can not run in {}, it is not a directory", joined.display()));
+        }
+        dir = Some(joined);
+    }
+    *WORKING_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+    Ok(())
+}
+
+/// The directory td acts from: the one given with `-C`, otherwise the
+/// current directory. Relative paths given on the command line for files to
+/// read are resolved against it.
+pub fn working_dir() -> PathBuf {
+    WORKING_DIR.read().unwrap_or_else(|e| e.into_inner()).clone().or_else(|| std::env::current_dir().ok()).unwrap_or_default()
+}
+
+/// A path given on the command line, taken relative to `working_dir` unless
+/// it is absolute.
+pub fn resolve(path: &Path) -> PathBuf {
+    working_dir().join(path)
+}
+
+/// Runs `f` with `dir` standing in for the working directory. The working
+/// directory is process wide, so the tests that change it take turns.
+#[cfg(test)]
+pub fn in_working_dir<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+    static TURN: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    set_working_dir(&[dir.to_path_buf()]).unwrap();
+    let result = f();
+    *WORKING_DIR.write().unwrap_or_else(|e| e.into_inner()) = None;
+    result
+}
+
+/// Opens the repository td acts in, found from `working_dir`. Without `-C`
+/// the `GIT_DIR` and related environment variables are honoured like git does.
+pub fn open_repo() -> Result<Repository, git2::Error> {
+    match WORKING_DIR.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some(dir) => Repository::discover(dir),
+        None => Repository::open_from_env(),
+    }
+}
+
+pub fn get_project_path() -> Result<PathBuf> {
+    let mut project_dir = repo_project_path()?;
+    if let Some(scope) = current_scope() && project_dir != td_home()? {
+        project_dir.push(scope);
+        std::fs::create_dir_all(&project_dir)?;
+    }
+    Ok(project_dir)
+}
+
+/// The directory of the current project, sub-scope included, when it exists
+/// already. Unlike `get_project_path` nothing is created, and the tasks kept
+/// directly in the data directory outside of repositories are no project.
+pub fn existing_project_path() -> Result<Option<PathBuf>> {
+    let home = td_home()?;
+    let mut project_dir = project_dir_in(&home, config::get())?;
+    if project_dir == home {
+        return Ok(None);
+    }
+    if let Some(scope) = current_scope() {
+        project_dir.push(scope);
+    }
+    Ok(Some(project_dir).filter(|dir| dir.is_dir()))
+}
+
+/// The git config key that turns on per-subdirectory tasks for a repository, see `td init --subdirs`.
+pub const SUBDIRS_CONFIG_KEY: &str = "td.subdirs";
+
+/// Whether the current repository keeps separate tasks per subdirectory,
+/// through `subdirs` in the config or `td.subdirs` in its git config.
+fn subdirs_enabled(repo: &Repository) -> bool {
+    config::get().subdirs || repo.config().and_then(|config| config.get_bool(SUBDIRS_CONFIG_KEY)).unwrap_or(false)
+}
+
+/// The sub-scope the working directory falls in when the repository keeps
+/// tasks per subdirectory: its path from the repository root, made into a
+/// directory name. `None` at the root, outside a repository, when the mode
+/// is off or when `--project` picked the project.
+pub fn current_scope() -> Option<String> {
+    if selected().is_some() {
+        return None;
+    }
+    let repo = open_repo().ok()?;
+    if !subdirs_enabled(&repo) {
+        return None;
+    }
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let cwd = working_dir().canonicalize().ok()?;
+    let relative = cwd.strip_prefix(&workdir).ok()?.to_string_lossy().to_string();
+    if relative.is_empty() { None } else { Some(scope_name(&relative)) }
+}
+
+/// The directory name of the sub-scope for a path relative to the repository root.
+pub fn scope_name(relative: &str) -> String {
+    sanitize_dir_name(relative.trim_matches('/'))
+}
+
+/// The directory of the current repository's project without its sub-scope,
+/// which holds the tasks of the root and one directory per sub-scope.
+/// Creates the directory the first time, with a hint about `td init`.
+pub fn repo_project_path() -> Result<PathBuf> {
+    let home = td_home()?;
+    let project_dir = project_dir_in(&home, config::get())?;
+    if project_dir != home && !project_dir.exists() && !crate::output::quiet() {
+        eprintln!("hint: filing this repository's tasks in {}, run `td init` to set the project up explicitly", project_dir.display());
+    }
+    std::fs::create_dir_all(&project_dir)?;
+    Ok(project_dir)
+}
+
+/// `project_dir` followed by the sub-scope directories inside it. The global
+/// project has none, its subdirectories are the other projects.
+pub fn with_scopes(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![project_dir.to_path_buf()];
+    if project_dir == td_home()? {
+        return Ok(dirs);
+    }
+    let mut scopes = Vec::new();
+    for entry in fs::read_dir(project_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            scopes.push(entry.path());
+        }
+    }
+    scopes.sort();
+    dirs.extend(scopes);
+    Ok(dirs)
+}
+
+/// The directories `ls` shows: every sub-scope of the repository with
+/// `--repo` or at its root, otherwise just the current one.
+pub fn listing_dirs(whole_repo: bool) -> Result<Vec<PathBuf>> {
+    if whole_repo || current_scope().is_none() {
+        with_scopes(&repo_project_path()?)
+    } else {
+        Ok(vec![get_project_path()?])
+    }
+}
+
+/// The directory of the current project inside `home`, without creating it.
+/// Takes the settings explicitly because it is also used while they load.
+pub fn project_dir_in(home: &Path, settings: &Config) -> Result<PathBuf> {
+    if let Some(name) = selected() {
+        return named_project(home, &settings.projects, &name);
+    }
+    Ok(match chosen_remote(&settings.remote_priority) {
+        Some(remote) => home.join(sanitize_dir_name(&remote.url)),
+        None => home.to_path_buf(),
+    })
+}
+
+/// The directory of an existing project, looked up by alias or directory name.
+pub fn find_project(name: &str) -> Result<PathBuf> {
+    named_project(&td_home()?, &config::get().projects, name)
+}
+
+fn named_project(home: &Path, aliases: &BTreeMap<String, String>, name: &str) -> Result<PathBuf> {
+    if name == GLOBAL_PROJECT {
+        return Ok(home.to_path_buf());
+    }
+    // A name is one directory below the td home, never a path that could
+    // point anywhere else.
+    if name.contains(['/', '\\']) || name.contains("..") || Path::new(name).is_absolute() {
+        return Err(anyhow!("'{}' is not a project name, expected an alias or a directory name from `td projects`", name));
+    }
+    let dir = match aliases.get(name) {
+        Some(remote) => fs::read_dir(home)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.is_dir() && path.file_name().is_some_and(|n| remote_key(&n.to_string_lossy()) == remote_key(remote)))
+            .unwrap_or_else(|| home.join(sanitize_dir_name(remote))),
+        None => home.join(name),
+    };
+    if !dir.is_dir() || name.starts_with('.') || name == HOOKS_DIR {
+        return Err(anyhow!("No project named '{}', see `td projects` for the known ones", name));
+    }
+    Ok(dir)
+}
+
+/// A form of a remote that is the same for its URL, its `host/path` spelling
+/// and the directory name `sanitize_dir_name` makes of it.
+pub fn remote_key(remote: &str) -> String {
+    let mut key = sanitize_dir_name(&remote.to_lowercase());
+    for prefix in ["https___", "http___", "ssh___", "git___", "git_"] {
+        if let Some(rest) = key.strip_prefix(prefix) {
+            key = rest.to_string();
+            break;
+        }
+    }
+    key.trim_end_matches(".git").trim_matches('_').to_string()
+}
+
+/// The alias configured for a project directory name, if there is one.
+pub fn alias_for<'a>(dir_name: &str, aliases: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    aliases.iter()
+        .find(|(_, remote)| remote_key(remote) == remote_key(dir_name))
+        .map(|(alias, _)| alias.as_str())
+}
+
+/// The `~/.td` directory, which holds the global config.
+pub fn default_home() -> Result<PathBuf> {
+    Ok(dirs::home_dir().ok_or(anyhow!("Could not find the home directory"))?.join(".td"))
+}
+
+/// The directory holding every project: `data_dir` from the config, or `~/.td`.
+pub fn td_home() -> Result<PathBuf> {
+    match &config::get().data_dir {
+        Some(dir) => Ok(expand_home(dir)),
+        None => default_home(),
+    }
+}
+
+/// Replaces a leading `~` in a configured path with the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Every project directory: the global list in `~/.td` itself followed by
+/// one directory per repository, sorted by name. Projects with an alias in
+/// the config are named by it.
+pub fn list_projects() -> Result<Vec<(String, PathBuf)>> {
+    let home = td_home()?;
+    fs::create_dir_all(&home)?;
+    let aliases = &config::get().projects;
+    let mut projects = Vec::new();
+    for entry in fs::read_dir(&home)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() && !name.starts_with('.') && name != HOOKS_DIR {
+            let name = alias_for(&name, aliases).map(str::to_string).unwrap_or(name);
+            projects.push((name, entry.path()));
+        }
+    }
+    projects.sort();
+    projects.insert(0, (GLOBAL_PROJECT.to_string(), home));
+    Ok(projects)
+}
+
+/// The name shown for the tasks kept directly in `~/.td`, used outside of git repositories.
+pub const GLOBAL_PROJECT: &str = "(global)";
+
+/// The directory of the project a task directory belongs to, which is the
+/// directory itself unless it is a sub-scope.
+pub fn project_root(dir: &Path) -> Result<PathBuf> {
+    let home = td_home()?;
+    match dir.strip_prefix(&home).ok().and_then(|rest| rest.components().next()) {
+        Some(dir_name) => Ok(home.join(dir_name)),
+        None => Ok(home),
+    }
+}
+
+/// The name a project directory, or a sub-scope inside one, is shown by:
+/// its alias when it has one, otherwise the directory name.
+pub fn project_name(dir: &Path) -> String {
+    let home = td_home().unwrap_or_default();
+    let Some(dir_name) = dir.strip_prefix(&home).ok().and_then(|rest| rest.components().next()) else {
+        return GLOBAL_PROJECT.to_string();
+    };
+    let dir_name = dir_name.as_os_str().to_string_lossy().to_string();
+    alias_for(&dir_name, &config::get().projects).map(str::to_string).unwrap_or(dir_name)
+}
+
+pub fn create_td_home() -> io::Result<PathBuf> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not find home directory"))?;
+
+    let td_dir_path = home_dir.join(".td");
+    if !td_dir_path.exists() {
+        fs::create_dir(&td_dir_path)?;
+    }
+    Ok(td_dir_path)
+}
+
+/// The git config key a repository can set to pick its remote, see `td init --remote`.
+pub const REMOTE_CONFIG_KEY: &str = "td.remote";
+
+/// The remote a repository's tasks are filed under, and why it was chosen.
+#[derive(Debug, Clone)]
+pub struct ChosenRemote {
+    pub name: String,
+    pub url: String,
+    /// Whether it was picked by `td.remote` in the repository's git config
+    /// rather than by `remote_priority`
+    pub from_repo_config: bool,
+}
+
+/// Picks the remote of the current repository: the one named by `td.remote`
+/// in its git config, otherwise the first of `priority` that exists. `None`
+/// outside a repository or when none of the remotes exist.
+pub fn chosen_remote(priority: &[String]) -> Option<ChosenRemote> {
+    let repo = open_repo().ok()?;
+    let pinned = repo.config().ok().and_then(|config| config.get_string(REMOTE_CONFIG_KEY).ok());
+    let candidates = pinned.iter().map(|name| (name, true)).chain(priority.iter().map(|name| (name, false)));
+    for (name, from_repo_config) in candidates {
+        if let Some(url) = repo.find_remote(name).ok().and_then(|remote| remote.url().map(str::to_string)) {
+            return Some(ChosenRemote { name: name.clone(), url, from_repo_config });
+        }
+    }
+    None
+}
+
+/// What the current repository has checked out.
+pub enum Head {
+    Branch(String),
+    Detached,
+}
+
+/// The HEAD of the current repository, `None` outside of one. A branch is
+/// reported before its first commit too.
+pub fn head() -> Option<Head> {
+    let repo = open_repo().ok()?;
+    let head = repo.find_reference("HEAD").ok()?;
+    Some(match head.symbolic_target() {
+        Some(target) => Head::Branch(target.strip_prefix("refs/heads/").unwrap_or(target).to_string()),
+        None => Head::Detached,
+    })
+}
+
+/// The branch checked out in the current repository. `None` outside a
+/// repository, and with a notice when HEAD is detached, so branch scoping
+/// falls back to showing every task.
+pub fn current_branch() -> Option<String> {
+    match head()? {
+        Head::Branch(branch) => Some(branch),
+        Head::Detached => {
+            eprintln!("note: HEAD is detached, tasks are not scoped to a branch");
+            None
+        }
+    }
+}
+
+pub fn get_repo_remote() -> Option<String> {
+    get_repo_remote_url().as_deref().map(sanitize_dir_name)
+}
+
+/// The URL of the remote the current repository's tasks are filed under.
+pub fn get_repo_remote_url() -> Option<String> {
+    chosen_remote(&config::get().remote_priority).map(|remote| remote.url)
+}
+
+pub fn sanitize_dir_name(origin: &str) -> String {
+    let problematic_chars = ['/', '\\', ':', '*', '?', '"', '<', '>', '|', ' ', '@', '#', '$', '%', '^', '&', '+', '=', '~'];
+    let mut sanitized = origin.to_string();
+
+    for &c in problematic_chars.iter() {
+        sanitized = sanitized.replace(c, "_")
+    }
+
+    sanitized = sanitized.trim_matches('.').to_string();
+    sanitized = sanitized.replace("..", "_");
+    sanitized
+}
+
+/// The longest slug a file name gets, in bytes.
+const MAX_SLUG_LEN: usize = 40;
+
+/// An ASCII slug of a title for use in file names: lowercase, with every run
+/// of other characters collapsed into a single `-`.
+pub fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= MAX_SLUG_LEN {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() { "task".to_string() } else { slug.to_string() }
+}
+
+/// The file name a task is stored under in `dir`, like
+/// `fix-login-crash.a1b2c3d4.td`. The short id keeps names unique; the task
+/// itself is always found by the id in its frontmatter, so renamed files
+/// keep working.
+pub fn task_file_name(dir: &Path, task: &Task) -> String {
+    format!("{}.{}.{}", file_slug(dir, &task.metadata.title), task.short_id(), TASK_EXTENSION)
+}
+
+/// The slug of a title in file names, the same for every task in projects
+/// that encrypt their titles.
+fn file_slug(dir: &Path, title: &str) -> String {
+    if crypt::hides_titles(dir) { "task".to_string() } else { slug(title) }
+}
+
+/// The path a new task is stored at inside `project_dir`.
+pub fn task_path(project_dir: &Path, task: &Task) -> PathBuf {
+    project_dir.join(task_file_name(project_dir, task))
+}
+
+/// The id, or the start of it, that a task file name carries: the whole stem
+/// for files named by bare UUIDs, otherwise the part after the slug.
+fn file_name_id(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    match stem.rsplit_once('.') {
+        Some((_, id)) => Some(id.to_lowercase()),
+        None => Some(stem.to_lowercase()),
+    }
+}
+
+/// Reads and parses every task file in the project directory. Tasks with an
+/// unknown status are left out, see `index::report_skipped`.
+pub fn load_tasks(project_dir: &Path) -> Result<Vec<(PathBuf, Task)>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(project_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    load_task_files(paths)
+}
+
+/// Reads the tasks of the project and of its sub-scopes, the ones
+/// `find_task` looks ids up in, so a blocker or parent in another scope is
+/// seen too.
+pub fn load_scoped_tasks(project_dir: &Path) -> Result<Vec<(PathBuf, Task)>> {
+    let mut tasks = Vec::new();
+    for dir in with_scopes(project_dir)? {
+        tasks.extend(load_tasks(&dir)?);
+    }
+    Ok(tasks)
+}
+
+/// Reads and parses the given task files, leaving out those with an unknown
+/// status for `index::report_skipped` to warn about.
+pub fn load_task_files(mut paths: Vec<PathBuf>) -> Result<Vec<(PathBuf, Task)>> {
+    // Sorted so the tasks, and the first error, do not depend on the order
+    // the directory lists its files in or on how the threads finish.
+    paths.sort();
+    let parsed = parallel::map(&paths, |path| {
+        let content = crypt::read(path).map_err(|e| anyhow!("Could not read {}: {:#}", path.display(), e))?;
+        Task::from_str_lenient(&content).map_err(|e| anyhow!("Could not parse {}: {}", path.display(), e))
+    });
+    let mut tasks = Vec::new();
+    for (path, result) in paths.into_iter().zip(parsed) {
+        match result? {
+            (_, Some(problem)) => index::skip(path, problem),
+            (task, None) => tasks.push((path, task)),
+        }
+    }
+    Ok(tasks)
+}
+
+/// Finds the single task whose id starts with `prefix`, in the project or
+/// one of its sub-scopes.
+pub fn find_task(project_dir: &Path, prefix: &str) -> Result<(PathBuf, Task)> {
+    let prefix = prefix.to_lowercase();
+    let mut matches = Vec::new();
+    for dir in with_scopes(project_dir)? {
+        matches.extend(load_entries(&dir)?
+            .into_iter()
+            .filter(|entry| entry.metadata.id.to_string().starts_with(&prefix))
+            .map(|entry| entry.path));
+    }
+    let path = single_match(&prefix, matches)?;
+    let task = Task::from_str(&crypt::read(&path)?)
+        .map_err(|e| anyhow!("Could not parse {}: {}", path.display(), e))?;
+    Ok((path, task))
+}
+
+/// Finds the file of the task whose id starts with `prefix` without parsing
+/// it, falling back to the ids in the file names when some task in the
+/// project can not be parsed.
+pub fn find_task_path(project_dir: &Path, prefix: &str) -> Result<PathBuf> {
+    let prefix = prefix.to_lowercase();
+    let mut matches = Vec::new();
+    for dir in with_scopes(project_dir)? {
+        match load_entries(&dir) {
+            Ok(entries) => matches.extend(entries
+                .into_iter()
+                .filter(|entry| entry.metadata.id.to_string().starts_with(&prefix))
+                .map(|entry| entry.path)),
+            Err(_) => matches.extend(fs::read_dir(&dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(TASK_EXTENSION))
+                .filter(|path| file_name_id(path).is_some_and(|id| id.starts_with(&prefix) || prefix.starts_with(&id)))),
+        }
+    }
+    single_match(&prefix, matches)
+}
+
+fn single_match(prefix: &str, mut matches: Vec<PathBuf>) -> Result<PathBuf> {
+    match matches.len() {
+        0 => Err(anyhow!("No task matches the id '{}'", prefix)),
+        1 => Ok(matches.remove(0)),
+        n => Err(anyhow!("The id '{}' is ambiguous, it matches {} tasks", prefix, n)),
+    }
+}
+
+/// The file locked while a task of the project is read, changed and written
+/// back, in the project directory.
+pub const LOCK_FILE: &str = ".lock";
+
+/// An advisory lock on a project, released when dropped.
+struct ProjectLock {
+    _file: fs::File,
+}
+
+/// Waits until no other td is changing a task of the project and keeps the
+/// others waiting until the lock is dropped.
+fn lock_project(project_dir: &Path) -> Result<ProjectLock> {
+    let path = project_dir.join(LOCK_FILE);
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path)
+        .map_err(|e| anyhow!("Could not open {}: {}", path.display(), e))?;
+    file.lock().map_err(|e| anyhow!("Could not lock {}: {}", path.display(), e))?;
+    Ok(ProjectLock { _file: file })
+}
+
+thread_local! {
+    /// The projects whose lock this thread holds, so a nested `locked` does
+    /// not wait for itself.
+    static HELD: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `change` holding the lock of the project `dir` is in, so no other
+/// td, like `td serve` or another command, changes its tasks in between.
+/// Every command that reads tasks, changes them and writes them back does so
+/// in here or through `update_task`. Hooks run after it returns, they may
+/// call td again.
+pub fn locked<R>(dir: &Path, change: impl FnOnce() -> Result<R>) -> Result<R> {
+    let root = project_root(dir)?;
+    if HELD.with(|held| held.borrow().contains(&root)) {
+        return change();
+    }
+    let _lock = lock_project(&root)?;
+    HELD.with(|held| held.borrow_mut().push(root.clone()));
+    let result = change();
+    HELD.with(|held| held.borrow_mut().retain(|held| *held != root));
+    result
+}
+
+/// Finds the task whose id starts with `id`, lets `change` edit it and
+/// saves it, all under the project lock. Nothing is written when `change`
+/// fails. Returns where the task now lives and the task as saved.
+pub fn update_task(project_dir: &Path, id: &str, change: impl FnOnce(&mut Task) -> Result<()>) -> Result<(PathBuf, Task)> {
+    locked(project_dir, || {
+        let (path, mut task) = find_task(project_dir, id)?;
+        change(&mut task)?;
+        let path = save_task(&path, &task)?;
+        Ok((path, task))
+    })
+}
+
+/// Writes the task to `path`, going through a temporary file so an
+/// interrupted write never leaves a truncated task behind. When the title
+/// changed since the file was last written, the task moves to a file named
+/// after the new title; the returned path is where the task now lives.
+pub fn save_task(path: &Path, task: &Task) -> Result<PathBuf> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let old = crypt::read(path).ok().and_then(|content| Task::from_str(&content).ok());
+    let new_path = match &old {
+        Some(old) if file_slug(dir, &old.metadata.title) != file_slug(dir, &task.metadata.title) => {
+            free_task_path(dir, task, Some(path))
+        }
+        _ => path.to_path_buf(),
+    };
+    let file_name = new_path.file_name().and_then(|n| n.to_str()).ok_or(anyhow!("Invalid task path"))?;
+    let temp_path = new_path.with_file_name(format!(".{}.tmp", file_name));
+    fs::write(&temp_path, crypt::seal(dir, task, config::get().frontmatter)?)?;
+    // The new file is complete before the old one goes away, so a crash in
+    // between leaves a duplicate rather than losing the task.
+    fs::rename(&temp_path, &new_path)?;
+    if new_path != path {
+        fs::remove_file(path)?;
+    }
+    journal::record_change(&new_path, old.as_ref(), task);
+    Ok(new_path)
+}
+
+/// A free path in `dir` for the task's current title, adding a counter to
+/// the slug when another file already has the name. `current` is the file
+/// the task is stored in now, which does not count as taken.
+pub fn free_task_path(dir: &Path, task: &Task, current: Option<&Path>) -> PathBuf {
+    let slug = file_slug(dir, &task.metadata.title);
+    let mut candidate = dir.join(task_file_name(dir, task));
+    let mut counter = 2;
+    while candidate.exists() && Some(candidate.as_path()) != current {
+        candidate = dir.join(format!("{}-{}.{}.{}", slug, counter, task.short_id(), TASK_EXTENSION));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Checks that `parent` can become the parent of `child`: it has to exist and
+/// must not be `child` itself or one of its descendants.
+pub fn validate_parent(tasks: &[(PathBuf, Task)], child: Uuid, parent: Uuid) -> Result<()> {
+    let parent_of = |id: Uuid| {
+        tasks.iter()
+            .find(|(_, task)| task.metadata.id == id)
+            .map(|(_, task)| task.metadata.parent)
+    };
+    if parent_of(parent).is_none() {
+        return Err(anyhow!("No task with the id {} exists", parent));
+    }
+    let mut seen = Vec::new();
+    let mut current = Some(parent);
+    while let Some(id) = current {
+        if id == child {
+            return Err(anyhow!("Making {} the parent would create a cycle", &parent.to_string()[..8]));
+        }
+        if seen.contains(&id) {
+            break;
+        }
+        seen.push(id);
+        current = parent_of(id).flatten();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_project_stays_in_the_td_home() {
+        let home = std::env::temp_dir().join(format!("td-named-project-{}", std::process::id()));
+        fs::create_dir_all(home.join("github.com_a_b")).unwrap();
+        let aliases = BTreeMap::new();
+        assert_eq!(named_project(&home, &aliases, "github.com_a_b").unwrap(), home.join("github.com_a_b"));
+        for name in ["/tmp", "..", "../etc", "a/b", "a\\b", "github.com_a_b/.."] {
+            assert!(named_project(&home, &aliases, name).is_err(), "{} was accepted", name);
+        }
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn a_locked_project_keeps_others_waiting() {
+        let dir = std::env::temp_dir().join(format!("td-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let held = lock_project(&dir).unwrap();
+        let other = fs::File::open(dir.join(LOCK_FILE)).unwrap();
+        assert!(other.try_lock().is_err());
+        drop(held);
+        assert!(other.try_lock().is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn repeated_dirs_are_joined_like_git() {
+        let base = std::env::temp_dir().join(format!("td-working-dir-{}", std::process::id()));
+        fs::create_dir_all(base.join("a/b")).unwrap();
+        let (joined, missing) = in_working_dir(&base, || {
+            let dirs = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+            set_working_dir(&dirs(&[base.to_str().unwrap(), "a", "b"])).unwrap();
+            let joined = working_dir();
+            (joined, set_working_dir(&dirs(&[base.to_str().unwrap(), "nowhere"])).is_err())
+        });
+        fs::remove_dir_all(&base).unwrap();
+        assert_eq!(joined, base.join("a/b"));
+        assert!(missing);
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_the_working_dir() {
+        let dir = std::env::temp_dir();
+        in_working_dir(&dir, || {
+            assert_eq!(resolve(Path::new("tasks.md")), dir.join("tasks.md"));
+            assert_eq!(resolve(Path::new("/elsewhere/tasks.md")), Path::new("/elsewhere/tasks.md"));
+        });
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::BTreeMap, ffi::OsString};
+use anyhow::{Result, anyhow};
+
+/// Global options that take a value, skipped when looking for the command.
+const GLOBAL_VALUES: &[&str] = &["--project", "-C"];
+
+/// Rewrites `td ls --query <name>` and `td q <name>` into `td ls` followed by
+/// the flags saved under the name in `[queries]`. The saved flags go first so
+/// the ones given on the command line override them. Returns `None` when no
+/// query is used, without loading the queries.
+pub fn expand(args: &[OsString], queries: impl FnOnce() -> Result<BTreeMap<String, String>>) -> Result<Option<Vec<OsString>>> {
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        if GLOBAL_VALUES.iter().any(|option| arg == *option) {
+            position += 2;
+        } else if arg.to_string_lossy().starts_with('-') {
+            position += 1;
+        } else {
+            break;
+        }
+    }
+    let Some(command) = args.get(position) else {
+        return Ok(None);
+    };
+
+    let mut rest: Vec<OsString> = args[position + 1..].to_vec();
+    let name = if command == "q" {
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        rest.remove(0)
+    } else if command == "ls" {
+        let end = rest.iter().position(|arg| arg == "--").unwrap_or(rest.len());
+        let Some(i) = rest[..end].iter().position(|arg| arg == "--query" || arg.to_string_lossy().starts_with("--query=")) else {
+            return Ok(None);
+        };
+        let flag = rest.remove(i).to_string_lossy().to_string();
+        match flag.strip_prefix("--query=") {
+            Some(name) => OsString::from(name),
+            None if i < rest.len() => rest.remove(i),
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+
+    let name = name.to_string_lossy();
+    let mut expanded = args[..position].to_vec();
+    expanded.push(OsString::from("ls"));
+    expanded.extend(split(lookup(&queries()?, &name)?)?.into_iter().map(OsString::from));
+    expanded.extend(rest);
+    Ok(Some(expanded))
+}
+
+/// The flags saved under `name`, suggesting similar names when there is none.
+fn lookup<'a>(queries: &'a BTreeMap<String, String>, name: &str) -> Result<&'a str> {
+    if let Some(query) = queries.get(name) {
+        return Ok(query);
+    }
+    let close: Vec<&str> = queries.keys()
+        .filter(|candidate| candidate.starts_with(name) || distance(candidate, name) <= (name.chars().count() / 3).max(2))
+        .map(String::as_str)
+        .collect();
+    match close.as_slice() {
+        [] if queries.is_empty() => Err(anyhow!("There is no query '{}', none are defined in [queries] of the config", name)),
+        [] => Err(anyhow!("There is no query '{}', `td queries` lists the defined ones", name)),
+        [one] => Err(anyhow!("There is no query '{}', did you mean '{}'?", name, one)),
+        many => Err(anyhow!("There is no query '{}', did you mean one of: {}?", name, many.join(", "))),
+    }
+}
+
+/// The number of single character edits turning `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Splits a query into arguments at whitespace, keeping quoted parts
+/// together like a shell does.
+pub fn split(query: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in query.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.get_or_insert_default().push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (None, c) => current.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("Unclosed quote in the query '{}'", query));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn queries() -> Result<BTreeMap<String, String>> {
+        Ok(BTreeMap::from([("other".to_string(), "--project other -C sub --sort due".to_string())]))
+    }
+
+    #[test]
+    fn expansion_keeps_saved_global_flags() {
+        assert_eq!(expand(&args(&["td", "q", "other", "--reverse"]), queries).unwrap(), Some(args(&["td", "ls", "--project", "other", "-C", "sub", "--sort", "due", "--reverse"])));
+        assert_eq!(expand(&args(&["td", "-C", "x", "ls", "--query=other"]), queries).unwrap(), Some(args(&["td", "-C", "x", "ls", "--project", "other", "-C", "sub", "--sort", "due"])));
+    }
+
+    #[test]
+    fn queries_are_only_loaded_when_used() {
+        let unused = || -> Result<BTreeMap<String, String>> { panic!("the queries were loaded") };
+        assert_eq!(expand(&args(&["td", "ls", "--sort", "due"]), unused).unwrap(), None);
+    }
+
+    #[test]
+    fn unknown_queries_suggest_close_names() {
+        let error = expand(&args(&["td", "q", "othr"]), queries).unwrap_err();
+        assert_eq!(error.to_string(), "There is no query 'othr', did you mean 'other'?");
+    }
+}
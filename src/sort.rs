@@ -0,0 +1,136 @@
+use std::{cmp::Ordering, collections::HashMap};
+use uuid::Uuid;
+
+use crate::task::TaskMetadata;
+use crate::urgency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Created,
+    Title,
+    Status,
+    /// High to low, unprioritized tasks last
+    Priority,
+    /// Overdue first, then soonest, undated tasks last
+    Due,
+    /// Most urgent first, see the `[urgency]` config
+    Urgency,
+}
+
+/// Sorts tasks by `key`, falling back to `created_at` and then the id so the
+/// order is total. Tasks without a value for the key always end up last, even
+/// when `reverse` is set; reversing only flips the order within each group.
+pub fn sort_tasks<T>(tasks: &mut [T], key: SortKey, reverse: bool, metadata_of: impl Fn(&T) -> &TaskMetadata) {
+    if key == SortKey::Urgency {
+        let scores = urgency::scores(tasks.iter().map(&metadata_of));
+        return sort_by_urgency(tasks, &scores, reverse, metadata_of);
+    }
+    tasks.sort_by(|a, b| {
+        let (a, b) = (metadata_of(a), metadata_of(b));
+        let missing = has_value(a, key).cmp(&has_value(b, key)).reverse();
+        if missing != Ordering::Equal {
+            return missing;
+        }
+        let ordering = compare(a, b, key)
+            .then_with(|| a.created_at.cmp(&b.created_at))
+            .then_with(|| a.id.cmp(&b.id));
+        if reverse { ordering.reverse() } else { ordering }
+    });
+}
+
+/// Sorts tasks by the urgency in `scores`, most urgent first, falling back
+/// to `created_at` and the id. Used directly when the scores come from more
+/// tasks than are sorted, so blockers that are not listed still count.
+pub fn sort_by_urgency<T>(tasks: &mut [T], scores: &HashMap<Uuid, f64>, reverse: bool, metadata_of: impl Fn(&T) -> &TaskMetadata) {
+    let score = |meta: &TaskMetadata| scores.get(&meta.id).copied().unwrap_or_default();
+    tasks.sort_by(|a, b| {
+        let (a, b) = (metadata_of(a), metadata_of(b));
+        let ordering = score(b).total_cmp(&score(a))
+            .then_with(|| a.created_at.cmp(&b.created_at))
+            .then_with(|| a.id.cmp(&b.id));
+        if reverse { ordering.reverse() } else { ordering }
+    });
+}
+
+fn has_value(meta: &TaskMetadata, key: SortKey) -> bool {
+    match key {
+        SortKey::Priority => meta.priority.is_some(),
+        SortKey::Due => meta.due.is_some(),
+        SortKey::Created | SortKey::Title | SortKey::Status | SortKey::Urgency => true,
+    }
+}
+
+fn compare(a: &TaskMetadata, b: &TaskMetadata, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Created | SortKey::Urgency => Ordering::Equal,
+        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        SortKey::Status => a.status.cmp(&b.status),
+        SortKey::Priority => b.priority.cmp(&a.priority),
+        SortKey::Due => a.due.cmp(&b.due),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use crate::task::{Priority, Task};
+
+    fn created(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap()
+    }
+
+    /// Tasks named after the order they were created in, with and without
+    /// priorities and due dates.
+    fn fixtures() -> Vec<TaskMetadata> {
+        let now = Utc::now();
+        let task = |day: u32, title: &str, priority: Option<Priority>, due: Option<DateTime<Utc>>| {
+            let mut meta = Task::new(title).metadata;
+            meta.created_at = created(day);
+            meta.priority = priority;
+            meta.due = due;
+            meta
+        };
+        vec![
+            task(1, "a", None, Some(now + Duration::days(10))),
+            task(2, "b", Some(Priority::Low), None),
+            task(3, "c", Some(Priority::High), Some(now - Duration::days(2))),
+            task(4, "d", None, None),
+            task(5, "e", Some(Priority::Medium), Some(now + Duration::days(1))),
+            task(6, "f", Some(Priority::High), None),
+        ]
+    }
+
+    fn sorted(key: SortKey, reverse: bool) -> Vec<String> {
+        let mut tasks = fixtures();
+        sort_tasks(&mut tasks, key, reverse, |meta| meta);
+        tasks.into_iter().map(|meta| meta.title).collect()
+    }
+
+    #[test]
+    fn priority_puts_unprioritized_last() {
+        assert_eq!(sorted(SortKey::Priority, false), ["c", "f", "e", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn reversed_priority_keeps_unprioritized_last() {
+        assert_eq!(sorted(SortKey::Priority, true), ["b", "e", "f", "c", "d", "a"]);
+    }
+
+    #[test]
+    fn due_puts_overdue_first_and_undated_last() {
+        assert_eq!(sorted(SortKey::Due, false), ["c", "e", "a", "b", "d", "f"]);
+    }
+
+    #[test]
+    fn reversed_due_keeps_undated_last() {
+        assert_eq!(sorted(SortKey::Due, true), ["a", "e", "c", "f", "d", "b"]);
+    }
+
+    #[test]
+    fn created_is_oldest_first() {
+        assert_eq!(sorted(SortKey::Created, false), ["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(sorted(SortKey::Created, true), ["f", "e", "d", "c", "b", "a"]);
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::normalize;
+
+/// The longest tag td writes, in characters.
+pub const MAX_LEN: usize = 40;
+
+/// The form every tag is written in: trimmed, with runs of whitespace inside
+/// turned into one `-` and lowercased when `lowercase_tags` is set. Empty
+/// tags, overly long ones and those with a comma, which separates tags on the
+/// command line, are refused.
+pub fn normalize(input: &str) -> Result<String> {
+    let mut tag = input.split_whitespace().collect::<Vec<_>>().join("-");
+    if config::get().lowercase_tags {
+        tag = tag.to_lowercase();
+    }
+    if tag.is_empty() {
+        return Err(anyhow!("Invalid tag '{}': tags can not be empty", input));
+    }
+    if tag.contains(',') {
+        return Err(anyhow!("Invalid tag '{}': tags can not contain ','", input));
+    }
+    if tag.chars().count() > MAX_LEN {
+        return Err(anyhow!("Invalid tag '{}': tags can be at most {} characters long", input, MAX_LEN));
+    }
+    Ok(tag)
+}
+
+/// Separates the levels of a hierarchical tag like `area/backend`.
+pub const SEPARATOR: char = '/';
+
+/// Whether `tag` is the tag a filter asks for. Asking for a tag also matches
+/// the ones below it, so `area` matches `area/backend`; a trailing `/` as in
+/// `area/`, or `exact`, only matches the tag itself.
+pub fn matches(tag: &str, wanted: &str, case_sensitive: bool, exact: bool) -> bool {
+    let (tag, wanted) = (normalize::key(tag, case_sensitive), normalize::key(wanted, case_sensitive));
+    match wanted.strip_suffix(SEPARATOR) {
+        Some(wanted) => tag == wanted,
+        None if exact => tag == wanted,
+        None => tag == wanted || tag.strip_prefix(wanted.as_str()).is_some_and(|rest| rest.starts_with(SEPARATOR)),
+    }
+}
+
+/// The tag one level up, `area` for `area/backend`.
+pub fn parent(tag: &str) -> Option<&str> {
+    tag.rsplit_once(SEPARATOR).map(|(parent, _)| parent).filter(|parent| !parent.is_empty())
+}
+
+/// Normalizes the tags of a comma separated list like `bug, ui`.
+pub fn parse_list(input: &str) -> Result<Vec<String>> {
+    input.split(',')
+        .map(|tag| normalize(tag).map_err(|e| if input.contains(',') { anyhow!("{} (in '{}')", e, input) } else { e }))
+        .collect()
+}
+
+/// Why a tag already in a file is not in the normalized form.
+pub fn problem(tag: &str) -> Option<String> {
+    match normalize(tag) {
+        Ok(normalized) if normalized == tag => None,
+        Ok(normalized) => Some(format!("'{}' should be written '{}'", tag, normalized)),
+        Err(e) => Some(e.to_string()),
+    }
+}
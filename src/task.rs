@@ -0,0 +1,386 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+
+use crate::config;
+use crate::dates;
+use crate::tags;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    TODO,
+    DOING,
+    DONE
+}
+
+/// What a task stands for, when it is more than a plain task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    /// A larger piece of work that other tasks are filed under
+    Epic,
+}
+
+/// Statuses found in hand written files, read with a warning.
+const STATUS_SYNONYMS: &[(&str, TaskStatus)] = &[
+    ("in-progress", TaskStatus::DOING),
+    ("wip", TaskStatus::DOING),
+    ("complete", TaskStatus::DONE),
+];
+
+impl TaskStatus {
+    /// Reads a status in any casing, or one of the synonyms. The flag tells
+    /// whether it was written in the lowercase form td writes itself.
+    pub fn parse(text: &str) -> Option<(TaskStatus, bool)> {
+        let lower = text.to_lowercase();
+        let status = match lower.as_str() {
+            "todo" => TaskStatus::TODO,
+            "doing" => TaskStatus::DOING,
+            "done" => TaskStatus::DONE,
+            _ => STATUS_SYNONYMS.iter().find(|(synonym, _)| *synonym == lower)?.1,
+        };
+        Some((status, lower == text && !STATUS_SYNONYMS.iter().any(|(synonym, _)| *synonym == lower)))
+    }
+}
+
+impl<'de> Deserialize<'de> for TaskStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        match TaskStatus::parse(&text) {
+            Some((status, _)) => {
+                if STATUS_SYNONYMS.iter().any(|(synonym, _)| synonym.eq_ignore_ascii_case(&text)) {
+                    eprintln!("warning: read status '{}' as {:?}, `td migrate --status` rewrites it", text, status);
+                }
+                Ok(status)
+            }
+            None => Err(serde::de::Error::custom(unknown_status(&text))),
+        }
+    }
+}
+
+fn unknown_status(text: &str) -> String {
+    format!("Unknown status '{}', expected todo, doing or done", text)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskMetadata {
+    pub title: String,
+    pub status: TaskStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    pub id: Uuid,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// Tasks that have to be done before this one can start
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<Uuid>,
+    /// Tasks linked with `td relate`, which does not make either wait
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<Uuid>,
+    /// URLs of things the task is about, like a pull request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// Commit SHAs that implemented the task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<String>,
+    /// The git branch the task was filed under, see `td add --here`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TaskKind>,
+    /// The epic the task belongs to, see `td epic`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epic: Option<Uuid>,
+    /// The name of the milestone the task is planned for, see `td milestone`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub milestone: Option<String>,
+    /// Keys td does not know, like a hand written `sprint: 14`. They are kept
+    /// as they are and written back when the file is saved.
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// How the frontmatter of a task file is written, fenced by `---` for YAML
+/// or `+++` for TOML. Files in either style are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Frontmatter {
+    #[default]
+    Yaml,
+    Toml,
+}
+
+impl Frontmatter {
+    fn fence(self) -> &'static str {
+        match self {
+            Frontmatter::Yaml => "---",
+            Frontmatter::Toml => "+++",
+        }
+    }
+
+    /// The style of a task file, from its opening fence.
+    pub fn detect(content: &str) -> Option<Frontmatter> {
+        [Frontmatter::Yaml, Frontmatter::Toml].into_iter().find(|style| content.starts_with(&format!("{}\n", style.fence())))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub metadata: TaskMetadata,
+    pub description: String
+}
+
+impl Task {
+    // Inherent rather than `FromStr`, so callers need no trait import.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> Result<Self> {
+        match Task::from_str_lenient(content)? {
+            (task, None) => Ok(task),
+            (_, Some(problem)) => Err(anyhow!(problem)),
+        }
+    }
+
+    /// Reads a task even when its status is unknown, so listings can still
+    /// show it. The task is then read as TODO and the problem returned with
+    /// it; such a task must not be written back.
+    pub fn from_str_lenient(content: &str) -> Result<(Self, Option<String>)> {
+        let (style, frontmatter, description) = Task::split(content)?;
+        let mut value = match style {
+            Frontmatter::Yaml => serde_yaml::from_str(frontmatter)?,
+            Frontmatter::Toml => config::parse_toml(frontmatter)?,
+        };
+        let mut problem = None;
+        if let Some(status) = value.get_mut("status")
+            && let Value::String(text) = status
+            && TaskStatus::parse(text).is_none()
+        {
+            problem = Some(unknown_status(text));
+            *status = Value::String("todo".to_string());
+        }
+        let task = Task { metadata: serde_yaml::from_value(value)?, description: description.to_string() };
+        Ok((task, problem))
+    }
+
+    /// The style, frontmatter and description of a task file.
+    pub fn split(content: &str) -> Result<(Frontmatter, &str, &str)> {
+        let Some(style) = Frontmatter::detect(content) else {
+            return Err(anyhow!("The task file does not start with '---' or '+++' followed by a new line"))
+        };
+        let fence = format!("{}\n", style.fence());
+        let end_of_frontmatter = content[4..]
+            .find(&fence)
+            .ok_or_else(|| anyhow::anyhow!("Missing closing '{}'", style.fence()))?;
+        let frontmatter = &content[4..4 + end_of_frontmatter];
+        let description = &content[4 + end_of_frontmatter + 4..];
+        Ok((style, frontmatter, description))
+    }
+
+    /// The status exactly as the file spells it, to find the ones td did not
+    /// write itself.
+    pub fn raw_status(content: &str) -> Option<String> {
+        let (style, frontmatter, _) = Task::split(content).ok()?;
+        let value: Value = match style {
+            Frontmatter::Yaml => serde_yaml::from_str(frontmatter).ok()?,
+            Frontmatter::Toml => config::parse_toml(frontmatter).ok()?,
+        };
+        value.get("status")?.as_str().map(str::to_string)
+    }
+
+    pub fn to_string_as(&self, style: Frontmatter) -> Result<String> {
+        let frontmatter = match style {
+            Frontmatter::Yaml => serde_yaml::to_string(&self.metadata)?,
+            Frontmatter::Toml => config::to_toml(&serde_yaml::to_value(&self.metadata)?)?,
+        };
+
+        // Combine the parts into the final file format.
+        Ok(format!(
+            "{fence}\n{}{fence}\n{}",
+            frontmatter, self.description, fence = style.fence()
+        ))
+    }
+
+    pub fn new(title: &str) -> Self {
+        Task {
+            metadata: TaskMetadata {
+                title: title.to_string(),
+                status: TaskStatus::TODO,
+                created_at: Utc::now(),
+                id: Uuid::new_v4(),
+                tags: Vec::new(),
+                updated_at: None,
+                priority: None,
+                due: None,
+                scheduled: None,
+                parent: None,
+                snoozed_until: None,
+                blocked_by: Vec::new(),
+                related: Vec::new(),
+                links: Vec::new(),
+                commits: Vec::new(),
+                branch: None,
+                kind: None,
+                epic: None,
+                milestone: None,
+                extra: Mapping::new(),
+            },
+            description: String::new()
+        }
+    }
+
+    pub fn short_id(&self) -> String {
+        self.metadata.short_id()
+    }
+}
+
+impl TaskMetadata {
+    /// Adds tags in their normalized form, leaving out those the task
+    /// already has. Fails on the first invalid one.
+    pub fn add_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        for tag in tags {
+            let tag = tags::normalize(tag)?;
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        Ok(())
+    }
+
+    /// The first eight characters of the id, used when listing tasks.
+    pub fn short_id(&self) -> String {
+        self.id.to_string()[..8].to_string()
+    }
+
+    pub fn is_epic(&self) -> bool {
+        self.kind == Some(TaskKind::Epic)
+    }
+
+    /// Whether the task is snoozed and should stay out of listings at `now`.
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    /// When the task was last changed, its creation for one never edited.
+    pub fn last_touched(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.created_at)
+    }
+
+    /// Whether the task is open and went more than `days` days without a
+    /// change before `now`.
+    pub fn is_stale(&self, days: u64, now: DateTime<Utc>) -> bool {
+        self.status != TaskStatus::DONE && now - self.last_touched() > chrono::Duration::days(days as i64)
+    }
+
+    /// Whether any of the tasks blocking this one is still open. `status_of`
+    /// looks up a task in the project; blockers that no longer exist are ignored.
+    pub fn is_blocked(&self, status_of: impl Fn(Uuid) -> Option<TaskStatus>) -> bool {
+        self.blocked_by.iter().any(|id| status_of(*id).is_some_and(|status| status != TaskStatus::DONE))
+    }
+
+    /// Whether the task is open, awake and due before `days` from today have
+    /// passed, overdue tasks included.
+    pub fn due_within(&self, days: i64) -> bool {
+        self.status != TaskStatus::DONE
+            && !self.is_snoozed(Utc::now())
+            && self.due.is_some_and(|due| (dates::day_of(due) - dates::today()).num_days() <= days)
+    }
+
+    /// Whether the task is still TODO although its scheduled start has passed.
+    pub fn start_overdue(&self) -> bool {
+        self.status == TaskStatus::TODO
+            && self.scheduled.is_some_and(|scheduled| dates::day_of(scheduled) < dates::today())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// A task with every kind of field set: timestamps, enums, ids and lists.
+    fn full_task() -> Task {
+        let mut task = Task::new("Ship the \"new\" parser: part 2");
+        let at = Utc.with_ymd_and_hms(2024, 3, 10, 8, 30, 15).unwrap() + chrono::Duration::milliseconds(250);
+        let meta = &mut task.metadata;
+        meta.status = TaskStatus::DOING;
+        meta.created_at = at;
+        meta.updated_at = Some(at + chrono::Duration::hours(2));
+        meta.due = Some(Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap());
+        meta.tags = vec!["parser".to_string(), "area/core".to_string()];
+        meta.priority = Some(Priority::High);
+        meta.kind = Some(TaskKind::Epic);
+        meta.parent = Some(Uuid::new_v4());
+        meta.blocked_by = vec![Uuid::new_v4(), Uuid::new_v4()];
+        meta.links = vec!["https://example.com/pr/1".to_string()];
+        meta.milestone = Some("v1.0".to_string());
+        task.description = "Notes\n\n- [ ] one\n+++\n---\n".to_string();
+        task
+    }
+
+    fn same(a: &Task, b: &Task) {
+        assert_eq!(serde_yaml::to_value(&a.metadata).unwrap(), serde_yaml::to_value(&b.metadata).unwrap());
+        assert_eq!(a.description, b.description);
+    }
+
+    #[test]
+    fn both_frontmatter_styles_round_trip() {
+        let task = full_task();
+        for style in [Frontmatter::Yaml, Frontmatter::Toml] {
+            let text = task.to_string_as(style).unwrap();
+            assert!(text.starts_with(&format!("{}\n", style.fence())), "{}", text);
+            assert_eq!(Frontmatter::detect(&text), Some(style));
+            same(&Task::from_str(&text).unwrap(), &task);
+        }
+        let converted = Task::from_str(&task.to_string_as(Frontmatter::Toml).unwrap()).unwrap().to_string_as(Frontmatter::Yaml).unwrap();
+        assert_eq!(converted, task.to_string_as(Frontmatter::Yaml).unwrap());
+    }
+
+    #[test]
+    fn hand_written_toml_reads_like_yaml() {
+        let id = Uuid::new_v4();
+        let toml = format!("+++\ntitle = \"Fix it\"\nstatus = \"DOING\"\ncreated_at = 2024-03-10T08:30:00Z\nid = \"{}\"\npriority = \"high\"\n+++\nbody\n", id);
+        let yaml = format!("---\ntitle: Fix it\nstatus: DOING\ncreated_at: 2024-03-10T08:30:00Z\nid: {}\npriority: high\n---\nbody\n", id);
+        let (from_toml, from_yaml) = (Task::from_str(&toml).unwrap(), Task::from_str(&yaml).unwrap());
+        same(&from_toml, &from_yaml);
+        assert_eq!(from_toml.metadata.created_at, Utc.with_ymd_and_hms(2024, 3, 10, 8, 30, 0).unwrap());
+        assert_eq!(from_toml.metadata.priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn unknown_keys_survive_a_rewrite() {
+        let extra = "sprint: 14\njira: ABC-123\nestimate: 2.5\nbillable: false\nreview:\n  by: ana\n  rounds:\n  - 1\n  - 2\n";
+        let text = format!("---\ntitle: Fix it\nstatus: TODO\ncreated_at: 2024-03-10T08:30:00Z\nid: {}\n{}---\nbody\n", Uuid::new_v4(), extra);
+        let mut task = Task::from_str(&text).unwrap();
+        let expected: Mapping = serde_yaml::from_str(extra).unwrap();
+        assert_eq!(task.metadata.extra, expected);
+
+        task.metadata.status = TaskStatus::DONE;
+        let yaml = task.to_string_as(Frontmatter::Yaml).unwrap();
+        assert!(yaml.contains("jira: ABC-123\n"), "{}", yaml);
+        assert_eq!(Task::from_str(&yaml).unwrap().metadata.extra, expected);
+        let toml = task.to_string_as(Frontmatter::Toml).unwrap();
+        assert_eq!(Task::from_str(&toml).unwrap().metadata.extra, expected);
+    }
+}
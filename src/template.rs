@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use anyhow::{Result, anyhow};
+
+use crate::dates;
+use crate::index::IndexEntry;
+use crate::width;
+
+/// Every placeholder a format string can use.
+pub const FIELDS: &[&str] = &[
+    "id", "short_id", "title", "status", "created_at", "updated_at", "tags", "priority",
+    "due", "scheduled", "parent", "snoozed_until", "blocked_by", "branch", "age", "due_in", "checklist",
+    "commits", "epic", "kind", "milestone", "related", "links",
+];
+
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Field { name: String, align: char, width: Option<usize> },
+}
+
+/// A parsed `--format` string like `{short_id}  {status:>5}  {title:<40}`.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses a format string, rejecting unknown placeholders before anything
+    /// is printed. `{{` and `}}` produce literal braces.
+    pub fn parse(format: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        spec.push(c);
+                    }
+                    if !closed {
+                        return Err(anyhow!("Unclosed '{{{}' in the format string", spec));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_field(&spec)?);
+                }
+                '}' => return Err(anyhow!("Unmatched '}}' in the format string, use '}}}}' for a literal brace")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, task: &IndexEntry) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field { name, align, width } => {
+                    let value = field(task, name);
+                    let Some(width) = *width else {
+                        out.push_str(&value);
+                        continue;
+                    };
+                    let padding = width.saturating_sub(width::display_width(&value));
+                    let (left, right) = match align {
+                        '>' => (padding, 0),
+                        '^' => (padding / 2, padding - padding / 2),
+                        _ => (0, padding),
+                    };
+                    out.push_str(&" ".repeat(left));
+                    out.push_str(&value);
+                    out.push_str(&" ".repeat(right));
+                }
+            }
+        }
+        out
+    }
+}
+
+fn parse_field(spec: &str) -> Result<Part> {
+    let (name, format) = match spec.split_once(':') {
+        Some((name, format)) => (name.trim(), Some(format)),
+        None => (spec.trim(), None),
+    };
+    if !FIELDS.contains(&name) {
+        return Err(anyhow!("Unknown placeholder '{{{}}}', valid names are: {}", name, FIELDS.join(", ")));
+    }
+    let (align, width) = match format {
+        None => ('<', None),
+        Some(format) => {
+            let (align, digits) = match format.chars().next() {
+                Some(c @ ('<' | '>' | '^')) => (c, &format[1..]),
+                _ => ('<', format),
+            };
+            let width = digits.parse::<usize>()
+                .map_err(|_| anyhow!("Invalid width '{}' for '{{{}}}', expected something like {{{}:<20}}", format, name, name))?;
+            (align, Some(width))
+        }
+    };
+    Ok(Part::Field { name: name.to_string(), align, width })
+}
+
+fn timestamp(time: Option<DateTime<Utc>>) -> String {
+    time.map(|t| t.to_rfc3339()).unwrap_or_default()
+}
+
+fn day(time: Option<DateTime<Utc>>) -> String {
+    time.map(dates::format_day).unwrap_or_default()
+}
+
+fn field(task: &IndexEntry, name: &str) -> String {
+    let meta = &task.metadata;
+    match name {
+        "id" => meta.id.to_string(),
+        "short_id" => meta.short_id(),
+        "title" => meta.title.clone(),
+        "status" => format!("{:?}", meta.status),
+        "created_at" => timestamp(Some(meta.created_at)),
+        "updated_at" => timestamp(meta.updated_at),
+        "tags" => meta.tags.join(", "),
+        "priority" => meta.priority.map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_default(),
+        "due" => day(meta.due),
+        "scheduled" => day(meta.scheduled),
+        "parent" => meta.parent.map(|p| p.to_string()).unwrap_or_default(),
+        "snoozed_until" => timestamp(meta.snoozed_until),
+        "blocked_by" => meta.blocked_by.iter().map(|id| id.to_string()[..8].to_string()).collect::<Vec<_>>().join(", "),
+        "branch" => meta.branch.clone().unwrap_or_default(),
+        "age" => dates::age(meta.created_at, Utc::now()),
+        "due_in" => meta.due.map(dates::due_in).unwrap_or_default(),
+        "checklist" => task.checklist.fraction(),
+        "commits" => meta.commits.join(", "),
+        "epic" => meta.epic.map(|epic| epic.to_string()).unwrap_or_default(),
+        "kind" => meta.kind.map(|kind| format!("{:?}", kind).to_lowercase()).unwrap_or_default(),
+        "milestone" => meta.milestone.clone().unwrap_or_default(),
+        "related" => meta.related.iter().map(|id| id.to_string()[..8].to_string()).collect::<Vec<_>>().join(", "),
+        // Spaces, as a URL may contain commas.
+        "links" => meta.links.join(" "),
+        _ => unreachable!("placeholders are validated when the template is parsed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Task, TaskKind};
+
+    fn render(format: &str, change: impl FnOnce(&mut Task)) -> String {
+        let mut task = Task::new("a task");
+        change(&mut task);
+        let entry = IndexEntry { path: "a.td".into(), metadata: task.metadata, checklist: Default::default(), problem: None };
+        Template::parse(format).unwrap().render(&entry)
+    }
+
+    #[test]
+    fn commits_are_listed() {
+        assert_eq!(render("[{commits}]", |_| {}), "[]");
+        assert_eq!(render("{commits}", |task| task.metadata.commits = vec!["abc1234".to_string(), "def5678".to_string()]), "abc1234, def5678");
+    }
+
+    #[test]
+    fn epics_show_their_kind_and_members_their_epic() {
+        let epic = Task::new("an epic").metadata.id;
+        assert_eq!(render("{kind}|{epic}", |_| {}), "|");
+        assert_eq!(render("{kind}", |task| task.metadata.kind = Some(TaskKind::Epic)), "epic");
+        assert_eq!(render("{epic}", |task| task.metadata.epic = Some(epic)), epic.to_string());
+    }
+
+    #[test]
+    fn milestones_are_shown_by_name() {
+        assert_eq!(render("[{milestone}]", |_| {}), "[]");
+        assert_eq!(render("{milestone:>6}", |task| task.metadata.milestone = Some("v1.0".to_string())), "  v1.0");
+    }
+
+    #[test]
+    fn related_tasks_are_shown_by_short_id() {
+        let (a, b) = (Task::new("a").metadata, Task::new("b").metadata);
+        assert_eq!(render("[{related}]", |_| {}), "[]");
+        assert_eq!(render("{related}", |task| task.metadata.related = vec![a.id, b.id]), format!("{}, {}", a.short_id(), b.short_id()));
+    }
+
+    #[test]
+    fn links_are_separated_by_spaces() {
+        assert_eq!(render("[{links}]", |_| {}), "[]");
+        let links = vec!["https://example.com/a".to_string(), "https://example.com/b?x=1,2".to_string()];
+        assert_eq!(render("{links}", |task| task.metadata.links = links), "https://example.com/a https://example.com/b?x=1,2");
+    }
+}
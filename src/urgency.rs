@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::{self, UrgencyWeights};
+use crate::normalize;
+use crate::task::{Priority, TaskMetadata, TaskStatus};
+
+/// How urgent each of `tasks` is, by id. Blockers are looked up among the
+/// same tasks, so pass the whole project.
+pub fn scores<'a>(tasks: impl IntoIterator<Item = &'a TaskMetadata>) -> HashMap<Uuid, f64> {
+    let tasks: Vec<&TaskMetadata> = tasks.into_iter().collect();
+    let statuses: HashMap<Uuid, TaskStatus> = tasks.iter().map(|meta| (meta.id, meta.status)).collect();
+    let weights = &config::get().urgency;
+    let now = Utc::now();
+    tasks.iter()
+        .map(|meta| (meta.id, score(meta, meta.is_blocked(|id| statuses.get(&id).copied()), weights, now)))
+        .collect()
+}
+
+/// The urgency of one task: the sum of the weights that apply to it, some
+/// scaled by how close the deadline is and how old the task is. Finished
+/// tasks have none.
+pub fn score(meta: &TaskMetadata, blocked: bool, weights: &UrgencyWeights, now: DateTime<Utc>) -> f64 {
+    if meta.status == TaskStatus::DONE {
+        return 0.0;
+    }
+    let mut urgency = match meta.priority {
+        Some(Priority::High) => weights.high,
+        Some(Priority::Medium) => weights.medium,
+        Some(Priority::Low) => weights.low,
+        None => 0.0,
+    };
+    if meta.status == TaskStatus::DOING {
+        urgency += weights.doing;
+    }
+    if let Some(due) = meta.due {
+        let overdue_days = (now - due).num_seconds() as f64 / 86_400.0;
+        let factor = if overdue_days >= 7.0 {
+            1.0
+        } else if overdue_days >= -14.0 {
+            (overdue_days + 14.0) * 0.8 / 21.0 + 0.2
+        } else {
+            0.2
+        };
+        urgency += weights.due * factor;
+    }
+    if weights.age_max_days > 0.0 {
+        let age_days = (now - meta.created_at).num_seconds().max(0) as f64 / 86_400.0;
+        urgency += weights.age * (age_days / weights.age_max_days).min(1.0);
+    }
+    if blocked {
+        urgency += weights.blocked;
+    }
+    for (tag, weight) in &weights.tag {
+        let tag = normalize::normalize(tag);
+        if meta.tags.iter().any(|t| normalize::normalize(t) == tag) {
+            urgency += weight;
+        }
+    }
+    urgency
+}
+
+/// How likely each task with these urgencies is to be picked, relative to the
+/// others. The urgency counts on top of a base of one, so a task nothing makes
+/// urgent still has a chance and one below zero never goes negative.
+pub fn pick_weights(scores: &[f64]) -> Vec<f64> {
+    scores.iter().map(|score| 1.0 + score.max(0.0)).collect()
+}
+
+/// The index of the entry `rng` lands on when each is as likely as its
+/// weight, `None` for no entries.
+pub fn pick_weighted(weights: &[f64], rng: &mut Rng) -> Option<usize> {
+    let total: f64 = weights.iter().sum();
+    if weights.is_empty() || total <= 0.0 {
+        return None;
+    }
+    let mut target = rng.next_f64() * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return Some(i);
+        }
+        target -= weight;
+    }
+    Some(weights.len() - 1)
+}
+
+/// A small splitmix64 generator, enough to pick tasks and the same for the
+/// same seed everywhere.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    /// Seeded from the clock and the process id.
+    pub fn from_time() -> Self {
+        let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        Rng(nanos ^ (std::process::id() as u64).rotate_left(32))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::task::Task;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn task(title: &str, priority: Option<Priority>, edit: impl FnOnce(&mut TaskMetadata)) -> TaskMetadata {
+        let mut meta = Task::new(title).metadata;
+        meta.created_at = now();
+        meta.priority = priority;
+        edit(&mut meta);
+        meta
+    }
+
+    #[test]
+    fn representative_tasks_rank_in_a_deliberate_order() {
+        let mut weights = UrgencyWeights::default();
+        weights.tag.insert("Bug".to_string(), 2.0);
+        let tasks = [
+            (task("overdue", None, |meta| meta.due = Some(now() - Duration::days(8))), false),
+            (task("doing high", Some(Priority::High), |meta| meta.status = TaskStatus::DOING), false),
+            (task("high", Some(Priority::High), |_| {}), false),
+            (task("year old medium", Some(Priority::Medium), |meta| meta.created_at = now() - Duration::days(400)), false),
+            (task("medium", Some(Priority::Medium), |_| {}), false),
+            (task("low bug", Some(Priority::Low), |meta| meta.tags = vec!["bug".to_string()]), false),
+            (task("due in two months", None, |meta| meta.due = Some(now() + Duration::days(60))), false),
+            (task("low", Some(Priority::Low), |_| {}), false),
+            (task("blocked high", Some(Priority::High), |_| {}), true),
+            (task("plain", None, |_| {}), false),
+        ];
+        let mut ranked: Vec<(&str, f64)> = tasks.iter()
+            .map(|(meta, blocked)| (meta.title.as_str(), score(meta, *blocked, &weights, now())))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let titles: Vec<&str> = ranked.iter().map(|(title, _)| *title).collect();
+        let expected: Vec<&str> = tasks.iter().map(|(meta, _)| meta.title.as_str()).collect();
+        assert_eq!(titles, expected, "scores: {:?}", ranked);
+
+        let done = task("done high", Some(Priority::High), |meta| meta.status = TaskStatus::DONE);
+        assert_eq!(score(&done, false, &weights, now()), 0.0);
+    }
+
+    #[test]
+    fn only_open_blockers_lower_the_score() {
+        let open = task("open", None, |_| {});
+        let done = task("done", None, |meta| meta.status = TaskStatus::DONE);
+        let waiting = task("waiting", Some(Priority::High), |meta| meta.blocked_by = vec![open.id]);
+        let unblocked = task("unblocked", Some(Priority::High), |meta| meta.blocked_by = vec![done.id]);
+        let scores = scores([&open, &done, &waiting, &unblocked]);
+        assert!(scores[&waiting.id] < scores[&unblocked.id]);
+    }
+
+    #[test]
+    fn a_seed_always_picks_the_same_task() {
+        let weights = pick_weights(&[0.0, 12.5, 3.9, -5.0, 6.0]);
+        let picks = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..20).map(|_| pick_weighted(&weights, &mut rng).unwrap()).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(42), picks(42));
+        assert_ne!(picks(42), picks(43));
+        // Pinned so a change to the generator or the walk over the weights
+        // is noticed: `td pick --seed` should pick the same task across releases.
+        assert_eq!(picks(7)[..5], [1, 0, 4, 2, 1]);
+    }
+
+    #[test]
+    fn picks_follow_the_weights() {
+        assert_eq!(pick_weighted(&[], &mut Rng::new(1)), None);
+        assert_eq!(pick_weighted(&[0.0, 0.0], &mut Rng::new(1)), None);
+        let mut rng = Rng::new(1);
+        assert!((0..100).all(|_| pick_weighted(&[0.0, 1.0, 0.0], &mut rng) == Some(1)));
+        let mut counts = [0; 2];
+        for _ in 0..10_000 {
+            counts[pick_weighted(&[1.0, 3.0], &mut rng).unwrap()] += 1;
+        }
+        assert!((7_000..8_000).contains(&counts[1]), "{:?}", counts);
+        // A task nothing makes urgent still has a chance, negative urgency counts as none.
+        assert_eq!(pick_weights(&[0.0, -5.0, 2.5]), vec![1.0, 1.0, 3.5]);
+    }
+}
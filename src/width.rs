@@ -0,0 +1,85 @@
+use std::io::IsTerminal;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::icons;
+
+/// The number of terminal cells the text occupies, two for CJK and emoji
+/// and none for combining marks.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Shortens text to at most `width` cells, ending it with the ellipsis of
+/// the icon set when anything was cut. Grapheme clusters are never split.
+pub fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let ellipsis = icons::get().ellipsis;
+    let room = width.saturating_sub(display_width(ellipsis));
+    let mut out = String::new();
+    let mut used = 0;
+    for cluster in text.graphemes(true) {
+        let w = cluster.width();
+        if used + w > room {
+            break;
+        }
+        used += w;
+        out.push_str(cluster);
+    }
+    if display_width(ellipsis) <= width {
+        out.push_str(ellipsis);
+    }
+    out
+}
+
+/// Pads text with spaces on the right to `width` cells.
+pub fn pad(text: &str, width: usize) -> String {
+    let w = display_width(text);
+    format!("{}{}", text, " ".repeat(width.saturating_sub(w)))
+}
+
+/// The width of the terminal stdout is connected to, or `None` when stdout
+/// is not a terminal and output should not be truncated.
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    // SAFETY: TIOCGWINSZ only writes into the winsize struct we pass it.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 {
+        return Some(size.ws_col as usize);
+    }
+    std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).or(Some(80))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_take_two_cells_and_stay_whole() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(display_width("\u{1F41B} bug"), 6);
+        assert_eq!(display_width(family), 2);
+        assert_eq!(truncate(&format!("{}{} fix", family, family), 6), format!("{}...", family));
+        assert_eq!(truncate("\u{1F41B}\u{1F41B}\u{1F41B}\u{1F41B}", 6), "\u{1F41B}...");
+    }
+
+    #[test]
+    fn cjk_takes_two_cells() {
+        assert_eq!(display_width("日本語のタスク"), 14);
+        assert_eq!(truncate("日本語のタスク", 8), "日本...");
+        assert_eq!(pad("日本", 6), "日本  ");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        let title = "Re\u{301}sume\u{301} update";
+        assert_eq!(display_width(title), 13);
+        assert_eq!(truncate(title, 5), "Re\u{301}...");
+        assert_eq!(truncate(title, 13), title);
+    }
+}